@@ -0,0 +1,204 @@
+//! A visual mode written outside the library: a disc that swells with the
+//! bass, added to a headless renderer next to the built-in views.
+//!
+//! ```sh
+//! cargo run --example custom_view -- pulse.png
+//! ```
+
+use audio_visualizer::visualizer::{AnalysisFrame, SceneFormat, Visualizer};
+use audio_visualizer::{Analyzer, Layout, LayoutParams, Renderer};
+
+const SHADER: &str = "
+struct Pulse {
+    radius: f32,
+    aspect: f32,
+};
+
+@group(0) @binding(0) var<uniform> pulse: Pulse;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) xy: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let x = f32((vertex_index << 1u) & 2u) * 2.0 - 1.0;
+    let y = f32(vertex_index & 2u) * 2.0 - 1.0;
+    var output: VertexOutput;
+    output.position = vec4<f32>(x, y, 0.0, 1.0);
+    output.xy = vec2<f32>(x * pulse.aspect, y);
+    return output;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    let edge = smoothstep(pulse.radius, pulse.radius - 0.02, length(input.xy));
+    return vec4<f32>(1.0, 0.4, 0.1, edge);
+}
+";
+
+/// Radius and width / height, as in `SHADER`.
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct Uniforms {
+    radius: f32,
+    aspect: f32,
+}
+
+struct Pulse {
+    aspect: f32,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    layout: wgpu::PipelineLayout,
+    shader: wgpu::ShaderModule,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl Pulse {
+    fn new(device: &wgpu::Device, scene: SceneFormat) -> Self {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Pulse"),
+            size: std::mem::size_of::<Uniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Pulse"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Pulse"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Pulse"),
+            bind_group_layouts: &[&bind_group_layout],
+            immediate_size: 0,
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Pulse"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let pipeline = create_pipeline(device, &layout, &shader, scene);
+
+        Self {
+            aspect: 1.0,
+            buffer,
+            bind_group,
+            layout,
+            shader,
+            pipeline,
+        }
+    }
+}
+
+impl Visualizer for Pulse {
+    fn name(&self) -> &str {
+        "pulse"
+    }
+
+    fn set_scene_format(&mut self, device: &wgpu::Device, scene: SceneFormat) {
+        self.pipeline = create_pipeline(device, &self.layout, &self.shader, scene);
+    }
+
+    fn resize(&mut self, _: &wgpu::Device, _: &wgpu::Queue, width: u32, height: u32) {
+        self.aspect = width as f32 / height as f32;
+    }
+
+    fn update(&mut self, _: &wgpu::Device, queue: &wgpu::Queue, frame: &AnalysisFrame) {
+        // Loudest of the lowest eighth of the bars
+        let bass = frame.bars[..frame.bars.len().div_ceil(8)]
+            .iter()
+            .copied()
+            .fold(0.0, f32::max);
+        let uniforms = Uniforms {
+            radius: 0.2 + 0.7 * (bass / frame.max_height).min(1.0),
+            aspect: self.aspect,
+        };
+        queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&uniforms));
+    }
+
+    fn render(&self, pass: &mut wgpu::RenderPass) {
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    scene: SceneFormat,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Pulse"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some("vs_main"),
+            buffers: &[],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: Some("fs_main"),
+            compilation_options: Default::default(),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: scene.format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: scene.sample_count,
+            ..Default::default()
+        },
+        multiview_mask: None,
+        cache: None,
+    })
+}
+
+fn main() {
+    let out = std::env::args().nth(1).unwrap_or("pulse.png".to_string());
+
+    let mut analyzer = Analyzer::default();
+    let mut renderer = pollster::block_on(Renderer::new_headless(
+        640,
+        360,
+        analyzer.num_bars() as u32,
+        Layout::Linear,
+        LayoutParams::default(),
+        false,
+    ))
+    .expect("no adapter");
+
+    let pulse = Pulse::new(renderer.device(), renderer.scene_format());
+    renderer.add_visualizer(Box::new(pulse));
+    renderer.set_view("pulse");
+    println!("Views: {}", renderer.views().collect::<Vec<_>>().join(", "));
+
+    // A low tone, so the bass bars (and the disc) are up
+    let tone: Vec<f32> = (0..analyzer.fft_size())
+        .map(|i| (i as f32 * 0.02).sin())
+        .collect();
+    renderer.render(analyzer.process(&tone, 0.0));
+    renderer.save_png(out.as_ref()).expect("write PNG");
+    println!("Wrote {out}");
+}
@@ -0,0 +1,123 @@
+//! A host winit app with the visualizer as one of its parts: it owns the
+//! window and event loop, and drives the analysis and renderer itself.
+//!
+//! ```sh
+//! cargo run --example embed -- song.wav   # or no file for the default input
+//! ```
+//!
+//! Space switches between the bars and the vectorscope.
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use audio_visualizer::{audio, Analyzer, Layout, LayoutParams, Renderer, Source};
+use winit::application::ApplicationHandler;
+use winit::event::{ElementState, KeyEvent, WindowEvent};
+use winit::event_loop::{ActiveEventLoop, EventLoop};
+use winit::keyboard::{Key, NamedKey};
+use winit::window::{Window, WindowAttributes, WindowId};
+
+#[derive(Default)]
+struct Host {
+    file: Option<String>,
+    window: Option<Arc<Window>>,
+    renderer: Option<Renderer>,
+    source: Option<Source>,
+    analyzer: Analyzer,
+    last_frame: Option<Instant>,
+    /// Seconds since the first frame.
+    clock: f32,
+}
+
+impl ApplicationHandler for Host {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.window.is_some() {
+            return;
+        }
+        let attrs = WindowAttributes::default().with_title("Embedded visualizer");
+        let window = Arc::new(event_loop.create_window(attrs).expect("create window"));
+
+        let mut renderer = pollster::block_on(Renderer::new(
+            window.clone(),
+            self.analyzer.num_bars() as u32,
+            Layout::Mirrored,
+            LayoutParams::default(),
+        ));
+        renderer.overlay.source = true;
+
+        let source = match &self.file {
+            Some(path) => Source::file(path),
+            None => audio::input_device(None).and_then(|device| Source::device(&device)),
+        };
+        let source = source.unwrap_or_else(|e| {
+            eprintln!("{e}");
+            std::process::exit(2);
+        });
+        renderer.set_bar_frequencies(self.analyzer.bar_frequencies());
+        renderer.set_source_info(source.info().name.clone());
+
+        self.renderer = Some(renderer);
+        self.source = Some(source);
+        self.window = Some(window);
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
+        let (Some(renderer), Some(source)) = (&mut self.renderer, &self.source) else {
+            return;
+        };
+        match event {
+            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::Resized(size) => renderer.resize(size),
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: Key::Named(NamedKey::Space),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => {
+                let view = match renderer.view() {
+                    "bars" => "vectorscope",
+                    _ => "bars",
+                };
+                renderer.set_view(view);
+            }
+            WindowEvent::RedrawRequested => {
+                let now = Instant::now();
+                let dt = self
+                    .last_frame
+                    .replace(now)
+                    .map_or(0.0, |last| (now - last).as_secs_f32());
+                self.clock += dt;
+
+                renderer.update_stereo(&source.drain_stereo());
+                let heights = match source.latest(self.analyzer.fft_size()) {
+                    Some(samples) => {
+                        renderer.update_waveform(&samples);
+                        self.analyzer.process(&samples, dt)
+                    }
+                    None => self.analyzer.values(),
+                };
+                renderer.set_time(self.clock, dt);
+                renderer.render(heights);
+            }
+            _ => {}
+        }
+    }
+
+    fn about_to_wait(&mut self, _: &ActiveEventLoop) {
+        if let Some(window) = &self.window {
+            window.request_redraw();
+        }
+    }
+}
+
+fn main() {
+    let mut host = Host {
+        file: std::env::args().nth(1),
+        ..Host::default()
+    };
+    let event_loop = EventLoop::new().expect("create event loop");
+    event_loop.run_app(&mut host).expect("event loop");
+}
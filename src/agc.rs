@@ -0,0 +1,199 @@
+use serde::{Deserialize, Serialize};
+
+// ---- Tuning knobs (change these to taste) ----------------------------------
+
+/// Height the tracked level is brought to, as a fraction of a full bar.
+const TARGET: f32 = 0.8;
+/// Seconds for the level to catch up with a louder signal. Quieter ones
+/// take the AGC's `speed`, so a sudden peak isn't pinned for long but a
+/// pause doesn't swell the noise.
+const ATTACK_SECONDS: f32 = 0.1;
+/// Raw magnitude below which the level isn't tracked, so silence holds
+/// the gain rather than raising it to the limit.
+const SILENCE: f32 = 1e-5;
+/// Adaptation time in seconds, and limits on the factor the AGC applies on
+/// top of the gain, by default.
+pub const DEFAULT_SPEED: f32 = 10.0;
+pub const DEFAULT_MIN_GAIN: f32 = 0.1;
+pub const DEFAULT_MAX_GAIN: f32 = 10.0;
+
+// ----------------------------------------------------------------------------
+
+/// What the automatic gain control follows.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "&'static str")]
+pub enum AgcMode {
+    /// The fixed gain only.
+    #[default]
+    Off,
+    /// One gain for every bar, from the loudest, so the shape of the
+    /// spectrum is kept.
+    Global,
+    /// A gain per bar, so each band uses the full height over time and a
+    /// quiet treble shows as much movement as the bass.
+    PerBand,
+}
+
+impl AgcMode {
+    pub const ALL: [AgcMode; 3] = [AgcMode::Off, AgcMode::Global, AgcMode::PerBand];
+
+    /// The mode after this one, wrapping around.
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|&m| m == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AgcMode::Off => "off",
+            AgcMode::Global => "global",
+            AgcMode::PerBand => "per-band",
+        }
+    }
+
+    /// The mode called `name` (see [`AgcMode::name`]).
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|m| m.name() == name)
+    }
+}
+
+impl TryFrom<String> for AgcMode {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        AgcMode::from_name(&s).ok_or_else(|| {
+            let names: Vec<&str> = AgcMode::ALL.iter().map(|m| m.name()).collect();
+            format!(
+                "unknown AGC mode {s:?}, expected one of {}",
+                names.join(", ")
+            )
+        })
+    }
+}
+
+impl From<AgcMode> for &'static str {
+    fn from(mode: AgcMode) -> Self {
+        mode.name()
+    }
+}
+
+/// Automatic gain control for the display: follows the peak of the raw
+/// bars, rising quickly and falling over `speed` seconds, and scales them
+/// so the peak sits near the top, within `min_gain` to `max_gain` times
+/// the fixed gain.
+///
+/// ```
+/// use audio_visualizer::agc::{Agc, AgcMode};
+///
+/// let mut agc = Agc::new(AgcMode::Global);
+/// // A quiet podcast: raw bars far below what the gain of 6 fills
+/// let quiet = [0.001; 16];
+/// let factors = agc.update(&quiet, 6.0, 2.0, 1.0 / 60.0).to_vec();
+/// assert!(factors.iter().all(|&f| f == agc.max_gain));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Agc {
+    pub mode: AgcMode,
+    /// Seconds the level takes to fall most of the way (1/e) to a quieter
+    /// signal.
+    pub speed: f32,
+    /// Limits on the factor applied on top of the gain.
+    pub min_gain: f32,
+    pub max_gain: f32,
+    /// Keep the current factors, e.g. to compare passages at one setting.
+    pub frozen: bool,
+    /// Tracked raw level: one in global mode, one per bar in per-band
+    /// mode; 0 until something is heard.
+    levels: Vec<f32>,
+    /// Factor per bar from the last update.
+    factors: Vec<f32>,
+}
+
+impl Default for Agc {
+    fn default() -> Self {
+        Self::new(AgcMode::default())
+    }
+}
+
+impl Agc {
+    pub fn new(mode: AgcMode) -> Self {
+        Self {
+            mode,
+            speed: DEFAULT_SPEED,
+            min_gain: DEFAULT_MIN_GAIN,
+            max_gain: DEFAULT_MAX_GAIN,
+            frozen: false,
+            levels: Vec::new(),
+            factors: Vec::new(),
+        }
+    }
+
+    /// Factor per bar from the last [`Agc::update`].
+    pub fn factors(&self) -> &[f32] {
+        &self.factors
+    }
+
+    /// Forget the tracked level, e.g. when the source or the bars change.
+    pub fn reset(&mut self) {
+        self.levels.clear();
+        self.factors.clear();
+    }
+
+    /// Track `raw` bar magnitudes, `dt` seconds after the previous frame,
+    /// and return the factor per bar that brings the level to near
+    /// `max_height` at `gain`. All 1 when off; unchanged while frozen,
+    /// unless the bars or the mode changed.
+    pub fn update(&mut self, raw: &[f32], gain: f32, max_height: f32, dt: f32) -> &[f32] {
+        let tracked = match self.mode {
+            AgcMode::Off => 0,
+            AgcMode::Global => 1,
+            AgcMode::PerBand => raw.len(),
+        };
+        let reshaped = self.levels.len() != tracked || self.factors.len() != raw.len();
+        if reshaped {
+            self.levels = vec![0.0; tracked];
+        } else if self.frozen {
+            return &self.factors;
+        }
+
+        let follow = |level: &mut f32, x: f32| {
+            if x < SILENCE {
+                return;
+            }
+            let seconds = if x > *level {
+                ATTACK_SECONDS
+            } else {
+                self.speed
+            };
+            if *level == 0.0 || dt <= 0.0 {
+                *level = x;
+            } else {
+                *level += (x - *level) * (1.0 - (-dt / seconds.max(f32::EPSILON)).exp());
+            }
+        };
+        match self.mode {
+            AgcMode::Off => {}
+            AgcMode::Global => follow(&mut self.levels[0], raw.iter().copied().fold(0.0, f32::max)),
+            AgcMode::PerBand => {
+                for (level, &x) in self.levels.iter_mut().zip(raw) {
+                    follow(level, x);
+                }
+            }
+        }
+
+        let target = TARGET * max_height / gain.max(f32::EPSILON);
+        let factor = |level: f32| {
+            if level > 0.0 {
+                (target / level).clamp(self.min_gain, self.max_gain.max(self.min_gain))
+            } else {
+                1.0
+            }
+        };
+        self.factors = match self.mode {
+            AgcMode::Off => vec![1.0; raw.len()],
+            AgcMode::Global => vec![factor(self.levels[0]); raw.len()],
+            AgcMode::PerBand => self.levels.iter().map(|&l| factor(l)).collect(),
+        };
+        &self.factors
+    }
+}
@@ -0,0 +1,210 @@
+use crate::agc::Agc;
+use crate::cqt;
+use crate::fft::BarScale;
+use crate::smoothing::Smoother;
+use crate::spectrum::{SpectrumAnalyzer, Transform};
+
+/// The analysis pipeline: a windowed FFT (or constant-Q transform, see
+/// [`Transform`]), grouped into bars, scaled by the gain and optionally an
+/// [`Agc`], and smoothed into display heights.
+///
+/// Feed it the latest samples once per frame and draw what it returns:
+///
+/// ```
+/// use audio_visualizer::Analyzer;
+///
+/// let mut analyzer = Analyzer::new(1024, 32, 6.0, 0.88);
+/// let tone: Vec<f32> = (0..1024).map(|i| (i as f32 * 0.3).sin()).collect();
+/// let heights = analyzer.process(&tone, 1.0 / 60.0);
+/// assert_eq!(heights.len(), 32);
+/// ```
+pub struct Analyzer {
+    transform: Transform,
+    /// Resolution of the constant-Q transform, kept while the FFT is in
+    /// use.
+    bins_per_octave: usize,
+    spectrum: Box<dyn SpectrumAnalyzer>,
+    agc: Agc,
+    /// Bar magnitudes of the last frame, before the gain and AGC.
+    raw: Vec<f32>,
+    smoother: Smoother,
+}
+
+impl Default for Analyzer {
+    /// The built-in tuning: `FFT_SIZE`, `NUM_BARS`, `GAIN` and `DECAY`.
+    fn default() -> Self {
+        Self::new(crate::FFT_SIZE, crate::NUM_BARS, crate::GAIN, crate::DECAY)
+    }
+}
+
+impl Analyzer {
+    /// `gain` scales raw FFT magnitudes into bar heights; `decay` is the
+    /// fraction of a bar's height kept per 1/60 s.
+    pub fn new(fft_size: usize, num_bars: usize, gain: f32, decay: f32) -> Self {
+        let transform = Transform::default();
+        Self {
+            transform,
+            bins_per_octave: cqt::BINS_PER_OCTAVE,
+            spectrum: transform.analyzer(
+                fft_size,
+                num_bars,
+                cqt::BINS_PER_OCTAVE,
+                BarScale::default(),
+                48_000,
+            ),
+            agc: Agc::default(),
+            raw: vec![0.0; num_bars],
+            smoother: Smoother::new(num_bars, gain, decay, crate::MAX_HEIGHT),
+        }
+    }
+
+    /// Number of samples per frame.
+    pub fn fft_size(&self) -> usize {
+        self.spectrum.size()
+    }
+
+    pub fn num_bars(&self) -> usize {
+        self.spectrum.num_bars()
+    }
+
+    pub fn gain(&self) -> f32 {
+        self.smoother.gain
+    }
+
+    pub fn set_gain(&mut self, gain: f32) {
+        self.smoother.gain = gain;
+    }
+
+    pub fn decay(&self) -> f32 {
+        self.smoother.decay
+    }
+
+    pub fn set_decay(&mut self, decay: f32) {
+        self.smoother.decay = decay;
+    }
+
+    /// Automatic gain control, off by default.
+    pub fn agc(&self) -> &Agc {
+        &self.agc
+    }
+
+    /// Change the AGC's mode, speed and limits, or freeze it.
+    pub fn agc_mut(&mut self) -> &mut Agc {
+        &mut self.agc
+    }
+
+    /// Switch to a new FFT size or bar count. Bars restart from zero when
+    /// either changes.
+    pub fn resize(&mut self, fft_size: usize, num_bars: usize) {
+        if (fft_size, num_bars) == (self.fft_size(), self.num_bars()) {
+            return;
+        }
+        self.spectrum = self.transform.analyzer(
+            fft_size,
+            num_bars,
+            self.bins_per_octave,
+            self.spectrum.scale(),
+            self.spectrum.sample_rate(),
+        );
+        self.raw = vec![0.0; num_bars];
+        self.agc.reset();
+        self.smoother.set_num_bars(num_bars);
+    }
+
+    pub fn scale(&self) -> BarScale {
+        self.spectrum.scale()
+    }
+
+    /// Group the spectrum into bars on `scale`, for audio at
+    /// `sample_rate`. The piano scale needs the rate to find its keys.
+    pub fn set_scale(&mut self, scale: BarScale, sample_rate: u32) {
+        if (scale, sample_rate) == (self.scale(), self.spectrum.sample_rate()) {
+            return;
+        }
+        self.spectrum = self.transform.analyzer(
+            self.fft_size(),
+            self.num_bars(),
+            self.bins_per_octave,
+            scale,
+            sample_rate,
+        );
+    }
+
+    pub fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    /// Compute the spectrum with `transform`, keeping the frame size, bars
+    /// and scale.
+    pub fn set_transform(&mut self, transform: Transform) {
+        if transform == self.transform {
+            return;
+        }
+        self.transform = transform;
+        self.spectrum = transform.analyzer(
+            self.fft_size(),
+            self.num_bars(),
+            self.bins_per_octave,
+            self.scale(),
+            self.spectrum.sample_rate(),
+        );
+    }
+
+    /// Bins per octave of the constant-Q transform.
+    pub fn bins_per_octave(&self) -> usize {
+        self.bins_per_octave
+    }
+
+    /// Give the constant-Q transform `bins_per_octave` bins per octave,
+    /// whether or not it is in use yet.
+    pub fn set_bins_per_octave(&mut self, bins_per_octave: usize) {
+        if bins_per_octave == self.bins_per_octave {
+            return;
+        }
+        self.bins_per_octave = bins_per_octave;
+        if self.transform == Transform::Cqt {
+            self.spectrum = self.transform.analyzer(
+                self.fft_size(),
+                self.num_bars(),
+                bins_per_octave,
+                self.scale(),
+                self.spectrum.sample_rate(),
+            );
+        }
+    }
+
+    /// Centre frequency in Hz of each bar, at the rate the analyzer was
+    /// set up for.
+    pub fn bar_frequencies(&self) -> Vec<f32> {
+        self.spectrum.bar_frequencies()
+    }
+
+    /// Analyze one frame of `fft_size` mono samples, `dt` seconds after
+    /// the previous one, and return the bar heights. Fewer samples are
+    /// zero-padded.
+    pub fn process(&mut self, samples: &[f32], dt: f32) -> &[f32] {
+        self.raw = self.spectrum.process(samples);
+        let factors = self
+            .agc
+            .update(&self.raw, self.smoother.gain, self.smoother.max_height, dt);
+        let scaled: Vec<f32> = self.raw.iter().zip(factors).map(|(r, f)| r * f).collect();
+        self.smoother.update(&scaled, dt)
+    }
+
+    /// Bar magnitudes of the last frame before the gain and AGC, for
+    /// metering: the same for the same sound whatever the display does.
+    pub fn raw_values(&self) -> &[f32] {
+        &self.raw
+    }
+
+    /// Magnitude spectrum of the last frame, before grouping into bars,
+    /// e.g. for [`crate::harmony::HarmonyTracker`].
+    pub fn spectrum(&self) -> &[f32] {
+        self.spectrum.spectrum()
+    }
+
+    /// Bar heights from the last call to [`Analyzer::process`].
+    pub fn values(&self) -> &[f32] {
+        self.smoother.values()
+    }
+}
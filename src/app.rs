@@ -0,0 +1,710 @@
+use audio_visualizer::pipeline::FramePipeline;
+use audio_visualizer::vectorscope::Vectorscope;
+use audio_visualizer::watch::FileWatcher;
+use audio_visualizer::{audio, renderer, theme, Analyzer, BarScale, Transform};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+use winit::application::ApplicationHandler;
+use winit::dpi::LogicalSize;
+use winit::event::{ElementState, KeyEvent, WindowEvent};
+use winit::event_loop::ActiveEventLoop;
+use winit::keyboard::Key;
+use winit::window::{Fullscreen, Window, WindowAttributes, WindowId};
+
+use crate::configure::{
+    configure_agc, configure_renderer, configured_analyzer, configured_theme, configured_view,
+    set_beat_pulse, set_bloom, set_layers, set_trails, startup_theme,
+};
+use crate::keys::Action;
+use crate::{cli, config};
+
+/// Bars added or removed per ← / → press.
+const BAR_STEP: usize = 8;
+
+const WINDOW_TITLE: &str = "Audio Visualizer";
+
+pub enum AudioSource {
+    /// Capture from an input device.
+    Device(cpal::Device),
+    /// Play a WAV file and visualize it.
+    File(String),
+}
+
+pub struct App {
+    window: Option<Arc<Window>>,
+    renderer: Option<renderer::Renderer>,
+    /// Device or file being visualized, once the stream has started.
+    /// Must stay alive or audio stops.
+    source: Option<audio::Source>,
+    analyzer: Analyzer,
+    /// Tempo, pitch, harmony, loudness and features of the source, at its
+    /// sample rate.
+    pipeline: Option<FramePipeline>,
+    last_frame: Instant,
+    /// Start of the session, for the Shadertoy clock.
+    start_time: Instant,
+    audio_source: AudioSource,
+    /// Command line, reapplied over the config file when it is reloaded.
+    cli: cli::Cli,
+    /// Settings last loaded. Keys change the live values without touching
+    /// this, so a reload only applies what changed in the file.
+    config: config::Config,
+    /// Config file, applied again whenever it is saved.
+    config_watcher: Option<FileWatcher>,
+    /// Built-in themes plus any loaded from a file (T cycles).
+    themes: Vec<theme::Theme>,
+    theme_index: usize,
+    /// Current window title, to avoid retitling every frame.
+    title: String,
+}
+
+impl App {
+    pub fn new(audio_source: AudioSource, cli: cli::Cli, config: config::Config) -> Self {
+        let theme = startup_theme(&config);
+        let mut themes = theme::Theme::builtin();
+        let theme_index = themes.iter().position(|t| *t == theme).unwrap_or_else(|| {
+            themes.push(theme);
+            themes.len() - 1
+        });
+
+        Self {
+            window: None,
+            renderer: None,
+            source: None,
+            analyzer: configured_analyzer(&config),
+            pipeline: None,
+            last_frame: Instant::now(),
+            start_time: Instant::now(),
+            audio_source,
+            config_watcher: cli.config_path().map(FileWatcher::new),
+            cli,
+            config,
+            themes,
+            theme_index,
+            title: WINDOW_TITLE.to_string(),
+        }
+    }
+
+    fn handle_key(&mut self, key: &Key) {
+        let Some(action) = self.config.keys.action(key) else {
+            return;
+        };
+        if self.handle_analysis_action(action) {
+            return;
+        }
+        let Some(r) = &mut self.renderer else {
+            return;
+        };
+
+        let mut params = r.layout_params();
+        let mut layout = r.layout();
+
+        match action {
+            // V: cycle bars → spectrogram → chromagram → vectorscope → Shadertoy
+            Action::CycleView => {
+                r.cycle_view();
+                println!("View: {}", r.view());
+            }
+            // M: L/R ↔ M/S scope orientation
+            Action::ScopeMode => {
+                if let Some(scope) = r.visualizer_mut::<Vectorscope>() {
+                    scope.mode = scope.mode.toggled();
+                }
+            }
+            // Up/Down: scope persistence
+            Action::ScopePersistenceUp => {
+                if let Some(scope) = r.visualizer_mut::<Vectorscope>() {
+                    scope.decay = (scope.decay + 0.02).min(0.99);
+                }
+            }
+            Action::ScopePersistenceDown => {
+                if let Some(scope) = r.visualizer_mut::<Vectorscope>() {
+                    scope.decay = (scope.decay - 0.02).max(0.0);
+                }
+            }
+            // T: cycle themes
+            Action::CycleTheme => {
+                self.theme_index = (self.theme_index + 1) % self.themes.len();
+                let theme = &self.themes[self.theme_index];
+                r.set_theme(theme);
+                println!("Theme: {}", theme.name);
+            }
+            // C: color bars by frequency / height / both
+            Action::CycleColorMode => {
+                let mode = r.color_mode().next();
+                r.set_color_mode(mode);
+                println!("Color by: {}", mode.name());
+            }
+            // B: bloom on/off
+            Action::ToggleBloom => match &mut r.bloom {
+                Some(bloom) => {
+                    bloom.enabled = !bloom.enabled;
+                    println!("Bloom: {}", on_off(bloom.enabled));
+                }
+                None => println!("Bloom is not supported on this adapter"),
+            },
+            // J / K: bloom threshold
+            Action::BloomThresholdDown => adjust_bloom(r, -0.05, 0.0),
+            Action::BloomThresholdUp => adjust_bloom(r, 0.05, 0.0),
+            // O / P: bloom intensity
+            Action::BloomIntensityDown => adjust_bloom(r, 0.0, -0.1),
+            Action::BloomIntensityUp => adjust_bloom(r, 0.0, 0.1),
+            // F: motion trails on/off
+            Action::ToggleTrails => {
+                let enabled = !r.feedback.enabled();
+                r.feedback.set_enabled(enabled);
+                println!("Trails: {}", on_off(enabled));
+            }
+            // E / R: trail decay
+            Action::TrailDecayDown => adjust_trails(r, -0.02, 1.0, 0.0),
+            Action::TrailDecayUp => adjust_trails(r, 0.02, 1.0, 0.0),
+            // Z / X: trail zoom
+            Action::TrailZoomOut => adjust_trails(r, 0.0, 1.0 / 1.005, 0.0),
+            Action::TrailZoomIn => adjust_trails(r, 0.0, 1.005, 0.0),
+            // Q / W: trail rotation
+            Action::TrailRotateLeft => adjust_trails(r, 0.0, 1.0, 0.5f32.to_radians()),
+            Action::TrailRotateRight => adjust_trails(r, 0.0, 1.0, -0.5f32.to_radians()),
+            // A: cycle anti-aliasing sample counts
+            Action::CycleMsaa => {
+                let counts = r.sample_counts();
+                let i = counts
+                    .iter()
+                    .position(|&n| n == r.sample_count())
+                    .unwrap_or(0);
+                let next = counts[(i + 1) % counts.len()];
+                let used = r.set_sample_count(next);
+                println!("MSAA: {used}x");
+            }
+            // 1–8: overlay frequency labels, dB grid, source, frame timing,
+            // tempo, tuner, key and chord, loudness meters
+            Action::ToggleFrequencyLabels => {
+                r.overlay.frequencies = !r.overlay.frequencies;
+                println!("Frequency labels: {}", on_off(r.overlay.frequencies));
+            }
+            Action::ToggleDbGrid => {
+                r.overlay.grid = !r.overlay.grid;
+                println!("dB grid: {}", on_off(r.overlay.grid));
+            }
+            Action::ToggleSourceInfo => {
+                r.overlay.source = !r.overlay.source;
+                println!("Source info: {}", on_off(r.overlay.source));
+            }
+            Action::ToggleFrameTiming => {
+                r.overlay.timing = !r.overlay.timing;
+                println!("Frame timing: {}", on_off(r.overlay.timing));
+            }
+            Action::ToggleTempo => {
+                r.overlay.tempo = !r.overlay.tempo;
+                println!("Tempo: {}", on_off(r.overlay.tempo));
+            }
+            Action::ToggleTuner => {
+                r.overlay.tuner = !r.overlay.tuner;
+                println!("Tuner: {}", on_off(r.overlay.tuner));
+            }
+            Action::ToggleHarmony => {
+                r.overlay.harmony = !r.overlay.harmony;
+                println!("Key and chord: {}", on_off(r.overlay.harmony));
+            }
+            Action::ToggleMeters => {
+                r.overlay.meters = !r.overlay.meters;
+                println!("Loudness meters: {}", on_off(r.overlay.meters));
+            }
+            // L: cycle bar layouts
+            Action::CycleLayout => {
+                layout = layout.next();
+                println!("Layout: {}", layout.name());
+            }
+            // [ / ]: ring radius
+            Action::RadiusDown => params.radius = (params.radius - 0.02).max(0.02),
+            Action::RadiusUp => params.radius = (params.radius + 0.02).min(1.0),
+            // , / .: rotate the whole layout
+            Action::RotateLeft => params.rotation -= 5f32.to_radians(),
+            Action::RotateRight => params.rotation += 5f32.to_radians(),
+            // - / =: gap between bars
+            Action::GapDown => params.gap = (params.gap - 0.05).max(0.0),
+            Action::GapUp => params.gap = (params.gap + 0.05).min(0.95),
+            // 9 / 0: start angle of ring and spiral layouts
+            Action::StartAngleDown => params.start_angle -= 5f32.to_radians(),
+            Action::StartAngleUp => params.start_angle += 5f32.to_radians(),
+            _ => {}
+        }
+
+        if layout != r.layout() || params != r.layout_params() {
+            r.set_layout(layout, params);
+        }
+    }
+
+    /// Actions for the analysis knobs: gain, AGC, decay, bar count, FFT
+    /// size, scale and transform. Returns whether `action` was one of them.
+    fn handle_analysis_action(&mut self, action: Action) -> bool {
+        let num_bars = self.analyzer.num_bars();
+        let fft_size = self.analyzer.fft_size();
+        let analyzer = &mut self.analyzer;
+
+        let readout = match action {
+            // G / H: gain
+            Action::GainDown => {
+                analyzer.set_gain((analyzer.gain() / 1.25).max(0.1));
+                format!("Gain: {:.2}", analyzer.gain())
+            }
+            Action::GainUp => {
+                analyzer.set_gain((analyzer.gain() * 1.25).min(100.0));
+                format!("Gain: {:.2}", analyzer.gain())
+            }
+            // I / Space: automatic gain mode, and holding it
+            Action::CycleAgc => {
+                let agc = analyzer.agc_mut();
+                agc.mode = agc.mode.next();
+                format!("AGC: {}", agc.mode.name())
+            }
+            Action::FreezeAgc => {
+                let agc = analyzer.agc_mut();
+                agc.frozen = !agc.frozen;
+                format!("AGC frozen: {}", on_off(agc.frozen))
+            }
+            // S / D: bar decay
+            Action::DecayDown => {
+                analyzer.set_decay((analyzer.decay() - 0.02).max(0.0));
+                format!("Decay: {:.2}", analyzer.decay())
+            }
+            Action::DecayUp => {
+                analyzer.set_decay((analyzer.decay() + 0.02).min(0.99));
+                format!("Decay: {:.2}", analyzer.decay())
+            }
+            // ← / →: number of bars
+            Action::FewerBars => {
+                // Down to one step, unless there were fewer bars already
+                let fewer = num_bars
+                    .saturating_sub(BAR_STEP)
+                    .max(BAR_STEP.min(num_bars));
+                self.set_analysis(fft_size, fewer);
+                format!("Bars: {}", self.analyzer.num_bars())
+            }
+            Action::MoreBars => {
+                self.set_analysis(fft_size, (num_bars + BAR_STEP).min(config::MAX_BARS));
+                format!("Bars: {}", self.analyzer.num_bars())
+            }
+            // PageDown / PageUp: FFT size
+            Action::SmallerFft => {
+                self.set_analysis((fft_size / 2).max(config::MIN_FFT_SIZE), num_bars);
+                format!("FFT size: {}", self.analyzer.fft_size())
+            }
+            Action::LargerFft => {
+                self.set_analysis((fft_size * 2).min(config::MAX_FFT_SIZE), num_bars);
+                format!("FFT size: {}", self.analyzer.fft_size())
+            }
+            // N: bars over the spectrum or on the piano keys
+            Action::CycleScale => {
+                let scale = self.analyzer.scale().next();
+                self.set_scale(scale);
+                format!("Scale: {}", scale.name())
+            }
+            // Y: FFT or constant-Q transform
+            Action::CycleTransform => {
+                let transform = self.analyzer.transform().next();
+                self.set_transform(transform);
+                format!("Transform: {}", transform.name())
+            }
+            _ => return false,
+        };
+
+        println!("{readout}");
+        if let Some(r) = &mut self.renderer {
+            r.show_notice(readout);
+        }
+        true
+    }
+
+    /// Resize the analyzer and the renderer's bars for a new FFT size or
+    /// bar count.
+    fn set_analysis(&mut self, fft_size: usize, num_bars: usize) {
+        if (fft_size, num_bars) == (self.analyzer.fft_size(), self.analyzer.num_bars()) {
+            return;
+        }
+        self.analyzer.resize(fft_size, num_bars);
+        if let Some(r) = &mut self.renderer {
+            r.set_num_bars(num_bars as u32);
+            if self.source.is_some() {
+                r.set_bar_frequencies(self.analyzer.bar_frequencies());
+            }
+        }
+    }
+
+    /// Group the bars on `scale`, once the source's sample rate is known.
+    fn set_scale(&mut self, scale: BarScale) {
+        let Some(source) = &self.source else {
+            return;
+        };
+        let sample_rate = source.info().sample_rate;
+        self.analyzer.set_scale(scale, sample_rate);
+        if let Some(r) = &mut self.renderer {
+            r.set_bar_frequencies(self.analyzer.bar_frequencies());
+        }
+    }
+
+    /// Compute the spectrum with `transform`.
+    fn set_transform(&mut self, transform: Transform) {
+        self.analyzer.set_transform(transform);
+        if let (Some(r), Some(_)) = (&mut self.renderer, &self.source) {
+            r.set_bar_frequencies(self.analyzer.bar_frequencies());
+        }
+    }
+
+    /// Give the constant-Q transform `bins_per_octave` bins per octave.
+    fn set_bins_per_octave(&mut self, bins_per_octave: usize) {
+        self.analyzer.set_bins_per_octave(bins_per_octave);
+        if let (Some(r), Some(_)) = (&mut self.renderer, &self.source) {
+            r.set_bar_frequencies(self.analyzer.bar_frequencies());
+        }
+    }
+
+    /// Start (or restart) the audio stream for `audio_source`. On error
+    /// there is no source until the next start.
+    fn start_audio(&mut self) -> Result<(), String> {
+        // Release the device before opening it again
+        self.source = None;
+        let source = match &self.audio_source {
+            AudioSource::Device(device) => audio::Source::device(device)?,
+            AudioSource::File(path) => audio::Source::file(path)?,
+        };
+
+        let sample_rate = source.info().sample_rate;
+        self.analyzer.set_scale(self.config.scale, sample_rate);
+        if let Some(r) = &mut self.renderer {
+            r.set_bar_frequencies(self.analyzer.bar_frequencies());
+        }
+        self.analyzer.agc_mut().reset();
+        self.pipeline = Some(FramePipeline::for_source(&source));
+        self.source = Some(source);
+        Ok(())
+    }
+
+    /// Reload the config file if it was saved since the last check. A file
+    /// with errors is reported and ignored.
+    fn reload_config(&mut self) {
+        let Some(watcher) = &mut self.config_watcher else {
+            return;
+        };
+        if !watcher.changed() {
+            return;
+        }
+
+        let path = watcher.path().to_path_buf();
+        let config = config::Config::load(&path).map(|mut config| {
+            self.cli.apply(&mut config);
+            config
+        });
+        let notice = match config {
+            Ok(config) => {
+                self.apply_config(config);
+                println!("Reloaded {}", path.display());
+                "Config reloaded".to_string()
+            }
+            Err(e) => {
+                eprintln!("{e}");
+                "Config error (see terminal)".to_string()
+            }
+        };
+        if let Some(r) = &mut self.renderer {
+            r.show_notice(notice);
+        }
+    }
+
+    /// Apply the settings that differ between `new` and the last config.
+    fn apply_config(&mut self, new: config::Config) {
+        let old = std::mem::replace(&mut self.config, new.clone());
+
+        if (new.fft_size, new.bars) != (old.fft_size, old.bars) {
+            self.set_analysis(new.fft_size, new.bars);
+        }
+        if new.gain != old.gain {
+            self.analyzer.set_gain(new.gain);
+        }
+        if new.decay != old.decay {
+            self.analyzer.set_decay(new.decay);
+        }
+        if new.agc != old.agc {
+            configure_agc(&mut self.analyzer, &new);
+        }
+        if new.scale != old.scale {
+            self.set_scale(new.scale);
+        }
+        if new.cqt_bins_per_octave != old.cqt_bins_per_octave {
+            self.set_bins_per_octave(new.cqt_bins_per_octave);
+        }
+        if new.transform != old.transform {
+            self.set_transform(new.transform);
+        }
+
+        if new.theme != old.theme {
+            match configured_theme(&new) {
+                Ok(theme) => {
+                    self.theme_index =
+                        self.themes
+                            .iter()
+                            .position(|t| *t == theme)
+                            .unwrap_or_else(|| {
+                                self.themes.push(theme);
+                                self.themes.len() - 1
+                            });
+                    if let Some(r) = &mut self.renderer {
+                        r.set_theme(&self.themes[self.theme_index]);
+                    }
+                }
+                Err(e) => eprintln!("{e}"),
+            }
+        }
+
+        if new.device != old.device && matches!(self.audio_source, AudioSource::Device(_)) {
+            match audio::input_device(new.device.as_deref()) {
+                Ok(device) => {
+                    self.audio_source = AudioSource::Device(device);
+                    if let Err(e) = self.start_audio() {
+                        eprintln!("{e}");
+                    }
+                }
+                Err(e) => eprintln!("{e}"),
+            }
+        }
+
+        if new.fullscreen != old.fullscreen {
+            if let Some(w) = &self.window {
+                w.set_fullscreen(new.fullscreen.then_some(Fullscreen::Borderless(None)));
+            }
+        }
+
+        let Some(r) = &mut self.renderer else {
+            return;
+        };
+        if (new.layout, new.layout_params) != (old.layout, old.layout_params) {
+            r.set_layout(new.layout, new.layout_params);
+        }
+        if new.shader != old.shader {
+            r.set_bar_shader(new.shader.clone());
+        }
+        if new.shadertoy != old.shadertoy {
+            r.set_shadertoy_shader(new.shadertoy.clone());
+        }
+        let view_changed = (&new.view, &new.shadertoy) != (&old.view, &old.shadertoy);
+        if view_changed {
+            if let Some(view) = configured_view(&new) {
+                r.set_view(view);
+            }
+        }
+        // Setting the view drops the layers, so they go back on top
+        if view_changed || new.layers != old.layers {
+            set_layers(r, &new.layers);
+        }
+        if new.msaa != old.msaa {
+            // Back to the most supported when the setting is removed
+            let most = r.sample_counts().iter().copied().max().unwrap_or(1);
+            r.set_sample_count(new.msaa.unwrap_or(most));
+        }
+        if new.bloom != old.bloom {
+            set_bloom(r, &new.bloom);
+        }
+        if new.trails != old.trails {
+            set_trails(r, &new.trails);
+        }
+        if new.overlay != old.overlay {
+            r.overlay = new.overlay;
+        }
+        if new.beat_pulse != old.beat_pulse {
+            set_beat_pulse(r, new.beat_pulse);
+        }
+    }
+
+    /// Show status in the window title: shader errors, and the phase
+    /// correlation while the vectorscope is active.
+    fn update_title(&mut self) {
+        let (Some(r), Some(w)) = (&self.renderer, &self.window) else {
+            return;
+        };
+
+        let title = if r.shader_error().is_some() {
+            format!("{WINDOW_TITLE} — shader error (see terminal)")
+        } else if r.is_view::<Vectorscope>() {
+            let correlation = r
+                .visualizer::<Vectorscope>()
+                .map_or(0.0, Vectorscope::correlation);
+            format!("{WINDOW_TITLE} — correlation {correlation:+.2}")
+        } else {
+            WINDOW_TITLE.to_string()
+        };
+
+        if title != self.title {
+            w.set_title(&title);
+            self.title = title;
+        }
+    }
+}
+
+impl ApplicationHandler for App {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        // Only initialise once
+        if self.window.is_some() {
+            return;
+        }
+
+        let attrs = WindowAttributes::default()
+            .with_title(WINDOW_TITLE)
+            .with_inner_size(LogicalSize::new(1200, 600))
+            .with_fullscreen(
+                self.config
+                    .fullscreen
+                    .then_some(Fullscreen::Borderless(None)),
+            );
+
+        let window = Arc::new(
+            event_loop
+                .create_window(attrs)
+                .expect("Failed to create window"),
+        );
+
+        let mut renderer = pollster::block_on(renderer::Renderer::new(
+            window.clone(),
+            self.config.bars as u32,
+            self.config.layout,
+            self.config.layout_params,
+        ));
+        renderer.set_theme(&self.themes[self.theme_index]);
+        configure_renderer(&mut renderer, &self.config);
+
+        self.renderer = Some(renderer);
+        self.window = Some(window);
+        if let Err(e) = self.start_audio() {
+            eprintln!("{e}");
+            std::process::exit(2);
+        }
+    }
+
+    fn window_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        _window_id: WindowId,
+        event: WindowEvent,
+    ) {
+        match event {
+            WindowEvent::CloseRequested => {
+                event_loop.exit();
+            }
+
+            WindowEvent::Resized(size) => {
+                if let Some(r) = &mut self.renderer {
+                    r.resize(size);
+                }
+            }
+
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key,
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => self.handle_key(&logical_key),
+
+            WindowEvent::RedrawRequested => {
+                self.reload_config();
+                let now = Instant::now();
+                let dt = now.duration_since(self.last_frame).as_secs_f32();
+                self.last_frame = now;
+
+                let Some(r) = &mut self.renderer else {
+                    return;
+                };
+                if let (Some(source), Some(pipeline)) = (&self.source, &mut self.pipeline) {
+                    r.reload_shaders();
+                    r.set_source_info(source_text(source.info()));
+
+                    // Each pair is drawn once; persistence is handled on the GPU
+                    let stereo = source.drain_stereo();
+                    r.update_stereo(&stereo);
+
+                    // ---- bars, pitch, harmony and features ----
+                    // Silence until the ring buffer has a whole window. The
+                    // tempo and loudness are fed on the audio thread.
+                    let window = pipeline.window_size(&self.analyzer);
+                    let latest = source.latest(window).unwrap_or_default();
+                    pipeline.process(&mut self.analyzer, &latest, dt);
+                    pipeline.update_renderer(r);
+                }
+
+                // ---- render ----
+                r.set_time(self.start_time.elapsed().as_secs_f32(), dt);
+                r.render(self.analyzer.values());
+                self.update_title();
+            }
+
+            _ => {}
+        }
+    }
+
+    /// Called after all pending events have been processed.
+    /// We use this to request continuous redraws (~vsync rate).
+    fn about_to_wait(&mut self, _event_loop: &ActiveEventLoop) {
+        if let Some(w) = &self.window {
+            w.request_redraw();
+        }
+    }
+}
+
+/// Nudge the bloom threshold and intensity, if bloom is available.
+fn adjust_bloom(r: &mut renderer::Renderer, threshold_step: f32, intensity_step: f32) {
+    if let Some(bloom) = &mut r.bloom {
+        bloom.threshold = (bloom.threshold + threshold_step).clamp(0.0, 2.0);
+        bloom.intensity = (bloom.intensity + intensity_step).clamp(0.0, 5.0);
+        println!(
+            "Bloom threshold: {:.2}, intensity: {:.1}",
+            bloom.threshold, bloom.intensity
+        );
+    }
+}
+
+/// Nudge the trail decay, scale the zoom and add to the rotation.
+fn adjust_trails(
+    r: &mut renderer::Renderer,
+    decay_step: f32,
+    zoom_factor: f32,
+    rotation_step: f32,
+) {
+    let feedback = &mut r.feedback;
+    feedback.decay = (feedback.decay + decay_step).clamp(0.0, 0.99);
+    feedback.zoom = (feedback.zoom * zoom_factor).clamp(0.9, 1.1);
+    feedback.rotation = (feedback.rotation + rotation_step).clamp(-0.1, 0.1);
+    println!(
+        "Trails decay: {:.2}, zoom: {:.3}, rotation: {:.1}°",
+        feedback.decay,
+        feedback.zoom,
+        feedback.rotation.to_degrees()
+    );
+}
+
+fn on_off(enabled: bool) -> &'static str {
+    if enabled {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+/// Overlay line for the audio source: file name and playback position, or
+/// the input device.
+fn source_text(source: &audio::SourceInfo) -> String {
+    let minutes = |seconds: f32| {
+        let seconds = seconds as u32;
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    };
+    match source.playback_time() {
+        Some((position, length)) => {
+            let name = Path::new(&source.name)
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| source.name.clone());
+            format!("{name}  {} / {}", minutes(position), minutes(length))
+        }
+        None => format!("Input: {}", source.name),
+    }
+}
@@ -3,9 +3,12 @@ use std::collections::VecDeque;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 
+use crate::pipeline::{SharedStreamAnalysis, StreamAnalysis};
+
 /// Maximum number of mono samples to keep in the shared ring buffer.
-/// Large enough to hold several FFT windows worth of data.
-const MAX_BUFFER_SIZE: usize = 2048 * 4;
+/// Large enough to hold several FFT windows worth of data, and the limit
+/// on the FFT size.
+pub const MAX_BUFFER_SIZE: usize = 2048 * 4;
 
 /// Shared ring buffer that the audio thread writes into and the render
 /// loop reads from.
@@ -16,61 +19,235 @@ pub fn new_shared_buffer() -> SharedBuffer {
     Arc::new(Mutex::new(VecDeque::with_capacity(MAX_BUFFER_SIZE)))
 }
 
+/// Shared ring buffer of `[left, right]` sample pairs for the stereo views.
+/// Mono sources write the same value to both channels.
+pub type SharedStereoBuffer = Arc<Mutex<VecDeque<[f32; 2]>>>;
+
+/// Create a new shared stereo buffer.
+pub fn new_shared_stereo_buffer() -> SharedStereoBuffer {
+    Arc::new(Mutex::new(VecDeque::with_capacity(MAX_BUFFER_SIZE)))
+}
+
+/// What a stream is capturing or playing, for display.
+pub struct SourceInfo {
+    /// Input device name, or the path of the file being played.
+    pub name: String,
+    pub sample_rate: u32,
+    /// Channels captured or in the file; the stereo pairs hold the first
+    /// two.
+    pub channels: usize,
+    /// Set for file playback.
+    playback: Option<Playback>,
+}
+
+/// Shared position of a playing file.
+struct Playback {
+    /// Index of the next interleaved sample to play.
+    position: Arc<AtomicUsize>,
+    channels: usize,
+    len: usize,
+}
+
+impl SourceInfo {
+    /// Current position and total length in seconds, for file playback.
+    pub fn playback_time(&self) -> Option<(f32, f32)> {
+        let playback = self.playback.as_ref()?;
+        let rate = (self.sample_rate as usize * playback.channels).max(1) as f32;
+        let position = playback.position.load(Ordering::Relaxed);
+        Some((position as f32 / rate, playback.len as f32 / rate))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Sources
+// ---------------------------------------------------------------------------
+
+/// A running capture or playback stream and the buffers it fills. Audio
+/// stops when it is dropped.
+pub struct Source {
+    _stream: cpal::Stream,
+    info: SourceInfo,
+    samples: SharedBuffer,
+    stereo: SharedStereoBuffer,
+    /// Tempo and loudness, fed every sample on the audio thread.
+    analysis: SharedStreamAnalysis,
+}
+
+impl Source {
+    /// Capture from `device` (see [`input_device`]).
+    ///
+    /// # Errors
+    ///
+    /// If the device has no usable input configuration or the stream
+    /// can't be started.
+    pub fn device(device: &cpal::Device) -> Result<Self, String> {
+        let (samples, stereo) = (new_shared_buffer(), new_shared_stereo_buffer());
+        let (stream, info, analysis) =
+            start_input_capture(device, samples.clone(), stereo.clone())?;
+        Ok(Self {
+            _stream: stream,
+            info,
+            samples,
+            stereo,
+            analysis,
+        })
+    }
+
+    /// Play the WAV file at `path` on the default output device, looping,
+    /// and visualize what is playing.
+    ///
+    /// # Errors
+    ///
+    /// If the file can't be decoded, there is no output device, or the
+    /// stream can't be started.
+    pub fn file(path: &str) -> Result<Self, String> {
+        let (samples, stereo) = (new_shared_buffer(), new_shared_stereo_buffer());
+        let (stream, info, analysis) = start_file_playback(path, samples.clone(), stereo.clone())?;
+        Ok(Self {
+            _stream: stream,
+            info,
+            samples,
+            stereo,
+            analysis,
+        })
+    }
+
+    pub fn info(&self) -> &SourceInfo {
+        &self.info
+    }
+
+    /// The most recent `n` mono samples, oldest first, or `None` until
+    /// that many have arrived.
+    pub fn latest(&self, n: usize) -> Option<Vec<f32>> {
+        let buf = self.samples.lock().unwrap();
+        if buf.len() < n {
+            return None;
+        }
+        Some(buf.range(buf.len() - n..).copied().collect())
+    }
+
+    /// Take every `[left, right]` pair that arrived since the last call,
+    /// or the latest `MAX_BUFFER_SIZE` of them if frames are slow.
+    pub fn drain_stereo(&self) -> Vec<[f32; 2]> {
+        self.stereo.lock().unwrap().drain(..).collect()
+    }
+
+    /// Tempo and loudness of every sample so far, however often frames
+    /// are drawn.
+    pub fn stream_analysis(&self) -> &SharedStreamAnalysis {
+        &self.analysis
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Device input (captures from default input device — e.g. BlackHole for
 // Logic Pro routing, or any other virtual/hardware input)
 // ---------------------------------------------------------------------------
 
+/// Names of the host's input devices.
+pub fn input_device_names() -> Vec<String> {
+    let host = cpal::default_host();
+    host.input_devices()
+        .map(|devices| devices.filter_map(|d| d.name().ok()).collect())
+        .unwrap_or_default()
+}
+
 /// Print available input devices to stdout so the user knows what's there.
 pub fn list_input_devices() {
-    let host = cpal::default_host();
     println!("Available input devices:");
-    if let Ok(devices) = host.input_devices() {
-        for (i, device) in devices.enumerate() {
-            if let Ok(name) = device.name() {
-                println!("  [{}] {}", i, name);
-            }
-        }
+    for (i, name) in input_device_names().iter().enumerate() {
+        println!("  [{}] {}", i, name);
     }
     println!();
-    println!("Using default input device.");
     println!("To visualize Logic Pro output, route it through a virtual audio");
-    println!("device like BlackHole and set that as the default input.");
+    println!("device like BlackHole and pick it with --device, or set it as the");
+    println!("default input.");
     println!();
     println!("Pass a .wav file path as an argument to visualize a file instead:");
     println!("  cargo run -- path/to/song.wav");
     println!();
 }
 
-/// Start capturing audio from the default system input device.
-/// Returns a `cpal::Stream` that must be kept alive for the duration of capture.
-pub fn start_input_capture(buffer: SharedBuffer) -> cpal::Stream {
+/// Find the input device called `name`, or whose name contains it
+/// (ignoring case), or the default device if `name` is `None`.
+pub fn input_device(name: Option<&str>) -> Result<cpal::Device, String> {
     let host = cpal::default_host();
-    let device = host
-        .default_input_device()
-        .expect("No input device available");
+    let Some(name) = name else {
+        return host
+            .default_input_device()
+            .ok_or_else(|| "No input device available".to_string());
+    };
+
+    let devices: Vec<cpal::Device> = host
+        .input_devices()
+        .map(|devices| devices.collect())
+        .unwrap_or_default();
+    let device_name = |d: &cpal::Device| d.name().unwrap_or_default();
+    let lower = name.to_lowercase();
+    let exact = devices.iter().position(|d| device_name(d) == name);
+    let partial = || {
+        devices
+            .iter()
+            .position(|d| device_name(d).to_lowercase().contains(&lower))
+    };
+    match exact.or_else(partial) {
+        Some(i) => Ok(devices.into_iter().nth(i).unwrap()),
+        None => {
+            let names: Vec<String> = devices.iter().map(device_name).collect();
+            Err(format!(
+                "No input device matches {name:?}. Available: {}",
+                if names.is_empty() {
+                    "none".to_string()
+                } else {
+                    names.join(", ")
+                }
+            ))
+        }
+    }
+}
 
-    println!("Capturing from: {}", device.name().unwrap_or_default());
+fn start_input_capture(
+    device: &cpal::Device,
+    buffer: SharedBuffer,
+    stereo: SharedStereoBuffer,
+) -> Result<(cpal::Stream, SourceInfo, SharedStreamAnalysis), String> {
+    let name = device.name().unwrap_or_default();
+    println!("Capturing from: {name}");
 
     let supported = device
         .default_input_config()
-        .expect("No default input config");
+        .map_err(|e| format!("No default input config for {name}: {e}"))?;
     let channels = supported.channels() as usize;
     let config: cpal::StreamConfig = supported.into();
+    let info = SourceInfo {
+        name,
+        sample_rate: config.sample_rate.0,
+        channels,
+        playback: None,
+    };
+    let analysis = Arc::new(Mutex::new(StreamAnalysis::new(info.sample_rate, channels)));
+    let analysis_c = analysis.clone();
+    // Reused by every callback, so it only allocates while growing
+    let mut pairs = Vec::new();
 
     let stream = device
         .build_input_stream(
             &config,
             move |data: &[f32], _: &cpal::InputCallbackInfo| {
                 push_samples(data, channels, &buffer);
+                stereo_pairs(data, channels, &mut pairs);
+                analysis_c.lock().unwrap().process(&pairs);
+                push_stereo_samples(&pairs, &stereo);
             },
             |err| eprintln!("Audio input error: {err}"),
             None,
         )
-        .expect("Failed to build input stream");
+        .map_err(|e| format!("Failed to build input stream: {e}"))?;
 
-    stream.play().expect("Failed to start input stream");
     stream
+        .play()
+        .map_err(|e| format!("Failed to start input stream: {e}"))?;
+    Ok((stream, info, analysis))
 }
 
 // ---------------------------------------------------------------------------
@@ -81,59 +258,56 @@ pub fn start_input_capture(buffer: SharedBuffer) -> cpal::Stream {
 /// Load a WAV file, play it through the default output device, and
 /// simultaneously feed samples into the shared buffer for visualization.
 /// Returns a `cpal::Stream` that must be kept alive.
-pub fn start_file_playback(path: &str, buffer: SharedBuffer) -> cpal::Stream {
+fn start_file_playback(
+    path: &str,
+    buffer: SharedBuffer,
+    stereo: SharedStereoBuffer,
+) -> Result<(cpal::Stream, SourceInfo, SharedStreamAnalysis), String> {
     // ---- decode the WAV file ----
-    let mut reader =
-        hound::WavReader::open(path).unwrap_or_else(|e| panic!("Failed to open {path}: {e}"));
-    let spec = reader.spec();
+    let decoded = decode_wav(path).map_err(|e| format!("Failed to open {path} (only WAV is supported): {e}"))?;
     println!(
-        "Playing: {} ({}Hz, {} ch, {:?})",
-        path, spec.sample_rate, spec.channels, spec.sample_format
+        "Playing: {} ({}Hz, {} ch)",
+        path, decoded.sample_rate, decoded.channels
     );
 
-    let samples: Vec<f32> = match spec.sample_format {
-        hound::SampleFormat::Float => reader.samples::<f32>().map(|s| s.unwrap()).collect(),
-        hound::SampleFormat::Int => match spec.bits_per_sample {
-            16 => reader
-                .samples::<i16>()
-                .map(|s| s.unwrap() as f32 / i16::MAX as f32)
-                .collect(),
-            24 => reader
-                .samples::<i32>()
-                .map(|s| s.unwrap() as f32 / 8_388_607.0)
-                .collect(),
-            _ => reader
-                .samples::<i32>()
-                .map(|s| s.unwrap() as f32 / i32::MAX as f32)
-                .collect(),
-        },
-    };
-
-    let src_channels = spec.channels as usize;
-    let samples = Arc::new(samples);
+    let src_channels = decoded.channels;
+    let sample_rate = decoded.sample_rate;
+    let samples = Arc::new(decoded.samples);
     let position = Arc::new(AtomicUsize::new(0));
+    let info = SourceInfo {
+        name: path.to_string(),
+        sample_rate,
+        channels: src_channels,
+        playback: Some(Playback {
+            position: position.clone(),
+            channels: src_channels,
+            len: samples.len(),
+        }),
+    };
 
     // ---- set up cpal output stream ----
     let host = cpal::default_host();
     let device = host
         .default_output_device()
-        .expect("No output device available");
+        .ok_or_else(|| "No output device available".to_string())?;
 
     let out_supported = device
         .default_output_config()
-        .expect("No default output config");
+        .map_err(|e| format!("No default output config: {e}"))?;
     let dst_channels = out_supported.channels() as usize;
 
     // Use the file's sample rate so pitch is correct.
     // Most devices accept 44100 / 48000 natively.
     let config = cpal::StreamConfig {
         channels: dst_channels as u16,
-        sample_rate: cpal::SampleRate(spec.sample_rate),
+        sample_rate: cpal::SampleRate(sample_rate),
         buffer_size: cpal::BufferSize::Default,
     };
 
+    let analysis = Arc::new(Mutex::new(StreamAnalysis::new(sample_rate, src_channels)));
     let samples_c = samples.clone();
     let position_c = position.clone();
+    let analysis_c = analysis.clone();
 
     let stream = device
         .build_output_stream(
@@ -143,6 +317,7 @@ pub fn start_file_playback(path: &str, buffer: SharedBuffer) -> cpal::Stream {
                 let total = samples_c.len();
                 let frames_needed = data.len() / dst_channels;
                 let mut mono_samples: Vec<f32> = Vec::with_capacity(frames_needed);
+                let mut stereo_samples: Vec<[f32; 2]> = Vec::with_capacity(frames_needed);
 
                 for frame in 0..frames_needed {
                     // Wrap position back to start when we reach the end (loop)
@@ -157,6 +332,10 @@ pub fn start_file_playback(path: &str, buffer: SharedBuffer) -> cpal::Stream {
                         / src_channels as f32;
                     mono_samples.push(mono);
 
+                    // First two source channels → L/R for the stereo views
+                    let right_ch = if src_channels > 1 { 1 } else { 0 };
+                    stereo_samples.push([samples_c[pos], samples_c[pos + right_ch]]);
+
                     // Write to output channels (duplicate / map as needed)
                     for ch in 0..dst_channels {
                         data[frame * dst_channels + ch] =
@@ -174,14 +353,106 @@ pub fn start_file_playback(path: &str, buffer: SharedBuffer) -> cpal::Stream {
                 while buf.len() > MAX_BUFFER_SIZE {
                     buf.pop_front();
                 }
+                drop(buf);
+
+                // Every pair is metered, even those the display drops
+                analysis_c.lock().unwrap().process(&stereo_samples);
+                push_stereo_samples(&stereo_samples, &stereo);
             },
             |err| eprintln!("Audio output error: {err}"),
             None,
         )
-        .expect("Failed to build output stream");
+        .map_err(|e| format!("Failed to build output stream: {e}"))?;
 
-    stream.play().expect("Failed to start output stream");
     stream
+        .play()
+        .map_err(|e| format!("Failed to start output stream: {e}"))?;
+    Ok((stream, info, analysis))
+}
+
+// ---------------------------------------------------------------------------
+// Decoding
+// ---------------------------------------------------------------------------
+
+/// A whole audio file decoded into memory.
+pub struct DecodedAudio {
+    /// Interleaved samples in -1..1.
+    pub samples: Vec<f32>,
+    pub channels: usize,
+    pub sample_rate: u32,
+}
+
+impl DecodedAudio {
+    /// Number of sample frames (samples per channel).
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels.max(1)
+    }
+
+    /// Downmix frames `start..end` to mono. Out-of-range frames are silent.
+    pub fn mono_range(&self, start: isize, end: isize) -> Vec<f32> {
+        let frames = self.frames() as isize;
+        (start..end)
+            .map(|frame| {
+                if frame < 0 || frame >= frames {
+                    return 0.0;
+                }
+                let base = frame as usize * self.channels;
+                self.samples[base..base + self.channels].iter().sum::<f32>()
+                    / self.channels as f32
+            })
+            .collect()
+    }
+
+    /// Frames `start..end` as `[left, right]` pairs (mono is duplicated).
+    /// Out-of-range frames are silent.
+    pub fn stereo_range(&self, start: isize, end: isize) -> Vec<[f32; 2]> {
+        let frames = self.frames() as isize;
+        let right_ch = if self.channels > 1 { 1 } else { 0 };
+        (start..end)
+            .map(|frame| {
+                if frame < 0 || frame >= frames {
+                    return [0.0; 2];
+                }
+                let base = frame as usize * self.channels;
+                [self.samples[base], self.samples[base + right_ch]]
+            })
+            .collect()
+    }
+}
+
+/// Decode a WAV file into interleaved f32 samples.
+pub fn decode_wav(path: &str) -> Result<DecodedAudio, hound::Error> {
+    let mut reader = hound::WavReader::open(path)?;
+    let spec = reader.spec();
+
+    let samples: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+        hound::SampleFormat::Int => {
+            // Full-scale value for the file's bit depth
+            let scale = match spec.bits_per_sample {
+                16 => i16::MAX as f32,
+                24 => 8_388_607.0,
+                _ => i32::MAX as f32,
+            };
+            if spec.bits_per_sample == 16 {
+                reader
+                    .samples::<i16>()
+                    .map(|s| s.map(|s| s as f32 / scale))
+                    .collect::<Result<_, _>>()?
+            } else {
+                reader
+                    .samples::<i32>()
+                    .map(|s| s.map(|s| s as f32 / scale))
+                    .collect::<Result<_, _>>()?
+            }
+        }
+    };
+
+    Ok(DecodedAudio {
+        samples,
+        channels: spec.channels.max(1) as usize,
+        sample_rate: spec.sample_rate,
+    })
 }
 
 // ---------------------------------------------------------------------------
@@ -205,3 +476,24 @@ fn push_samples(data: &[f32], channels: usize, buffer: &SharedBuffer) {
         buf.pop_front();
     }
 }
+
+/// Interleaved multi-channel samples as `[left, right]` pairs, written to
+/// `pairs`. Extra channels are ignored; mono is duplicated.
+fn stereo_pairs(data: &[f32], channels: usize, pairs: &mut Vec<[f32; 2]>) {
+    pairs.clear();
+    pairs.extend(data.chunks(channels.max(1)).map(|chunk| {
+        let left = chunk[0];
+        let right = if chunk.len() > 1 { chunk[1] } else { left };
+        [left, right]
+    }));
+}
+
+/// Push `[left, right]` pairs into the stereo ring buffer, dropping the
+/// oldest beyond `MAX_BUFFER_SIZE`.
+fn push_stereo_samples(pairs: &[[f32; 2]], buffer: &SharedStereoBuffer) {
+    let mut buf = buffer.lock().unwrap();
+    buf.extend(pairs);
+    while buf.len() > MAX_BUFFER_SIZE {
+        buf.pop_front();
+    }
+}
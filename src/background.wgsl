@@ -0,0 +1,30 @@
+// Theme background: a vertical gradient on a full-screen triangle
+
+struct Background {
+    bottom: vec4<f32>,
+    top: vec4<f32>,
+};
+
+@group(0) @binding(0) var<uniform> background: Background;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    // 0 = bottom edge, 1 = top edge
+    @location(0) t: f32,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let x = f32((vertex_index << 1u) & 2u) * 2.0 - 1.0;
+    let y = f32(vertex_index & 2u) * 2.0 - 1.0;
+
+    var output: VertexOutput;
+    output.position = vec4<f32>(x, y, 0.0, 1.0);
+    output.t = y * 0.5 + 0.5;
+    return output;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    return mix(background.bottom, background.top, input.t);
+}
@@ -0,0 +1,534 @@
+use glam::Mat4;
+use std::path::PathBuf;
+
+use crate::layout::{self, Layout, LayoutParams};
+use crate::renderer::with_validation;
+use crate::theme::{self, ColorMode, Theme};
+use crate::visualizer::{AnalysisFrame, SceneFormat, Visualizer};
+use crate::watch::FileWatcher;
+
+/// Uniform parameters sent to the shader.
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct Params {
+    /// Layout units → clip space, kept square-pixel on resize.
+    projection: Mat4,
+    num_bars: u32,
+    bar_width: f32,
+    height_scale: f32,
+    mirrored: u32,
+    color_mode: u32,
+    _pad: [u32; 3],
+}
+
+impl Params {
+    fn new(
+        projection: Mat4,
+        num_bars: u32,
+        geometry: &layout::BarGeometry,
+        color_mode: ColorMode,
+    ) -> Self {
+        Self {
+            projection,
+            num_bars,
+            bar_width: geometry.bar_width,
+            height_scale: geometry.height_scale,
+            mirrored: geometry.mirrored as u32,
+            color_mode: color_mode.index(),
+            _pad: [0; 3],
+        }
+    }
+}
+
+/// Spectrum bars: one instanced quad per bar, placed by a [`Layout`] and
+/// colored from the theme palette.
+pub struct Bars {
+    scene: SceneFormat,
+    pipeline: wgpu::RenderPipeline,
+    pipeline_layout: wgpu::PipelineLayout,
+    /// Set when bars are drawn with a user shader file.
+    shader_watcher: Option<FileWatcher>,
+    /// Last compile error of the user shader, if it failed.
+    shader_error: Option<String>,
+    /// Per-bar buffers, rebuilt when the bar count changes.
+    buffers: BarBuffers,
+    bind_group_layout: wgpu::BindGroupLayout,
+    params_buffer: wgpu::Buffer,
+    /// Theme gradient sampled by the bar shader.
+    palette_texture: wgpu::Texture,
+    palette_view: wgpu::TextureView,
+    palette_sampler: wgpu::Sampler,
+    color_mode: ColorMode,
+    num_bars: u32,
+    layout: Layout,
+    layout_params: LayoutParams,
+    size: (u32, u32),
+    /// Fraction the ring radius grows by on each beat (0 = steady).
+    pub beat_pulse: f32,
+    /// Ring radius multiplier of the current frame, from the beat.
+    radius_scale: f32,
+    /// Bar placement for the current layout and size, for the overlay.
+    geometry: layout::BarGeometry,
+}
+
+impl Bars {
+    /// Create the buffers and pipeline. Transforms are written once the
+    /// renderer resizes and themes the bars.
+    pub fn new(
+        device: &wgpu::Device,
+        scene: SceneFormat,
+        num_bars: u32,
+        layout: Layout,
+        layout_params: LayoutParams,
+    ) -> Self {
+        let size = (1, 1);
+        let geometry = layout::build(
+            layout,
+            &layout_params,
+            num_bars,
+            layout::view_extent(size.0, size.1),
+        );
+
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Params"),
+            size: std::mem::size_of::<Params>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // sRGB so theme colors interpolate and display as written. One texel
+        // tall rather than 1D, which some software drivers sample as black.
+        let palette_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Palette"),
+            size: wgpu::Extent3d {
+                width: theme::PALETTE_SIZE,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let palette_view = palette_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let palette_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Palette Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        // --- Bind group ---
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let buffers = BarBuffers::new(
+            device,
+            &bind_group_layout,
+            num_bars,
+            &params_buffer,
+            &palette_view,
+            &palette_sampler,
+        );
+
+        // --- Shader & pipeline ---
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            immediate_size: 0,
+        });
+
+        let pipeline =
+            create_pipeline(device, &pipeline_layout, scene, include_str!("shader.wgsl"));
+
+        Self {
+            scene,
+            pipeline,
+            pipeline_layout,
+            shader_watcher: None,
+            shader_error: None,
+            buffers,
+            bind_group_layout,
+            params_buffer,
+            palette_texture,
+            palette_view,
+            palette_sampler,
+            color_mode: ColorMode::Frequency,
+            num_bars,
+            layout,
+            layout_params,
+            size,
+            beat_pulse: 0.0,
+            radius_scale: 1.0,
+            geometry,
+        }
+    }
+
+    pub fn num_bars(&self) -> u32 {
+        self.num_bars
+    }
+
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    pub fn layout_params(&self) -> LayoutParams {
+        self.layout_params
+    }
+
+    /// Bar placement for the current layout and size.
+    pub fn geometry(&self) -> &layout::BarGeometry {
+        &self.geometry
+    }
+
+    /// Switch layout and/or layout parameters. Only the transforms and
+    /// params buffers are rewritten; the pipeline is reused.
+    pub fn set_layout(&mut self, queue: &wgpu::Queue, layout: Layout, params: LayoutParams) {
+        self.layout = layout;
+        self.layout_params = params;
+        self.update_geometry(queue);
+    }
+
+    /// Change the number of bars, reallocating the per-bar buffers.
+    pub fn set_num_bars(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, num_bars: u32) {
+        let num_bars = num_bars.max(1);
+        if num_bars == self.num_bars {
+            return;
+        }
+        self.num_bars = num_bars;
+        self.buffers = BarBuffers::new(
+            device,
+            &self.bind_group_layout,
+            num_bars,
+            &self.params_buffer,
+            &self.palette_view,
+            &self.palette_sampler,
+        );
+        self.update_geometry(queue);
+    }
+
+    pub fn color_mode(&self) -> ColorMode {
+        self.color_mode
+    }
+
+    /// Override the current theme's color mode.
+    pub fn set_color_mode(&mut self, queue: &wgpu::Queue, color_mode: ColorMode) {
+        self.color_mode = color_mode;
+        self.update_geometry(queue);
+    }
+
+    /// Rebuild the transforms and projection for the current layout and
+    /// size.
+    fn update_geometry(&mut self, queue: &wgpu::Queue) {
+        let extent = layout::view_extent(self.size.0, self.size.1);
+        let params = LayoutParams {
+            radius: self.layout_params.radius * self.radius_scale,
+            ..self.layout_params
+        };
+        let geometry = layout::build(self.layout, &params, self.num_bars, extent);
+        let params = Params::new(
+            layout::projection(extent),
+            self.num_bars,
+            &geometry,
+            self.color_mode,
+        );
+
+        queue.write_buffer(
+            &self.buffers.transforms,
+            0,
+            bytemuck::cast_slice(&geometry.transforms),
+        );
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(&params));
+        self.geometry = geometry;
+    }
+
+    /// Draw with a user WGSL file instead of the built-in shader, and
+    /// recompile it whenever the file changes.
+    ///
+    /// The file must declare the same bindings and `vs_main` / `fs_main`
+    /// entry points as `shader.wgsl`. `None` returns to the built-in shader.
+    pub fn set_shader(&mut self, device: &wgpu::Device, path: Option<PathBuf>) {
+        match path {
+            Some(path) => {
+                self.shader_watcher = Some(FileWatcher::new(path));
+                self.load_shader(device);
+            }
+            None => {
+                self.shader_watcher = None;
+                self.shader_error = None;
+                self.pipeline = create_pipeline(
+                    device,
+                    &self.pipeline_layout,
+                    self.scene,
+                    include_str!("shader.wgsl"),
+                );
+            }
+        }
+    }
+
+    /// (Re)compile the watched shader file, keeping the old pipeline on
+    /// failure.
+    fn load_shader(&mut self, device: &wgpu::Device) {
+        let Some(watcher) = &self.shader_watcher else {
+            return;
+        };
+        let path = watcher.path().display().to_string();
+
+        let result = std::fs::read_to_string(watcher.path())
+            .map_err(|e| format!("Failed to read {path}: {e}"))
+            .and_then(|source| {
+                with_validation(device, || {
+                    create_pipeline(device, &self.pipeline_layout, self.scene, &source)
+                })
+            });
+
+        match result {
+            Ok(pipeline) => {
+                println!("Loaded shader {path}");
+                self.pipeline = pipeline;
+                self.shader_error = None;
+            }
+            Err(e) => {
+                eprintln!("Shader error in {path}:\n{e}");
+                self.shader_error = Some(e);
+            }
+        }
+    }
+}
+
+impl Visualizer for Bars {
+    fn name(&self) -> &str {
+        "bars"
+    }
+
+    fn set_scene_format(&mut self, device: &wgpu::Device, scene: SceneFormat) {
+        self.scene = scene;
+        // The built-in shader first, so a broken user shader can't leave a
+        // pipeline with the old format behind
+        self.pipeline = create_pipeline(
+            device,
+            &self.pipeline_layout,
+            scene,
+            include_str!("shader.wgsl"),
+        );
+        self.load_shader(device);
+    }
+
+    fn resize(&mut self, _device: &wgpu::Device, queue: &wgpu::Queue, width: u32, height: u32) {
+        // Keep bars and rings undistorted in the new aspect ratio
+        self.size = (width, height);
+        self.update_geometry(queue);
+    }
+
+    fn set_theme(&mut self, queue: &wgpu::Queue, theme: &Theme) {
+        queue.write_texture(
+            self.palette_texture.as_image_copy(),
+            &theme.palette(),
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(theme::PALETTE_SIZE * 4),
+                rows_per_image: None,
+            },
+            self.palette_texture.size(),
+        );
+        self.set_color_mode(queue, theme.color_mode);
+    }
+
+    fn update(&mut self, _device: &wgpu::Device, queue: &wgpu::Queue, frame: &AnalysisFrame) {
+        // Ignore any magnitudes beyond the bar count
+        let magnitudes = &frame.bars[..frame.bars.len().min(self.num_bars as usize)];
+        queue.write_buffer(
+            &self.buffers.magnitudes,
+            0,
+            bytemuck::cast_slice(magnitudes),
+        );
+
+        // Swell the ring on the beat; the geometry only changes while it
+        // moves
+        let pulse = frame.tempo.map_or(0.0, |t| t.pulse());
+        let radius_scale = 1.0 + self.beat_pulse * pulse;
+        if (radius_scale - self.radius_scale).abs() > 1e-4 {
+            self.radius_scale = radius_scale;
+            self.update_geometry(queue);
+        }
+    }
+
+    fn render(&self, pass: &mut wgpu::RenderPass) {
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.buffers.bind_group, &[]);
+        // 6 vertices per quad, one instance per bar
+        pass.draw(0..6, 0..self.num_bars);
+    }
+
+    fn reload(&mut self, device: &wgpu::Device) {
+        if self.shader_watcher.as_mut().is_some_and(|w| w.changed()) {
+            self.load_shader(device);
+        }
+    }
+
+    fn error(&self) -> Option<&str> {
+        self.shader_error.as_deref()
+    }
+}
+
+/// Storage buffers sized by the bar count, and the bar bind group that
+/// references them.
+struct BarBuffers {
+    magnitudes: wgpu::Buffer,
+    transforms: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl BarBuffers {
+    /// Zeroed buffers for `num_bars` bars; fill the transforms with
+    /// `update_geometry`.
+    fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        num_bars: u32,
+        params: &wgpu::Buffer,
+        palette_view: &wgpu::TextureView,
+        palette_sampler: &wgpu::Sampler,
+    ) -> Self {
+        let storage = |label, size| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size: num_bars as u64 * size as u64,
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        };
+        let magnitudes = storage("Magnitudes", std::mem::size_of::<f32>());
+        let transforms = storage("Transforms", std::mem::size_of::<Mat4>());
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: magnitudes.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: params.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: transforms.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(palette_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::Sampler(palette_sampler),
+                },
+            ],
+        });
+
+        Self {
+            magnitudes,
+            transforms,
+            bind_group,
+        }
+    }
+}
+
+/// Build the bar pipeline from WGSL `source`. Invalid source is reported
+/// through wgpu's error handling (a panic unless inside [`with_validation`]).
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    scene: SceneFormat,
+    source: &str,
+) -> wgpu::RenderPipeline {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Shader"),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Render Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vs_main"),
+            buffers: &[],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some("fs_main"),
+            compilation_options: Default::default(),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: scene.format,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            ..Default::default()
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: scene.sample_count,
+            ..Default::default()
+        },
+        multiview_mask: None,
+        cache: None,
+    })
+}
@@ -0,0 +1,460 @@
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+// ---- Tuning knobs (change these to taste) ----------------------------------
+
+/// Samples per onset-detection FFT frame.
+const FRAME_SIZE: usize = 1024;
+/// Samples between frames: about 11 ms at 44.1-48 kHz.
+const HOP: usize = 512;
+/// Tempo range searched, in beats per minute.
+pub const MIN_BPM: f32 = 60.0;
+pub const MAX_BPM: f32 = 200.0;
+/// Tempo favoured when a multiple of it fits the onsets about as well, so
+/// 140 BPM isn't taken for 70.
+const PRIOR_BPM: f32 = 120.0;
+/// Width of that preference, in octaves.
+const PRIOR_OCTAVES: f32 = 1.0;
+/// Seconds of onset strength the tempo is estimated from.
+const HISTORY_SECONDS: f32 = 8.0;
+/// Seconds of onset strength needed before the first estimate.
+const MIN_HISTORY_SECONDS: f32 = 2.0;
+/// Frames between tempo estimates; the beat phase is updated every frame.
+const TEMPO_INTERVAL: usize = 8;
+/// Confidence below which no beats are reported.
+pub const MIN_CONFIDENCE: f32 = 0.2;
+
+/// Frames after a candidate onset that must be seen before picking it.
+const PEAK_LOOKAHEAD: usize = 2;
+/// Frames before a candidate averaged into its threshold.
+const PEAK_LOOKBEHIND: usize = 8;
+/// Part of the recent loudest onset a peak must rise above the average by.
+const PEAK_DELTA: f32 = 0.1;
+/// Onset strength below which nothing is picked (silence, dither).
+const MIN_ONSET_STRENGTH: f32 = 0.01;
+/// Shortest time between two onsets, in seconds.
+const MIN_ONSET_GAP: f32 = 0.05;
+/// Fraction of the loudest-onset envelope kept per second.
+const ENVELOPE_DECAY: f32 = 0.5;
+
+// ----------------------------------------------------------------------------
+
+/// How the onset strength of a frame is measured.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum OnsetMethod {
+    /// Rise of the log-compressed magnitude spectrum since the previous
+    /// frame. Robust, and good at drums.
+    #[default]
+    SpectralFlux,
+    /// Distance of the spectrum from a prediction by the previous two
+    /// frames' magnitudes and phases, so soft note changes count too.
+    ComplexDomain,
+}
+
+/// Something the tracker noticed, timed in seconds of audio since it
+/// started.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BeatEvent {
+    /// A sudden rise in energy: a hit or a note starting. `strength` is
+    /// relative to the loudest recent onset, up to 1.
+    Onset { time: f32, strength: f32 },
+    /// A beat of the tracked tempo, whether or not anything was played on
+    /// it.
+    Beat { time: f32 },
+}
+
+/// The tempo being tracked.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Tempo {
+    pub bpm: f32,
+    /// How far through the current beat: 0 on a beat, rising to 1 at the
+    /// next.
+    pub phase: f32,
+    /// How periodic the onsets are: 0 = no steady pulse, 1 = a metronome.
+    pub confidence: f32,
+}
+
+impl Tempo {
+    /// 1 on a beat, falling quickly to 0 before the next; always 0 below
+    /// [`MIN_CONFIDENCE`]. A drive for effects that hit on the beat.
+    pub fn pulse(&self) -> f32 {
+        if self.confidence < MIN_CONFIDENCE {
+            return 0.0;
+        }
+        (1.0 - self.phase).clamp(0.0, 1.0).powi(4)
+    }
+}
+
+/// Onset detection and beat tracking on a stream of mono samples.
+///
+/// Onsets are peaks in a spectral flux (or complex-domain) onset function
+/// above an adaptive threshold. The tempo is the autocorrelation peak of
+/// the last few seconds of that function, and the beat phase the offset
+/// that best lines a comb of beats at that tempo up with it:
+///
+/// ```
+/// use audio_visualizer::beat::{BeatEvent, BeatTracker};
+///
+/// // 8 seconds of clicks at 120 BPM
+/// let sample_rate = 48_000;
+/// let mut clicks = vec![0.0; sample_rate as usize * 8];
+/// for beat in clicks.chunks_mut(sample_rate as usize / 2) {
+///     beat[0] = 1.0;
+/// }
+///
+/// let mut tracker = BeatTracker::new(sample_rate);
+/// let mut beats = 0;
+/// for chunk in clicks.chunks(800) {
+///     let events = tracker.process(chunk);
+///     beats += events.iter().filter(|e| matches!(e, BeatEvent::Beat { .. })).count();
+/// }
+/// let tempo = tracker.tempo().unwrap();
+/// assert!((tempo.bpm - 120.0).abs() < 2.0);
+/// assert!(beats > 0);
+/// ```
+pub struct BeatTracker {
+    sample_rate: u32,
+    method: OnsetMethod,
+    fft: Arc<dyn rustfft::Fft<f32>>,
+    window: Vec<f32>,
+    scratch: Vec<Complex<f32>>,
+    /// Samples not yet in a frame, oldest first.
+    pending: Vec<f32>,
+    /// Spectra of the previous two frames, newest first.
+    previous: [Vec<Complex<f32>>; 2],
+
+    /// Frames analyzed so far.
+    frames: usize,
+    /// Onset strength of the last `history_len` frames, oldest first.
+    strengths: VecDeque<f32>,
+    history_len: usize,
+    /// Decaying maximum of the onset strength.
+    envelope: f32,
+    /// Frame of the last picked onset.
+    last_onset: Option<usize>,
+
+    /// Beat period in frames and confidence, once estimated.
+    period: Option<(f32, f32)>,
+    /// Frames since the last beat, as of the newest frame.
+    beat_offset: usize,
+
+    events: Vec<BeatEvent>,
+}
+
+impl BeatTracker {
+    pub fn new(sample_rate: u32) -> Self {
+        Self::with_method(sample_rate, OnsetMethod::default())
+    }
+
+    pub fn with_method(sample_rate: u32, method: OnsetMethod) -> Self {
+        let fft = FftPlanner::new().plan_fft_forward(FRAME_SIZE);
+        let scratch = vec![Complex::new(0.0, 0.0); fft.get_inplace_scratch_len()];
+        let window = (0..FRAME_SIZE)
+            .map(|i| {
+                let t = i as f32 / (FRAME_SIZE - 1) as f32;
+                0.5 * (1.0 - (std::f32::consts::TAU * t).cos())
+            })
+            .collect();
+        let silent = vec![Complex::new(0.0, 0.0); FRAME_SIZE / 2];
+        let frame_rate = sample_rate.max(1) as f32 / HOP as f32;
+
+        Self {
+            sample_rate: sample_rate.max(1),
+            method,
+            fft,
+            window,
+            scratch,
+            pending: Vec::with_capacity(FRAME_SIZE * 2),
+            previous: [silent.clone(), silent],
+            frames: 0,
+            strengths: VecDeque::new(),
+            history_len: (HISTORY_SECONDS * frame_rate) as usize,
+            envelope: 0.0,
+            last_onset: None,
+            period: None,
+            beat_offset: 0,
+            events: Vec::new(),
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn method(&self) -> OnsetMethod {
+        self.method
+    }
+
+    /// Onset strength frames per second.
+    fn frame_rate(&self) -> f32 {
+        self.sample_rate as f32 / HOP as f32
+    }
+
+    /// Feed the samples that arrived since the last call, of any length,
+    /// and return what happened in them.
+    pub fn process(&mut self, samples: &[f32]) -> &[BeatEvent] {
+        self.events.clear();
+        self.pending.extend_from_slice(samples);
+        let mut start = 0;
+        while self.pending.len() - start >= FRAME_SIZE {
+            let strength = self.onset_strength(start);
+            self.add_frame(strength);
+            start += HOP;
+        }
+        self.pending.drain(..start);
+        &self.events
+    }
+
+    /// The tempo, once there is enough audio to estimate one.
+    pub fn tempo(&self) -> Option<Tempo> {
+        let (period, confidence) = self.period?;
+        Some(Tempo {
+            bpm: 60.0 * self.frame_rate() / period,
+            phase: (self.beat_offset as f32 / period).min(1.0),
+            confidence,
+        })
+    }
+
+    /// Start over, forgetting all audio and the tempo.
+    pub fn reset(&mut self) {
+        *self = Self::with_method(self.sample_rate, self.method);
+    }
+
+    /// Seconds from the start of the stream to the middle of `frame`.
+    fn frame_time(&self, frame: usize) -> f32 {
+        (frame * HOP + FRAME_SIZE / 2) as f32 / self.sample_rate as f32
+    }
+
+    /// Onset strength of the frame at `start` in `pending`: how much the
+    /// spectrum rose (or changed unpredictably) since the previous frames.
+    fn onset_strength(&mut self, start: usize) -> f32 {
+        let mut buffer: Vec<Complex<f32>> = self.pending[start..start + FRAME_SIZE]
+            .iter()
+            .zip(&self.window)
+            .map(|(&s, &w)| Complex::new(s * w, 0.0))
+            .collect();
+        self.fft
+            .process_with_scratch(&mut buffer, &mut self.scratch);
+        buffer.truncate(FRAME_SIZE / 2);
+        for bin in &mut buffer {
+            *bin /= FRAME_SIZE as f32;
+        }
+
+        let [previous, before] = &self.previous;
+        let strength: f32 = match self.method {
+            OnsetMethod::SpectralFlux => buffer
+                .iter()
+                .zip(previous)
+                .map(|(now, then)| (compress(now.norm()) - compress(then.norm())).max(0.0))
+                .sum(),
+            OnsetMethod::ComplexDomain => buffer
+                .iter()
+                .zip(previous)
+                .zip(before)
+                .map(|((now, then), before)| {
+                    // Same magnitude, phase advancing at the same rate
+                    let turn = match (then.norm(), before.norm()) {
+                        (a, b) if a > 0.0 && b > 0.0 => (then / a) * (before.conj() / b),
+                        _ => Complex::new(1.0, 0.0),
+                    };
+                    let predicted = then * turn;
+                    // Only rising bins, so note endings don't count
+                    if now.norm() >= then.norm() {
+                        compress((now - predicted).norm())
+                    } else {
+                        0.0
+                    }
+                })
+                .sum(),
+        };
+
+        self.previous.swap(0, 1);
+        self.previous[0] = buffer;
+        strength / (FRAME_SIZE / 2) as f32
+    }
+
+    /// Take in the onset strength of the next frame: pick the onset
+    /// `PEAK_LOOKAHEAD` frames back, re-estimate the tempo now and then
+    /// and move the beat phase on.
+    fn add_frame(&mut self, strength: f32) {
+        let frame = self.frames;
+        self.frames += 1;
+        self.strengths.push_back(strength);
+        if self.strengths.len() > self.history_len {
+            self.strengths.pop_front();
+        }
+        let decay = ENVELOPE_DECAY.powf(1.0 / self.frame_rate());
+        self.envelope = (self.envelope * decay).max(strength);
+
+        if let Some(candidate) = frame.checked_sub(PEAK_LOOKAHEAD) {
+            self.pick_onset(candidate);
+        }
+        if frame.is_multiple_of(TEMPO_INTERVAL) {
+            self.estimate_tempo();
+        }
+        self.track_phase(frame);
+    }
+
+    /// Onset strength of `frame`, or 0 if it's no longer (or not yet) in
+    /// the history.
+    fn strength(&self, frame: usize) -> f32 {
+        let first = self.frames - self.strengths.len();
+        frame
+            .checked_sub(first)
+            .and_then(|i| self.strengths.get(i))
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    /// Report `frame` as an onset if it's a local maximum that stands out
+    /// from its neighbourhood.
+    fn pick_onset(&mut self, frame: usize) {
+        let value = self.strength(frame);
+        if value < MIN_ONSET_STRENGTH {
+            return;
+        }
+        let first = frame.saturating_sub(PEAK_LOOKAHEAD);
+        let last = frame + PEAK_LOOKAHEAD;
+        // Strictly above what came before, so a plateau counts once
+        let is_peak = (first..frame).all(|f| self.strength(f) < value)
+            && (frame + 1..=last).all(|f| self.strength(f) <= value);
+        if !is_peak {
+            return;
+        }
+
+        let first = frame.saturating_sub(PEAK_LOOKBEHIND);
+        let mean =
+            (first..=last).map(|f| self.strength(f)).sum::<f32>() / (last - first + 1) as f32;
+        if value < mean + PEAK_DELTA * self.envelope {
+            return;
+        }
+
+        let min_gap = (MIN_ONSET_GAP * self.frame_rate()).ceil() as usize;
+        if self.last_onset.is_some_and(|last| frame - last < min_gap) {
+            return;
+        }
+        self.last_onset = Some(frame);
+        self.events.push(BeatEvent::Onset {
+            time: self.frame_time(frame),
+            strength: (value / self.envelope.max(f32::EPSILON)).min(1.0),
+        });
+    }
+
+    /// Estimate the beat period from the autocorrelation of the onset
+    /// strength, weighted towards `PRIOR_BPM`.
+    fn estimate_tempo(&mut self) {
+        let frame_rate = self.frame_rate();
+        if (self.strengths.len() as f32) < MIN_HISTORY_SECONDS * frame_rate {
+            return;
+        }
+
+        // Above-average strength only, smoothed a little so onsets that
+        // fall either side of a frame boundary still line up, then made
+        // zero-mean so a steady level doesn't correlate with itself
+        let mean = self.strengths.iter().sum::<f32>() / self.strengths.len() as f32;
+        let rectified: Vec<f32> = self.strengths.iter().map(|s| (s - mean).max(0.0)).collect();
+        let mut smoothed: Vec<f32> = (0..rectified.len())
+            .map(|i| {
+                let at = |j: usize| rectified.get(j).copied().unwrap_or(0.0);
+                (at(i.wrapping_sub(1)) + 2.0 * at(i) + at(i + 1)) / 4.0
+            })
+            .collect();
+        let mean = smoothed.iter().sum::<f32>() / smoothed.len() as f32;
+        smoothed.iter_mut().for_each(|s| *s -= mean);
+
+        let correlation = |lag: usize| -> f32 {
+            smoothed[lag..]
+                .iter()
+                .zip(&smoothed)
+                .map(|(a, b)| a * b)
+                .sum()
+        };
+        let energy = correlation(0);
+        if energy <= f32::EPSILON {
+            self.period = None;
+            return;
+        }
+
+        let min_lag = (60.0 * frame_rate / MAX_BPM).floor() as usize;
+        let max_lag = ((60.0 * frame_rate / MIN_BPM).ceil() as usize).min(smoothed.len() - 1);
+        let weight = |lag: f32| {
+            let octaves = (60.0 * frame_rate / lag / PRIOR_BPM).log2() / PRIOR_OCTAVES;
+            (-0.5 * octaves * octaves).exp()
+        };
+        // One either side of the range, for the interpolation
+        let scores: Vec<f32> = (min_lag - 1..=max_lag + 1)
+            .map(|lag| correlation(lag.min(smoothed.len() - 1)) * weight(lag as f32))
+            .collect();
+        let Some(best) = (1..scores.len() - 1).max_by(|&a, &b| scores[a].total_cmp(&scores[b]))
+        else {
+            return;
+        };
+
+        // Parabola through the peak and its neighbours, for a period
+        // between whole frames
+        let (left, peak, right) = (scores[best - 1], scores[best], scores[best + 1]);
+        let curvature = left - 2.0 * peak + right;
+        let shift = if curvature < 0.0 {
+            (0.5 * (left - right) / curvature).clamp(-0.5, 0.5)
+        } else {
+            0.0
+        };
+        let lag = (min_lag - 1 + best) as f32 + shift;
+        let confidence = (correlation(min_lag - 1 + best) / energy).clamp(0.0, 1.0);
+
+        // Settle on a steady tempo rather than jittering between frames
+        let period = match self.period {
+            Some((old, _)) if (lag / old - 1.0).abs() < 0.04 => old + 0.25 * (lag - old),
+            _ => lag,
+        };
+        self.period = Some((period, confidence));
+    }
+
+    /// Find how long ago the last beat was by lining a comb of beats at
+    /// the current period up with the onset strength, and report a beat
+    /// when that wraps around.
+    fn track_phase(&mut self, frame: usize) {
+        const BEATS: usize = 4;
+        let Some((period, confidence)) = self.period else {
+            return;
+        };
+
+        // The newest beat must match exactly, older ones within a frame
+        let near = |f: usize| {
+            (f.saturating_sub(1)..=f + 1)
+                .map(|f| self.strength(f))
+                .fold(0.0, f32::max)
+        };
+        let score = |offset: usize| -> f32 {
+            let Some(newest) = frame.checked_sub(offset) else {
+                return 0.0;
+            };
+            let older: f32 = (1..=BEATS)
+                .filter_map(|k| newest.checked_sub((k as f32 * period).round() as usize))
+                .map(near)
+                .sum();
+            self.strength(newest) + older
+        };
+        let offsets = 0..(period.round() as usize).max(1);
+        let Some(offset) = offsets.max_by(|&a, &b| score(a).total_cmp(&score(b)).then(b.cmp(&a)))
+        else {
+            return;
+        };
+
+        let wrapped = (offset as f32) < self.beat_offset as f32 - period / 2.0;
+        self.beat_offset = offset;
+        if wrapped && confidence >= MIN_CONFIDENCE {
+            self.events.push(BeatEvent::Beat {
+                time: self.frame_time(frame - offset),
+            });
+        }
+    }
+}
+
+/// Log compression of a bin magnitude, so quiet partials count next to
+/// loud ones.
+fn compress(magnitude: f32) -> f32 {
+    (1000.0 * magnitude).ln_1p()
+}
@@ -0,0 +1,472 @@
+/// Format of the scene and blur textures. Values above 1.0 survive until
+/// the composite, so bright bars can glow past white.
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Brightness at which bars start to glow, until changed.
+pub const DEFAULT_THRESHOLD: f32 = 0.6;
+/// Strength of the glow, until changed.
+pub const DEFAULT_INTENSITY: f32 = 1.0;
+
+/// Most blur levels, each half the size of the previous one.
+const MAX_MIP_LEVELS: u32 = 6;
+
+/// Whether `adapter` can render to, filter and blend `HDR_FORMAT`. Without
+/// that the renderer draws straight to the target and bloom is unavailable.
+pub fn supported(adapter: &wgpu::Adapter) -> bool {
+    let features = adapter.get_texture_format_features(HDR_FORMAT);
+    features
+        .allowed_usages
+        .contains(wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING)
+        && features.flags.contains(
+            wgpu::TextureFormatFeatureFlags::FILTERABLE
+                | wgpu::TextureFormatFeatureFlags::BLENDABLE,
+        )
+}
+
+/// Uniforms shared with `bloom.wgsl`.
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct BloomParams {
+    threshold: f32,
+    knee: f32,
+    intensity: f32,
+    _pad: f32,
+}
+
+/// Size-dependent textures and the bind groups that read them.
+struct Targets {
+    scene_view: wgpu::TextureView,
+    /// Writes go to `mip_views[i]`; each reads the previous level.
+    mip_views: Vec<wgpu::TextureView>,
+    prefilter_bind_group: wgpu::BindGroup,
+    /// `[i]` reads mip `i` (downsampling into `i + 1`).
+    mip_bind_groups: Vec<wgpu::BindGroup>,
+    composite_bind_group: wgpu::BindGroup,
+}
+
+/// HDR scene target plus a multi-pass bloom: a bright-pass into half
+/// resolution, a chain of downsamples, tent-filtered upsamples added back
+/// up the chain, then a composite onto the real target.
+///
+/// The scene is always drawn into the HDR texture when bloom is supported;
+/// with bloom disabled the composite is a plain copy.
+pub struct Bloom {
+    pub enabled: bool,
+    /// Brightness (0..1 for most themes) at which bars start to glow.
+    pub threshold: f32,
+    /// Strength of the glow added onto the scene.
+    pub intensity: f32,
+    size: (u32, u32),
+
+    params_buffer: wgpu::Buffer,
+    sampler: wgpu::Sampler,
+    layout: wgpu::BindGroupLayout,
+    composite_layout: wgpu::BindGroupLayout,
+    targets: Targets,
+
+    prefilter_pipeline: wgpu::RenderPipeline,
+    downsample_pipeline: wgpu::RenderPipeline,
+    upsample_pipeline: wgpu::RenderPipeline,
+    composite_pipeline: wgpu::RenderPipeline,
+}
+
+impl Bloom {
+    pub fn new(
+        device: &wgpu::Device,
+        target_format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Bloom Params"),
+            size: std::mem::size_of::<BloomParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Bloom Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        // --- Bind group layouts ---
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let sampler_entry = wgpu::BindGroupLayoutEntry {
+            binding: 1,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            count: None,
+        };
+        let params_entry = wgpu::BindGroupLayoutEntry {
+            binding: 2,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Bloom Layout"),
+            entries: &[texture_entry(0), sampler_entry, params_entry],
+        });
+        let composite_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Bloom Composite Layout"),
+            entries: &[
+                texture_entry(0),
+                sampler_entry,
+                params_entry,
+                texture_entry(3),
+            ],
+        });
+
+        // --- Pipelines ---
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Bloom Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("bloom.wgsl").into()),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Bloom Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            immediate_size: 0,
+        });
+        let composite_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Bloom Composite Pipeline Layout"),
+                bind_group_layouts: &[&composite_layout],
+                immediate_size: 0,
+            });
+
+        let additive = wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent::REPLACE,
+        };
+
+        let pipeline = |label, fs_entry, layout, format, blend| {
+            create_pipeline(device, label, layout, &shader, fs_entry, format, blend)
+        };
+        let prefilter_pipeline = pipeline(
+            "Bloom Prefilter Pipeline",
+            "fs_prefilter",
+            &pipeline_layout,
+            HDR_FORMAT,
+            wgpu::BlendState::REPLACE,
+        );
+        let downsample_pipeline = pipeline(
+            "Bloom Downsample Pipeline",
+            "fs_downsample",
+            &pipeline_layout,
+            HDR_FORMAT,
+            wgpu::BlendState::REPLACE,
+        );
+        let upsample_pipeline = pipeline(
+            "Bloom Upsample Pipeline",
+            "fs_upsample",
+            &pipeline_layout,
+            HDR_FORMAT,
+            additive,
+        );
+        let composite_pipeline = pipeline(
+            "Bloom Composite Pipeline",
+            "fs_composite",
+            &composite_pipeline_layout,
+            target_format,
+            wgpu::BlendState::REPLACE,
+        );
+
+        let (width, height) = (width.max(1), height.max(1));
+        let targets = create_targets(
+            device,
+            &layout,
+            &composite_layout,
+            &sampler,
+            &params_buffer,
+            width,
+            height,
+        );
+
+        Self {
+            enabled: false,
+            threshold: DEFAULT_THRESHOLD,
+            intensity: DEFAULT_INTENSITY,
+            size: (width, height),
+            params_buffer,
+            sampler,
+            layout,
+            composite_layout,
+            targets,
+            prefilter_pipeline,
+            downsample_pipeline,
+            upsample_pipeline,
+            composite_pipeline,
+        }
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        let (width, height) = (width.max(1), height.max(1));
+        if (width, height) == self.size {
+            return;
+        }
+        self.targets = create_targets(
+            device,
+            &self.layout,
+            &self.composite_layout,
+            &self.sampler,
+            &self.params_buffer,
+            width,
+            height,
+        );
+        self.size = (width, height);
+    }
+
+    /// Where the scene should be drawn before [`Self::render`].
+    pub fn scene_view(&self) -> &wgpu::TextureView {
+        &self.targets.scene_view
+    }
+
+    /// Blur the bright parts of the scene and composite onto `target`.
+    pub fn render(
+        &self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+    ) {
+        let params = BloomParams {
+            threshold: self.threshold,
+            knee: (self.threshold * 0.5).max(0.01),
+            intensity: if self.enabled { self.intensity } else { 0.0 },
+            _pad: 0.0,
+        };
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(&params));
+
+        let targets = &self.targets;
+        if self.enabled {
+            let levels = targets.mip_views.len();
+
+            // Bright-pass: scene → mip 0 (half resolution)
+            draw(
+                encoder,
+                "Bloom Prefilter",
+                &targets.mip_views[0],
+                &self.prefilter_pipeline,
+                &targets.prefilter_bind_group,
+            );
+            // Down the chain
+            for i in 1..levels {
+                draw(
+                    encoder,
+                    "Bloom Downsample",
+                    &targets.mip_views[i],
+                    &self.downsample_pipeline,
+                    &targets.mip_bind_groups[i - 1],
+                );
+            }
+            // Back up, accumulating every level into mip 0
+            for i in (0..levels - 1).rev() {
+                draw(
+                    encoder,
+                    "Bloom Upsample",
+                    &targets.mip_views[i],
+                    &self.upsample_pipeline,
+                    &targets.mip_bind_groups[i + 1],
+                );
+            }
+        }
+
+        draw(
+            encoder,
+            "Bloom Composite",
+            target,
+            &self.composite_pipeline,
+            &targets.composite_bind_group,
+        );
+    }
+}
+
+/// One full-screen pass. The upsample pipeline blends onto what is there;
+/// every other pass covers the whole target.
+fn draw(
+    encoder: &mut wgpu::CommandEncoder,
+    label: &str,
+    view: &wgpu::TextureView,
+    pipeline: &wgpu::RenderPipeline,
+    bind_group: &wgpu::BindGroup,
+) {
+    let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some(label),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Load,
+                store: wgpu::StoreOp::Store,
+            },
+            depth_slice: None,
+        })],
+        depth_stencil_attachment: None,
+        timestamp_writes: None,
+        occlusion_query_set: None,
+        multiview_mask: None,
+    });
+    pass.set_pipeline(pipeline);
+    pass.set_bind_group(0, bind_group, &[]);
+    pass.draw(0..3, 0..1);
+}
+
+fn create_targets(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    composite_layout: &wgpu::BindGroupLayout,
+    sampler: &wgpu::Sampler,
+    params_buffer: &wgpu::Buffer,
+    width: u32,
+    height: u32,
+) -> Targets {
+    let texture = |label, width: u32, height: u32, mip_level_count| {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: HDR_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        })
+    };
+
+    let scene = texture("Bloom Scene", width, height, 1);
+    let scene_view = scene.create_view(&wgpu::TextureViewDescriptor::default());
+
+    // Stop before the smallest level gets below a couple of pixels
+    let (mip_width, mip_height) = ((width / 2).max(1), (height / 2).max(1));
+    let levels = MAX_MIP_LEVELS.min(mip_width.min(mip_height).max(2).ilog2());
+    let mips = texture("Bloom Mips", mip_width, mip_height, levels);
+    let mip_views: Vec<wgpu::TextureView> = (0..levels)
+        .map(|level| {
+            mips.create_view(&wgpu::TextureViewDescriptor {
+                label: Some("Bloom Mip"),
+                base_mip_level: level,
+                mip_level_count: Some(1),
+                ..Default::default()
+            })
+        })
+        .collect();
+
+    let bind_group = |source: &wgpu::TextureView| {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Bloom Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(source),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: params_buffer.as_entire_binding(),
+                },
+            ],
+        })
+    };
+    let prefilter_bind_group = bind_group(&scene_view);
+    let mip_bind_groups = mip_views.iter().map(bind_group).collect();
+
+    let composite_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Bloom Composite Bind Group"),
+        layout: composite_layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&scene_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: params_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::TextureView(&mip_views[0]),
+            },
+        ],
+    });
+
+    Targets {
+        scene_view,
+        mip_views,
+        prefilter_bind_group,
+        mip_bind_groups,
+        composite_bind_group,
+    }
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    label: &str,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    fs_entry: &str,
+    format: wgpu::TextureFormat,
+    blend: wgpu::BlendState,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some("vs_main"),
+            buffers: &[],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: Some(fs_entry),
+            compilation_options: Default::default(),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(blend),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            ..Default::default()
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview_mask: None,
+        cache: None,
+    })
+}
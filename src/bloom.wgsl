@@ -0,0 +1,88 @@
+// Bloom post-processing: bright-pass, downsample/upsample blur chain and
+// composite, all drawn as full-screen triangles.
+
+struct BloomParams {
+    // Brightness where glow starts
+    threshold: f32,
+    // Width of the soft transition below the threshold
+    knee: f32,
+    // Strength of the glow added back onto the scene
+    intensity: f32,
+    _pad: f32,
+};
+
+@group(0) @binding(0) var source: texture_2d<f32>;
+@group(0) @binding(1) var linear_sampler: sampler;
+@group(0) @binding(2) var<uniform> params: BloomParams;
+// Composite only: the finished blur
+@group(0) @binding(3) var bloom: texture_2d<f32>;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let x = f32((vertex_index << 1u) & 2u);
+    let y = f32(vertex_index & 2u);
+
+    var output: VertexOutput;
+    output.position = vec4<f32>(x * 2.0 - 1.0, y * 2.0 - 1.0, 0.0, 1.0);
+    output.uv = vec2<f32>(x, 1.0 - y);
+    return output;
+}
+
+// 4 bilinear taps = a 4x4 box of source texels
+fn downsample(uv: vec2<f32>) -> vec3<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(source));
+    var sum = textureSample(source, linear_sampler, uv + texel * vec2<f32>(-1.0, -1.0)).rgb;
+    sum += textureSample(source, linear_sampler, uv + texel * vec2<f32>(1.0, -1.0)).rgb;
+    sum += textureSample(source, linear_sampler, uv + texel * vec2<f32>(-1.0, 1.0)).rgb;
+    sum += textureSample(source, linear_sampler, uv + texel * vec2<f32>(1.0, 1.0)).rgb;
+    return sum * 0.25;
+}
+
+@fragment
+fn fs_prefilter(input: VertexOutput) -> @location(0) vec4<f32> {
+    let color = downsample(input.uv);
+    let brightness = max(color.r, max(color.g, color.b));
+
+    // Quadratic soft knee so the glow fades in instead of switching on
+    var soft = clamp(brightness - params.threshold + params.knee, 0.0, 2.0 * params.knee);
+    soft = soft * soft / (4.0 * params.knee + 0.0001);
+    let contribution = max(soft, brightness - params.threshold) / max(brightness, 0.0001);
+
+    return vec4<f32>(color * contribution, 1.0);
+}
+
+@fragment
+fn fs_downsample(input: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(downsample(input.uv), 1.0);
+}
+
+// 3x3 tent filter over the smaller mip, added onto the larger one
+@fragment
+fn fs_upsample(input: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(source));
+    let uv = input.uv;
+
+    var sum = textureSample(source, linear_sampler, uv).rgb * 4.0;
+    sum += textureSample(source, linear_sampler, uv + vec2<f32>(-texel.x, 0.0)).rgb * 2.0;
+    sum += textureSample(source, linear_sampler, uv + vec2<f32>(texel.x, 0.0)).rgb * 2.0;
+    sum += textureSample(source, linear_sampler, uv + vec2<f32>(0.0, -texel.y)).rgb * 2.0;
+    sum += textureSample(source, linear_sampler, uv + vec2<f32>(0.0, texel.y)).rgb * 2.0;
+    sum += textureSample(source, linear_sampler, uv + vec2<f32>(-texel.x, -texel.y)).rgb;
+    sum += textureSample(source, linear_sampler, uv + vec2<f32>(texel.x, -texel.y)).rgb;
+    sum += textureSample(source, linear_sampler, uv + vec2<f32>(-texel.x, texel.y)).rgb;
+    sum += textureSample(source, linear_sampler, uv + vec2<f32>(texel.x, texel.y)).rgb;
+
+    return vec4<f32>(sum / 16.0, 1.0);
+}
+
+@fragment
+fn fs_composite(input: VertexOutput) -> @location(0) vec4<f32> {
+    let scene = textureSample(source, linear_sampler, input.uv).rgb;
+    let glow = textureSample(bloom, linear_sampler, input.uv).rgb;
+    return vec4<f32>(scene + glow * params.intensity, 1.0);
+}
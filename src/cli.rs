@@ -0,0 +1,274 @@
+use std::path::PathBuf;
+
+use audio_visualizer::agc::AgcMode;
+use audio_visualizer::{BarScale, Layout, Transform};
+use clap::builder::{PossibleValuesParser, TypedValueParser};
+use clap::Parser;
+
+use crate::config::{self, Config};
+
+const KEYS: &str = "\
+Default keys (rebind them in the config file's [keys] table):
+  V              cycle bars / spectrogram / chromagram / vectorscope /
+                 Shadertoy
+  L              cycle layouts
+  T / C          cycle themes / color modes
+  G H            gain down / up
+  I  Space       cycle automatic gain off / global / per-band, freeze it
+  S D            decay down / up
+  Left Right     fewer / more bars
+  PgDn PgUp      smaller / larger FFT
+  N              bars spread over the spectrum / on piano keys
+  Y              FFT / constant-Q transform
+  B  J K  O P    bloom on/off, threshold, intensity
+  F  E R  Z X  Q W
+                 trails on/off, decay, zoom, rotation
+  A              cycle MSAA sample counts
+  1 2 3 4 5 6 7 8
+                 frequency labels, dB grid, source, frame timing, tempo,
+                 tuner, key and chord, loudness meters
+  [ ]  , .  - =  9 0
+                 ring radius, rotation, bar gap, start angle
+  M  Up Down     vectorscope mode, persistence";
+
+/// Real-time audio spectrum visualizer.
+///
+/// Plays FILE and visualizes it, or captures from an input device if no
+/// file is given.
+#[derive(Parser, Debug)]
+#[command(version, about, after_help = KEYS)]
+pub struct Cli {
+    /// WAV file to play and visualize; no other format is supported.
+    pub file: Option<String>,
+
+    /// Capture from the input device whose name contains NAME.
+    #[arg(long, value_name = "NAME", conflicts_with = "file")]
+    pub device: Option<String>,
+
+    /// List input devices and exit.
+    #[arg(long)]
+    pub list_devices: bool,
+
+    /// Samples per FFT frame, a power of two [default: 2048].
+    #[arg(long, value_name = "N", value_parser = parse_fft_size)]
+    fft_size: Option<usize>,
+
+    /// Number of bars [default: 88].
+    #[arg(long, value_name = "N", value_parser = parse_bars)]
+    bars: Option<usize>,
+
+    /// Multiplier from FFT magnitude to bar height [default: 6].
+    #[arg(long, value_parser = parse_gain)]
+    gain: Option<f32>,
+
+    /// Fraction of bar height kept per 1/60 s, from 0 up to 1 [default: 0.88].
+    #[arg(long, value_parser = parse_decay)]
+    decay: Option<f32>,
+
+    /// Scale the display to the music's level: global keeps the spectrum's
+    /// shape, per-band brings every band up to the full height
+    /// [default: off].
+    #[arg(
+        long,
+        value_name = "MODE",
+        value_parser = PossibleValuesParser::new(AgcMode::ALL.map(AgcMode::name))
+            .map(|name| AgcMode::from_name(&name).expect("listed AGC mode")),
+    )]
+    agc: Option<AgcMode>,
+
+    /// Bar arrangement [default: radial-outward].
+    #[arg(
+        long,
+        value_parser = PossibleValuesParser::new(Layout::ALL.map(Layout::name))
+            .map(|name| Layout::from_name(&name).expect("listed layout")),
+    )]
+    layout: Option<Layout>,
+
+    /// How the spectrum is split into bars: power-law spreads them from
+    /// 0 Hz up, piano puts them on the 88 keys [default: power-law].
+    #[arg(
+        long,
+        value_parser = PossibleValuesParser::new(BarScale::ALL.map(BarScale::name))
+            .map(|name| BarScale::from_name(&name).expect("listed scale")),
+    )]
+    scale: Option<BarScale>,
+
+    /// How the spectrum is computed: fft has the same resolution in Hz
+    /// everywhere, cqt --bins-per-octave bins per octave, finer in the bass
+    /// and quicker in the treble [default: fft].
+    #[arg(
+        long,
+        value_parser = PossibleValuesParser::new(Transform::ALL.map(Transform::name))
+            .map(|name| Transform::from_name(&name).expect("listed transform")),
+    )]
+    transform: Option<Transform>,
+
+    /// Bins per octave of the constant-Q transform, from 12 to 96
+    /// [default: 24].
+    #[arg(long, value_name = "N", value_parser = parse_bins_per_octave)]
+    bins_per_octave: Option<usize>,
+
+    /// Built-in theme name or TOML theme file.
+    #[arg(long, value_name = "NAME|FILE")]
+    theme: Option<String>,
+
+    /// Start in borderless fullscreen.
+    #[arg(long)]
+    fullscreen: bool,
+
+    /// Read settings from a TOML file instead of
+    /// $XDG_CONFIG_HOME/audio-visualizer/config.toml. Options given here
+    /// override it. Edits are applied while running.
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Print the settings in effect as a config file and exit.
+    #[arg(long)]
+    pub dump_config: bool,
+
+    /// Draw bars with a user WGSL shader, reloaded when it changes.
+    #[arg(long, value_name = "FILE")]
+    shader: Option<PathBuf>,
+
+    /// Full-screen `mainImage` WGSL shader fed by the audio.
+    #[arg(long, value_name = "FILE")]
+    shadertoy: Option<PathBuf>,
+
+    /// Visualizer to show [default: bars].
+    #[arg(long, value_parser = PossibleValuesParser::new(config::VIEWS))]
+    view: Option<String>,
+
+    /// Glow around bright bars.
+    #[arg(long)]
+    bloom: bool,
+
+    /// Bars leave fading trails.
+    #[arg(long)]
+    trails: bool,
+
+    /// Fraction the ring radius swells by on each beat [default: 0].
+    #[arg(long, value_name = "FRACTION", value_parser = parse_beat_pulse)]
+    beat_pulse: Option<f32>,
+
+    /// Anti-aliasing samples per pixel [default: most supported].
+    #[arg(long, value_name = "N", value_parser = parse_msaa)]
+    msaa: Option<u32>,
+
+    /// Render one frame from the middle of the WAV FILE (or silence) to a
+    /// PNG and exit.
+    #[arg(long, value_name = "OUT.png", conflicts_with_all = ["export", "device"])]
+    pub headless: Option<String>,
+
+    /// Render every frame of the WAV FILE to PNGs in OUT_DIR, or to a Y4M
+    /// stream on stdout if `-`, and exit. Only WAV files can be exported.
+    #[arg(
+        long,
+        value_name = "OUT_DIR|-",
+        requires = "file",
+        conflicts_with = "device"
+    )]
+    pub export: Option<String>,
+}
+
+impl Cli {
+    /// `--config`, or the default config file if there is one.
+    pub fn config_path(&self) -> Option<PathBuf> {
+        self.config
+            .clone()
+            .or_else(|| config::default_path().filter(|path| path.exists()))
+    }
+
+    /// Settings from the config file (or the defaults) with the options
+    /// given on the command line applied on top.
+    pub fn config(&self) -> Result<Config, String> {
+        let mut config = match self.config_path() {
+            Some(path) => Config::load(&path)?,
+            None => Config::default(),
+        };
+        self.apply(&mut config);
+        Ok(config)
+    }
+
+    /// Override `config` with the options given on the command line.
+    pub fn apply(&self, config: &mut Config) {
+        fn set<T: Clone>(value: &mut T, option: &Option<T>) {
+            if let Some(option) = option {
+                *value = option.clone();
+            }
+        }
+        set(&mut config.fft_size, &self.fft_size);
+        set(&mut config.bars, &self.bars);
+        set(&mut config.gain, &self.gain);
+        set(&mut config.decay, &self.decay);
+        set(&mut config.agc.mode, &self.agc);
+        set(&mut config.layout, &self.layout);
+        set(&mut config.scale, &self.scale);
+        set(&mut config.transform, &self.transform);
+        set(&mut config.cqt_bins_per_octave, &self.bins_per_octave);
+        set(&mut config.beat_pulse, &self.beat_pulse);
+        if self.device.is_some() {
+            config.device = self.device.clone();
+        }
+        if self.theme.is_some() {
+            config.theme = self.theme.clone();
+        }
+        if self.shader.is_some() {
+            config.shader = self.shader.clone();
+        }
+        if self.shadertoy.is_some() {
+            config.shadertoy = self.shadertoy.clone();
+        }
+        if self.view.is_some() {
+            config.view = self.view.clone();
+        }
+        if self.msaa.is_some() {
+            config.msaa = self.msaa;
+        }
+        // Flags can only switch things on
+        config.fullscreen |= self.fullscreen;
+        config.bloom.enabled |= self.bloom;
+        config.trails.enabled |= self.trails;
+    }
+}
+
+fn parse_fft_size(s: &str) -> Result<usize, String> {
+    s.parse()
+        .map_err(|_| "expected a whole number".to_string())
+        .and_then(config::check_fft_size)
+}
+
+fn parse_bars(s: &str) -> Result<usize, String> {
+    s.parse()
+        .map_err(|_| "expected a whole number".to_string())
+        .and_then(config::check_bars)
+}
+
+fn parse_bins_per_octave(s: &str) -> Result<usize, String> {
+    s.parse()
+        .map_err(|_| "expected a whole number".to_string())
+        .and_then(config::check_bins_per_octave)
+}
+
+fn parse_gain(s: &str) -> Result<f32, String> {
+    s.parse()
+        .map_err(|_| "expected a number".to_string())
+        .and_then(config::check_gain)
+}
+
+fn parse_decay(s: &str) -> Result<f32, String> {
+    s.parse()
+        .map_err(|_| "expected a number".to_string())
+        .and_then(config::check_decay)
+}
+
+fn parse_beat_pulse(s: &str) -> Result<f32, String> {
+    s.parse()
+        .map_err(|_| "expected a number".to_string())
+        .and_then(config::check_beat_pulse)
+}
+
+fn parse_msaa(s: &str) -> Result<u32, String> {
+    s.parse()
+        .map_err(|_| "expected a whole number".to_string())
+        .and_then(config::check_msaa)
+}
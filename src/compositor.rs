@@ -0,0 +1,415 @@
+use serde::{Deserialize, Serialize};
+
+use crate::visualizer::SceneFormat;
+
+/// How a layer is combined with what is already on screen.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "&'static str")]
+pub enum BlendMode {
+    /// Brightens: light adds up, black changes nothing.
+    Add,
+    /// Covers what's below where the layer is opaque.
+    Alpha,
+    /// Darkens: white changes nothing, black stays black.
+    Multiply,
+    /// Brightens without blowing out: the inverse of multiply.
+    Screen,
+}
+
+impl BlendMode {
+    pub const ALL: [BlendMode; 4] = [
+        BlendMode::Add,
+        BlendMode::Alpha,
+        BlendMode::Multiply,
+        BlendMode::Screen,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BlendMode::Add => "add",
+            BlendMode::Alpha => "alpha",
+            BlendMode::Multiply => "multiply",
+            BlendMode::Screen => "screen",
+        }
+    }
+
+    /// The blend mode called `name` (see [`BlendMode::name`]).
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|b| b.name() == name)
+    }
+
+    /// Blend state for the premultiplied color `compositor.wgsl` outputs.
+    /// The scene's alpha is left as it is.
+    fn blend_state(self) -> wgpu::BlendState {
+        use wgpu::BlendFactor::{Dst, One, OneMinusSrc, OneMinusSrcAlpha};
+        let (src_factor, dst_factor) = match self {
+            // dst + src
+            BlendMode::Add => (One, One),
+            // src + dst * (1 - src_alpha)
+            BlendMode::Alpha => (One, OneMinusSrcAlpha),
+            // dst * (src + 1 - src_alpha): where the layer is transparent,
+            // that's dst * 1
+            BlendMode::Multiply => (Dst, OneMinusSrcAlpha),
+            // 1 - (1 - dst) * (1 - src)
+            BlendMode::Screen => (One, OneMinusSrc),
+        };
+        wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor,
+                dst_factor,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::Zero,
+                dst_factor: One,
+                operation: wgpu::BlendOperation::Add,
+            },
+        }
+    }
+}
+
+impl TryFrom<String> for BlendMode {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        BlendMode::from_name(&s).ok_or_else(|| {
+            let names: Vec<&str> = BlendMode::ALL.iter().map(|b| b.name()).collect();
+            format!(
+                "unknown blend mode {s:?}, expected one of {}",
+                names.join(", ")
+            )
+        })
+    }
+}
+
+impl From<BlendMode> for &'static str {
+    fn from(blend: BlendMode) -> Self {
+        blend.name()
+    }
+}
+
+/// One visualizer in a stack of layers, drawn in its own part of the
+/// screen. In a config file this is a `[[layers]]` table:
+///
+/// ```toml
+/// [[layers]]
+/// view = "spectrogram"
+///
+/// [[layers]]
+/// view = "vectorscope"
+/// rect = [0.7, 0.0, 0.3, 0.4]
+/// opacity = 0.8
+/// blend = "add"
+/// ```
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Layer {
+    /// Name of the visualizer drawn in this layer.
+    pub view: String,
+    /// Left, top, width and height as fractions of the screen.
+    pub rect: [f32; 4],
+    /// 0 = invisible, 1 = as drawn.
+    pub opacity: f32,
+    pub blend: BlendMode,
+}
+
+impl Default for Layer {
+    fn default() -> Self {
+        Self {
+            view: "bars".to_string(),
+            rect: [0.0, 0.0, 1.0, 1.0],
+            opacity: 1.0,
+            blend: BlendMode::Alpha,
+        }
+    }
+}
+
+impl Layer {
+    /// `rect` in whole pixels of a `width`×`height` screen, clamped to the
+    /// screen and at least one pixel across: `[x, y, width, height]`.
+    pub fn viewport(&self, width: u32, height: u32) -> [u32; 4] {
+        let [x, y, w, h] = self.rect;
+        let span = |start: f32, length: f32, size: u32| {
+            let size = size.max(1);
+            let to_pixels = |f: f32| (f.clamp(0.0, 1.0) * size as f32).round() as u32;
+            let start_px = to_pixels(start).min(size - 1);
+            let end_px = to_pixels(start + length).max(start_px + 1);
+            (start_px, end_px - start_px)
+        };
+        let (x, width) = span(x, w, width);
+        let (y, height) = span(y, h, height);
+        [x, y, width, height]
+    }
+}
+
+/// Uniforms shared with `compositor.wgsl`.
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct LayerParams {
+    opacity: f32,
+    _pad: [f32; 3],
+}
+
+/// What one layer's visualizer renders into: a texture the size of its
+/// viewport, resolved from `msaa` when the scene is multisampled.
+struct LayerTarget {
+    view: wgpu::TextureView,
+    msaa: Option<wgpu::TextureView>,
+    bind_group: wgpu::BindGroup,
+}
+
+/// Draws a stack of [`Layer`]s over the scene. Each layer's visualizer is
+/// rendered into a texture of its own, which is then blended into the
+/// layer's viewport.
+pub(crate) struct Compositor {
+    layers: Vec<Layer>,
+    targets: Vec<LayerTarget>,
+    /// Screen size the viewports are for.
+    size: (u32, u32),
+    scene: SceneFormat,
+
+    sampler: wgpu::Sampler,
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipeline_layout: wgpu::PipelineLayout,
+    shader: wgpu::ShaderModule,
+    /// `[mode as usize]` blends with `mode`.
+    pipelines: Vec<wgpu::RenderPipeline>,
+}
+
+impl Compositor {
+    pub fn new(device: &wgpu::Device, scene: SceneFormat, width: u32, height: u32) -> Self {
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Layer Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Layer Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Layer Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            immediate_size: 0,
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Compositor Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("compositor.wgsl").into()),
+        });
+        let pipelines = create_pipelines(device, &pipeline_layout, &shader, scene);
+
+        Self {
+            layers: Vec::new(),
+            targets: Vec::new(),
+            size: (width.max(1), height.max(1)),
+            scene,
+            sampler,
+            bind_group_layout,
+            pipeline_layout,
+            shader,
+            pipelines,
+        }
+    }
+
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    /// Replace the stack, bottom layer first.
+    pub fn set_layers(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, layers: Vec<Layer>) {
+        self.layers = layers;
+        self.create_targets(device, queue);
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, width: u32, height: u32) {
+        self.size = (width.max(1), height.max(1));
+        self.create_targets(device, queue);
+    }
+
+    /// Rebuild the pipelines and layer textures for a new scene format or
+    /// MSAA sample count.
+    pub fn set_scene_format(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        scene: SceneFormat,
+    ) {
+        self.scene = scene;
+        self.pipelines = create_pipelines(device, &self.pipeline_layout, &self.shader, scene);
+        self.create_targets(device, queue);
+    }
+
+    /// Texture layer `i`'s visualizer renders into, and the multisampled
+    /// one to draw into first, if any.
+    pub fn target(&self, i: usize) -> (&wgpu::TextureView, Option<&wgpu::TextureView>) {
+        let target = &self.targets[i];
+        (&target.view, target.msaa.as_ref())
+    }
+
+    /// Blend every layer into its viewport of `pass`, bottom first.
+    pub fn composite(&self, pass: &mut wgpu::RenderPass) {
+        let (width, height) = self.size;
+        for (layer, target) in self.layers.iter().zip(&self.targets) {
+            let [x, y, w, h] = layer.viewport(width, height);
+            pass.set_viewport(x as f32, y as f32, w as f32, h as f32, 0.0, 1.0);
+            pass.set_pipeline(&self.pipelines[layer.blend as usize]);
+            pass.set_bind_group(0, &target.bind_group, &[]);
+            pass.draw(0..3, 0..1);
+        }
+        pass.set_viewport(0.0, 0.0, width as f32, height as f32, 0.0, 1.0);
+    }
+
+    fn create_targets(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let (width, height) = self.size;
+        self.targets = self
+            .layers
+            .iter()
+            .map(|layer| {
+                let [_, _, w, h] = layer.viewport(width, height);
+                let size = wgpu::Extent3d {
+                    width: w,
+                    height: h,
+                    depth_or_array_layers: 1,
+                };
+                let texture = |label, sample_count, usage| {
+                    device
+                        .create_texture(&wgpu::TextureDescriptor {
+                            label: Some(label),
+                            size,
+                            mip_level_count: 1,
+                            sample_count,
+                            dimension: wgpu::TextureDimension::D2,
+                            format: self.scene.format,
+                            usage,
+                            view_formats: &[],
+                        })
+                        .create_view(&wgpu::TextureViewDescriptor::default())
+                };
+                let view = texture(
+                    "Layer Texture",
+                    1,
+                    wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+                );
+                let msaa = (self.scene.sample_count > 1).then(|| {
+                    texture(
+                        "Layer MSAA",
+                        self.scene.sample_count,
+                        wgpu::TextureUsages::RENDER_ATTACHMENT,
+                    )
+                });
+
+                let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Layer Params"),
+                    size: std::mem::size_of::<LayerParams>() as u64,
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                });
+                let params = LayerParams {
+                    opacity: layer.opacity.clamp(0.0, 1.0),
+                    _pad: [0.0; 3],
+                };
+                queue.write_buffer(&params_buffer, 0, bytemuck::bytes_of(&params));
+
+                let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("Layer Bind Group"),
+                    layout: &self.bind_group_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: wgpu::BindingResource::TextureView(&view),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: wgpu::BindingResource::Sampler(&self.sampler),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 2,
+                            resource: params_buffer.as_entire_binding(),
+                        },
+                    ],
+                });
+
+                LayerTarget {
+                    view,
+                    msaa,
+                    bind_group,
+                }
+            })
+            .collect();
+    }
+}
+
+/// One pipeline per blend mode, in `BlendMode::ALL` order.
+fn create_pipelines(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    scene: SceneFormat,
+) -> Vec<wgpu::RenderPipeline> {
+    BlendMode::ALL
+        .iter()
+        .map(|blend| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Layer Pipeline"),
+                layout: Some(layout),
+                vertex: wgpu::VertexState {
+                    module: shader,
+                    entry_point: Some("vs_main"),
+                    buffers: &[],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: shader,
+                    entry_point: Some("fs_main"),
+                    compilation_options: Default::default(),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: scene.format,
+                        blend: Some(blend.blend_state()),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState {
+                    count: scene.sample_count,
+                    ..Default::default()
+                },
+                multiview_mask: None,
+                cache: None,
+            })
+        })
+        .collect()
+}
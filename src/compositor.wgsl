@@ -0,0 +1,37 @@
+// Layer compositing: a visualizer's texture drawn into its viewport at the
+// layer's opacity. The blend mode is in the pipeline's blend state.
+
+struct LayerParams {
+    opacity: f32,
+    _pad0: f32,
+    _pad1: f32,
+    _pad2: f32,
+};
+
+// Premultiplied color, cleared to transparent before the visualizer drew
+@group(0) @binding(0) var layer: texture_2d<f32>;
+@group(0) @binding(1) var layer_sampler: sampler;
+@group(0) @binding(2) var<uniform> params: LayerParams;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let x = f32((vertex_index << 1u) & 2u);
+    let y = f32(vertex_index & 2u);
+
+    var output: VertexOutput;
+    output.position = vec4<f32>(x * 2.0 - 1.0, y * 2.0 - 1.0, 0.0, 1.0);
+    output.uv = vec2<f32>(x, 1.0 - y);
+    return output;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    // Still premultiplied, so a transparent texel leaves the scene alone
+    // in every mode
+    return textureSample(layer, layer_sampler, input.uv) * params.opacity;
+}
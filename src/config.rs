@@ -0,0 +1,486 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use audio_visualizer::agc::{self, AgcMode};
+use audio_visualizer::compositor::Layer;
+use audio_visualizer::fft::BarScale;
+use audio_visualizer::layout::{Layout, LayoutParams};
+use audio_visualizer::renderer::Overlay;
+use audio_visualizer::spectrum::Transform;
+use audio_visualizer::{bloom, cqt, feedback};
+
+use crate::keys::KeyBindings;
+
+/// Smallest FFT size accepted from the command line, config and keys.
+pub const MIN_FFT_SIZE: usize = 256;
+/// Largest FFT size: everything the audio ring buffer holds.
+pub const MAX_FFT_SIZE: usize = audio_visualizer::audio::MAX_BUFFER_SIZE;
+/// Largest number of bars.
+pub const MAX_BARS: usize = 512;
+/// Range of constant-Q bins per octave: from one per semitone to eight.
+pub const MIN_BINS_PER_OCTAVE: usize = 12;
+pub const MAX_BINS_PER_OCTAVE: usize = 96;
+/// Names of the built-in visualizers, in the order `V` cycles through them.
+pub const VIEWS: [&str; 5] = [
+    "bars",
+    "spectrogram",
+    "chromagram",
+    "vectorscope",
+    "shadertoy",
+];
+
+/// Settings, from the built-in defaults, a config file and the command
+/// line, in increasing priority. Config files use the long option names as
+/// keys, plus tables for the settings that have no option; missing keys
+/// keep their defaults:
+///
+/// ```toml
+/// device = "BlackHole"   # input device name, or part of it
+/// fft-size = 4096
+/// bars = 64
+/// gain = 8.0
+/// decay = 0.9
+/// layout = "linear"
+/// scale = "piano"        # bars on the piano keys
+/// transform = "cqt"      # constant-Q: finer bass, quicker treble
+/// cqt-bins-per-octave = 48
+/// theme = "fire"         # built-in name or theme file
+/// view = "spectrogram"
+/// beat-pulse = 0.2       # ring radius swell on each beat
+///
+/// [layout-params]
+/// radius = 0.4
+/// gap = 0.1
+///
+/// [agc]
+/// mode = "global"       # or per-band: scale the display to the music
+/// speed = 5.0           # seconds to settle on a quieter passage
+///
+/// [bloom]
+/// enabled = true
+/// threshold = 0.5
+///
+/// [overlay]
+/// frequencies = true
+///
+/// [keys]
+/// gain-up = "Up"
+/// scope-persistence-up = "u"
+///
+/// # Several views at once, bottom first, instead of `view`
+/// [[layers]]
+/// view = "spectrogram"
+///
+/// [[layers]]
+/// view = "vectorscope"
+/// rect = [0.7, 0.0, 0.3, 0.4]   # left, top, width, height
+/// blend = "add"                 # or alpha, multiply, screen
+/// ```
+///
+/// `--dump-config` prints every setting in this format.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// Input device to capture from instead of the default one.
+    pub device: Option<String>,
+    pub fft_size: usize,
+    pub bars: usize,
+    pub gain: f32,
+    pub decay: f32,
+    pub layout: Layout,
+    /// How the spectrum is split into bars.
+    pub scale: BarScale,
+    /// How the spectrum is computed.
+    pub transform: Transform,
+    /// Resolution of the constant-Q transform; `--bins-per-octave` on the
+    /// command line.
+    pub cqt_bins_per_octave: usize,
+    /// Built-in theme name or theme file; `None` for the default theme.
+    pub theme: Option<String>,
+    /// User WGSL file for the bars.
+    pub shader: Option<PathBuf>,
+    /// User `mainImage` WGSL file; selects the Shadertoy view unless
+    /// `view` is set.
+    pub shadertoy: Option<PathBuf>,
+    /// Visualizer to show, one of `VIEWS`; `None` for the bars.
+    pub view: Option<String>,
+    /// MSAA samples per pixel instead of the most supported.
+    pub msaa: Option<u32>,
+    pub fullscreen: bool,
+    /// Fraction the ring radius grows by on each beat.
+    pub beat_pulse: f32,
+    pub layout_params: LayoutParams,
+    pub agc: AgcConfig,
+    pub bloom: BloomConfig,
+    pub trails: TrailsConfig,
+    pub overlay: Overlay,
+    pub keys: KeyBindings,
+    /// Visualizers drawn together, bottom first; empty for the single
+    /// `view`.
+    pub layers: Vec<Layer>,
+}
+
+/// `[agc]` table.
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct AgcConfig {
+    pub mode: AgcMode,
+    /// Seconds the display takes to adapt to a quieter signal.
+    pub speed: f32,
+    /// Limits on the factor applied on top of `gain`.
+    pub min_gain: f32,
+    pub max_gain: f32,
+}
+
+impl Default for AgcConfig {
+    fn default() -> Self {
+        Self {
+            mode: AgcMode::default(),
+            speed: agc::DEFAULT_SPEED,
+            min_gain: agc::DEFAULT_MIN_GAIN,
+            max_gain: agc::DEFAULT_MAX_GAIN,
+        }
+    }
+}
+
+/// `[bloom]` table.
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BloomConfig {
+    pub enabled: bool,
+    /// Brightness at which bars start to glow.
+    pub threshold: f32,
+    pub intensity: f32,
+}
+
+impl Default for BloomConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold: bloom::DEFAULT_THRESHOLD,
+            intensity: bloom::DEFAULT_INTENSITY,
+        }
+    }
+}
+
+/// `[trails]` table. Settings are per 1/60 s, like the feedback effect's.
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrailsConfig {
+    pub enabled: bool,
+    /// Fraction of the previous frame kept.
+    pub decay: f32,
+    /// Scale of the previous frame (> 1 = trails move outward).
+    pub zoom: f32,
+    /// Rotation of the previous frame in radians, counter-clockwise.
+    pub rotation: f32,
+    /// Movement of the previous frame in screen fractions (y up).
+    pub drift: [f32; 2],
+}
+
+impl Default for TrailsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            decay: feedback::DEFAULT_DECAY,
+            zoom: feedback::DEFAULT_ZOOM,
+            rotation: 0.0,
+            drift: [0.0, 0.0],
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            device: None,
+            fft_size: audio_visualizer::FFT_SIZE,
+            bars: audio_visualizer::NUM_BARS,
+            gain: audio_visualizer::GAIN,
+            decay: audio_visualizer::DECAY,
+            layout: audio_visualizer::LAYOUT,
+            scale: BarScale::default(),
+            transform: Transform::default(),
+            cqt_bins_per_octave: cqt::BINS_PER_OCTAVE,
+            theme: None,
+            shader: None,
+            shadertoy: None,
+            view: None,
+            msaa: None,
+            fullscreen: false,
+            beat_pulse: 0.0,
+            layout_params: LayoutParams::default(),
+            agc: AgcConfig::default(),
+            bloom: BloomConfig::default(),
+            trails: TrailsConfig::default(),
+            overlay: Overlay::default(),
+            keys: KeyBindings::default(),
+            layers: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Read a config file, rejecting unknown keys and out-of-range values.
+    pub fn load(path: &Path) -> Result<Config, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        let config: Config =
+            toml::from_str(&text).map_err(|e| format!("Invalid config {}: {e}", path.display()))?;
+        config
+            .validate()
+            .map_err(|e| format!("Invalid config {}: {e}", path.display()))?;
+        Ok(config)
+    }
+
+    /// The whole config as a config file.
+    pub fn to_toml(&self) -> String {
+        let mut value = toml::Value::try_from(self).expect("config serializes to TOML");
+        shorten_floats(&mut value);
+        toml::to_string(&value).expect("config serializes to TOML")
+    }
+
+    /// Check every value against the same limits as the command line and
+    /// keys.
+    fn validate(&self) -> Result<(), String> {
+        let key = |name: &'static str| move |e: String| format!("{name}: {e}");
+        check_fft_size(self.fft_size).map_err(key("fft-size"))?;
+        check_bars(self.bars).map_err(key("bars"))?;
+        check_gain(self.gain).map_err(key("gain"))?;
+        check_decay(self.decay).map_err(key("decay"))?;
+        check_bins_per_octave(self.cqt_bins_per_octave).map_err(key("cqt-bins-per-octave"))?;
+        if let Some(msaa) = self.msaa {
+            check_msaa(msaa).map_err(key("msaa"))?;
+        }
+        if let Some(view) = &self.view {
+            check_view(view).map_err(key("view"))?;
+        }
+
+        check_beat_pulse(self.beat_pulse).map_err(key("beat-pulse"))?;
+
+        let params = &self.layout_params;
+        check_range(params.radius, 0.02, 1.0).map_err(key("layout-params.radius"))?;
+        check_range(params.gap, 0.0, 0.95).map_err(key("layout-params.gap"))?;
+        check_range(params.spiral_turns, 0.5, 20.0).map_err(key("layout-params.spiral-turns"))?;
+        for (name, angle) in [
+            ("layout-params.rotation", params.rotation),
+            ("layout-params.start-angle", params.start_angle),
+        ] {
+            if !angle.is_finite() {
+                return Err(format!("{name}: must be a finite number of radians"));
+            }
+        }
+
+        check_range(self.agc.speed, 0.1, 600.0).map_err(key("agc.speed"))?;
+        check_range(self.agc.min_gain, 0.01, 1.0).map_err(key("agc.min-gain"))?;
+        check_range(self.agc.max_gain, 1.0, 100.0).map_err(key("agc.max-gain"))?;
+
+        check_range(self.bloom.threshold, 0.0, 2.0).map_err(key("bloom.threshold"))?;
+        check_range(self.bloom.intensity, 0.0, 5.0).map_err(key("bloom.intensity"))?;
+
+        let trails = &self.trails;
+        check_range(trails.decay, 0.0, 0.99).map_err(key("trails.decay"))?;
+        check_range(trails.zoom, 0.9, 1.1).map_err(key("trails.zoom"))?;
+        check_range(trails.rotation, -0.1, 0.1).map_err(key("trails.rotation"))?;
+        for drift in trails.drift {
+            check_range(drift, -0.1, 0.1).map_err(key("trails.drift"))?;
+        }
+
+        for (i, layer) in self.layers.iter().enumerate() {
+            let key = |name: &str| {
+                let name = format!("layers[{i}].{name}");
+                move |e: String| format!("{name}: {e}")
+            };
+            check_view(&layer.view).map_err(key("view"))?;
+            if self.layers[..i].iter().any(|l| l.view == layer.view) {
+                return Err(key("view")("is already in an earlier layer".to_string()));
+            }
+            check_rect(layer.rect).map_err(key("rect"))?;
+            check_range(layer.opacity, 0.0, 1.0).map_err(key("opacity"))?;
+        }
+        Ok(())
+    }
+}
+
+/// `$XDG_CONFIG_HOME/audio-visualizer/config.toml` or the platform's
+/// equivalent, read when there is no `--config`.
+pub fn default_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("audio-visualizer").join("config.toml"))
+}
+
+/// Print `f32` settings as their shortest form (`0.88`, not
+/// `0.8799999952316284`); TOML only has `f64`.
+fn shorten_floats(value: &mut toml::Value) {
+    match value {
+        toml::Value::Float(f) => *f = (*f as f32).to_string().parse().unwrap_or(*f),
+        toml::Value::Array(values) => values.iter_mut().for_each(shorten_floats),
+        toml::Value::Table(table) => table.iter_mut().for_each(|(_, v)| shorten_floats(v)),
+        _ => {}
+    }
+}
+
+pub fn check_fft_size(size: usize) -> Result<usize, String> {
+    if size.is_power_of_two() && (MIN_FFT_SIZE..=MAX_FFT_SIZE).contains(&size) {
+        Ok(size)
+    } else {
+        Err(format!(
+            "must be a power of two from {MIN_FFT_SIZE} to {MAX_FFT_SIZE}"
+        ))
+    }
+}
+
+pub fn check_bars(bars: usize) -> Result<usize, String> {
+    if (1..=MAX_BARS).contains(&bars) {
+        Ok(bars)
+    } else {
+        Err(format!("must be from 1 to {MAX_BARS}"))
+    }
+}
+
+pub fn check_bins_per_octave(bins: usize) -> Result<usize, String> {
+    if (MIN_BINS_PER_OCTAVE..=MAX_BINS_PER_OCTAVE).contains(&bins) {
+        Ok(bins)
+    } else {
+        Err(format!(
+            "must be from {MIN_BINS_PER_OCTAVE} to {MAX_BINS_PER_OCTAVE}"
+        ))
+    }
+}
+
+pub fn check_gain(gain: f32) -> Result<f32, String> {
+    if gain.is_finite() && gain > 0.0 {
+        Ok(gain)
+    } else {
+        Err("must be greater than 0".to_string())
+    }
+}
+
+pub fn check_decay(decay: f32) -> Result<f32, String> {
+    if (0.0..1.0).contains(&decay) {
+        Ok(decay)
+    } else {
+        Err("must be at least 0 and less than 1".to_string())
+    }
+}
+
+/// Any count is accepted; unsupported ones fall back to a lower one.
+pub fn check_msaa(samples: u32) -> Result<u32, String> {
+    if samples >= 1 {
+        Ok(samples)
+    } else {
+        Err("must be at least 1".to_string())
+    }
+}
+
+pub fn check_beat_pulse(pulse: f32) -> Result<f32, String> {
+    check_range(pulse, 0.0, 1.0)
+}
+
+fn check_view(name: &str) -> Result<(), String> {
+    if VIEWS.contains(&name) {
+        Ok(())
+    } else {
+        Err(format!("must be one of {}", VIEWS.join(", ")))
+    }
+}
+
+/// `[left, top, width, height]` inside the window, with some area.
+fn check_rect([x, y, width, height]: [f32; 4]) -> Result<(), String> {
+    let inside = |start: f32, length: f32| start >= 0.0 && length > 0.0 && start + length <= 1.0;
+    if inside(x, width) && inside(y, height) {
+        Ok(())
+    } else {
+        Err("must be [left, top, width, height] within 0 to 1".to_string())
+    }
+}
+
+fn check_range(value: f32, min: f32, max: f32) -> Result<f32, String> {
+    if (min..=max).contains(&value) {
+        Ok(value)
+    } else {
+        Err(format!("must be from {min} to {max}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `toml` as a config file, validated.
+    fn parse(toml: &str) -> Result<Config, String> {
+        let config: Config = toml::from_str(toml).map_err(|e| e.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    #[test]
+    fn fft_sizes_are_powers_of_two_in_range() {
+        for size in [MIN_FFT_SIZE, 1024, MAX_FFT_SIZE] {
+            assert_eq!(check_fft_size(size), Ok(size));
+        }
+        for size in [0, 1000, MIN_FFT_SIZE / 2, MAX_FFT_SIZE * 2] {
+            assert!(check_fft_size(size).is_err(), "{size}");
+        }
+    }
+
+    #[test]
+    fn scalar_checks_take_their_bounds_and_reject_the_rest() {
+        assert!(check_bars(0).is_err());
+        assert_eq!(check_bars(1), Ok(1));
+        assert_eq!(check_bars(MAX_BARS), Ok(MAX_BARS));
+        assert!(check_bars(MAX_BARS + 1).is_err());
+
+        assert_eq!(check_bins_per_octave(12), Ok(12));
+        assert_eq!(check_bins_per_octave(96), Ok(96));
+        for bins in [0, 11, 97] {
+            assert!(check_bins_per_octave(bins).is_err(), "{bins}");
+        }
+
+        assert_eq!(check_gain(0.5), Ok(0.5));
+        for gain in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            assert!(check_gain(gain).is_err(), "{gain}");
+        }
+
+        assert_eq!(check_decay(0.0), Ok(0.0));
+        assert_eq!(check_decay(0.99), Ok(0.99));
+        for decay in [1.0, -0.1, f32::NAN] {
+            assert!(check_decay(decay).is_err(), "{decay}");
+        }
+
+        assert!(check_msaa(0).is_err());
+        assert_eq!(check_msaa(3), Ok(3));
+
+        assert_eq!(check_beat_pulse(0.0), Ok(0.0));
+        assert_eq!(check_beat_pulse(1.0), Ok(1.0));
+        assert!(check_beat_pulse(1.5).is_err());
+        assert!(check_range(f32::NAN, 0.0, 1.0).is_err());
+    }
+
+    #[test]
+    fn rects_stay_inside_the_window() {
+        assert!(check_rect([0.0, 0.0, 1.0, 1.0]).is_ok());
+        assert!(check_rect([0.5, 0.25, 0.5, 0.5]).is_ok());
+        assert!(check_rect([0.5, 0.0, 0.6, 1.0]).is_err());
+        assert!(check_rect([-0.1, 0.0, 0.5, 0.5]).is_err());
+        assert!(check_rect([0.0, 0.0, 0.0, 1.0]).is_err());
+    }
+
+    #[test]
+    fn errors_name_the_setting() {
+        assert!(parse("").is_ok());
+        for (toml, name) in [
+            ("fft-size = 1000", "fft-size: "),
+            ("view = \"piano\"", "view: "),
+            ("[agc]\nmax-gain = 200.0", "agc.max-gain: "),
+            ("[trails]\nzoom = 2.0", "trails.zoom: "),
+            (
+                "[[layers]]\nview = \"bars\"\n[[layers]]\nview = \"bars\"",
+                "layers[1].view: ",
+            ),
+        ] {
+            let e = parse(toml).unwrap_err();
+            assert!(e.starts_with(name), "{toml}: {e}");
+        }
+    }
+}
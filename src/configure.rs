@@ -0,0 +1,122 @@
+use std::path::Path;
+
+use audio_visualizer::bars::Bars;
+use audio_visualizer::{renderer, target, theme, Analyzer, Layer};
+
+use crate::config;
+
+/// The configured theme, exiting if it can't be loaded.
+pub fn startup_theme(config: &config::Config) -> theme::Theme {
+    configured_theme(config).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(2);
+    })
+}
+
+/// An analyzer with the configured frame size, bars, gain, decay, AGC and
+/// transform.
+pub fn configured_analyzer(config: &config::Config) -> Analyzer {
+    let mut analyzer = Analyzer::new(config.fft_size, config.bars, config.gain, config.decay);
+    analyzer.set_bins_per_octave(config.cqt_bins_per_octave);
+    analyzer.set_transform(config.transform);
+    configure_agc(&mut analyzer, config);
+    analyzer
+}
+
+/// Apply the `[agc]` settings, keeping the level tracked so far.
+pub fn configure_agc(analyzer: &mut Analyzer, config: &config::Config) {
+    let agc = analyzer.agc_mut();
+    agc.mode = config.agc.mode;
+    agc.speed = config.agc.speed;
+    agc.min_gain = config.agc.min_gain;
+    agc.max_gain = config.agc.max_gain;
+}
+
+/// Resolve the `theme` setting: a built-in name, else a TOML theme file,
+/// or the first built-in theme if unset.
+pub fn configured_theme(config: &config::Config) -> Result<theme::Theme, String> {
+    let Some(name) = &config.theme else {
+        return Ok(theme::Theme::builtin().remove(0));
+    };
+    if let Some(theme) = theme::Theme::named(name) {
+        return Ok(theme);
+    }
+    theme::Theme::load(Path::new(name)).map_err(|e| {
+        let names: Vec<String> = theme::Theme::builtin()
+            .into_iter()
+            .map(|t| t.name)
+            .collect();
+        format!("{e}\nBuilt-in themes: {}", names.join(", "))
+    })
+}
+
+/// Apply the settings that live in the renderer and its effects, other
+/// than the theme, layout and bar count.
+pub fn configure_renderer<T: target::RenderTarget>(
+    renderer: &mut renderer::Renderer<T>,
+    config: &config::Config,
+) {
+    set_bloom(renderer, &config.bloom);
+    set_trails(renderer, &config.trails);
+    renderer.overlay = config.overlay;
+    set_beat_pulse(renderer, config.beat_pulse);
+    if let Some(samples) = config.msaa {
+        renderer.set_sample_count(samples);
+    }
+    if config.shader.is_some() {
+        renderer.set_bar_shader(config.shader.clone());
+    }
+    if config.shadertoy.is_some() {
+        renderer.set_shadertoy_shader(config.shadertoy.clone());
+    }
+    if let Some(view) = configured_view(config) {
+        renderer.set_view(view);
+    }
+    set_layers(renderer, &config.layers);
+}
+
+/// The view `config` asks for: `view`, or the Shadertoy view if it has a
+/// Shadertoy shader.
+pub fn configured_view(config: &config::Config) -> Option<&str> {
+    config
+        .view
+        .as_deref()
+        .or(config.shadertoy.is_some().then_some("shadertoy"))
+}
+
+pub fn set_beat_pulse<T: target::RenderTarget>(renderer: &mut renderer::Renderer<T>, pulse: f32) {
+    if let Some(bars) = renderer.visualizer_mut::<Bars>() {
+        bars.beat_pulse = pulse;
+    }
+}
+
+pub fn set_layers<T: target::RenderTarget>(renderer: &mut renderer::Renderer<T>, layers: &[Layer]) {
+    if let Err(e) = renderer.set_layers(layers.to_vec()) {
+        eprintln!("Layers: {e}");
+    }
+}
+
+pub fn set_bloom<T: target::RenderTarget>(
+    renderer: &mut renderer::Renderer<T>,
+    settings: &config::BloomConfig,
+) {
+    if let Some(bloom) = &mut renderer.bloom {
+        bloom.enabled = settings.enabled;
+        bloom.threshold = settings.threshold;
+        bloom.intensity = settings.intensity;
+    }
+}
+
+pub fn set_trails<T: target::RenderTarget>(
+    renderer: &mut renderer::Renderer<T>,
+    settings: &config::TrailsConfig,
+) {
+    let feedback = &mut renderer.feedback;
+    if feedback.enabled() != settings.enabled {
+        feedback.set_enabled(settings.enabled);
+    }
+    feedback.decay = settings.decay;
+    feedback.zoom = settings.zoom;
+    feedback.rotation = settings.rotation;
+    feedback.offset = settings.drift;
+}
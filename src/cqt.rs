@@ -0,0 +1,265 @@
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+use std::sync::Arc;
+
+use crate::fft::{self, BarScale};
+use crate::pitch;
+use crate::spectrum::SpectrumAnalyzer;
+
+// ---- Tuning knobs (change these to taste) ----------------------------------
+
+/// Bins per octave by default: two per semitone, so each bar on the piano
+/// scale averages a pair.
+pub const BINS_PER_OCTAVE: usize = 24;
+/// Centre of the lowest bin in Hz: A0, the bottom of the piano.
+const MIN_FREQUENCY: f32 = 27.5;
+/// Part of a spectral kernel's peak below which its FFT bins are dropped.
+/// Higher is faster but leaks more between bins.
+const KERNEL_THRESHOLD: f32 = 0.0054;
+
+// ----------------------------------------------------------------------------
+
+/// The FFT bins one constant-Q bin is a weighted sum of.
+struct Kernel {
+    start: usize,
+    weights: Vec<Complex<f32>>,
+}
+
+/// Constant-Q transform: bins a fixed fraction of an octave apart from A0
+/// up, each the Hann-windowed correlation of the latest samples with its
+/// frequency over as many periods as it takes to resolve the next bin, or
+/// the whole frame if that is shorter. Treble bins only look at the last
+/// few milliseconds, so they react as fast as a small FFT would.
+///
+/// Computed as in Brown and Puckette's "An efficient algorithm for the
+/// calculation of a constant Q transform": one FFT of the frame, then a
+/// sparse kernel per bin.
+pub struct CqtProcessor {
+    fft: Arc<dyn rustfft::Fft<f32>>,
+    size: usize,
+    num_bars: usize,
+    scale: BarScale,
+    sample_rate: u32,
+    bins_per_octave: usize,
+    kernels: Vec<Kernel>,
+    buffer: Vec<Complex<f32>>,
+    scratch: Vec<Complex<f32>>,
+    /// Magnitudes of the last frame, lowest bin first.
+    bins: Vec<f32>,
+    /// Magnitudes of the last frame as a Hann-windowed FFT would give them.
+    spectrum: Vec<f32>,
+}
+
+impl CqtProcessor {
+    /// `BINS_PER_OCTAVE` bins over frames of `size` samples at
+    /// `sample_rate`, grouped into `num_bars` on `scale`.
+    pub fn new(size: usize, num_bars: usize, scale: BarScale, sample_rate: u32) -> Self {
+        Self::with_bins_per_octave(size, num_bars, BINS_PER_OCTAVE, scale, sample_rate)
+    }
+
+    pub fn with_bins_per_octave(
+        size: usize,
+        num_bars: usize,
+        bins_per_octave: usize,
+        scale: BarScale,
+        sample_rate: u32,
+    ) -> Self {
+        let sample_rate = sample_rate.max(1);
+        let bins_per_octave = bins_per_octave.max(1);
+        let mut planner = FftPlanner::new();
+        let fft = planner.plan_fft_forward(size);
+        let mut scratch = vec![Complex::new(0.0, 0.0); fft.get_inplace_scratch_len()];
+        let mut buffer = vec![Complex::new(0.0, 0.0); size];
+
+        // Q: periods of a bin's frequency needed to tell it from the next
+        let q = 1.0 / (2f32.powf(1.0 / bins_per_octave as f32) - 1.0);
+        let nyquist = sample_rate as f32 / 2.0;
+        let mut kernels = Vec::new();
+        loop {
+            let frequency = bin_frequency(kernels.len(), bins_per_octave);
+            if frequency * 2f32.powf(0.5 / bins_per_octave as f32) >= nyquist {
+                break;
+            }
+
+            // A windowed complex sinusoid over the end of the frame, scaled
+            // so a sine fills the bin as much as it would an FFT bin
+            let len = ((q * sample_rate as f32 / frequency).round() as usize).clamp(2, size);
+            buffer.fill(Complex::new(0.0, 0.0));
+            for n in 0..len {
+                let window = 0.5 * (1.0 - (std::f32::consts::TAU * n as f32 / len as f32).cos());
+                let phase = std::f32::consts::TAU * frequency * n as f32 / sample_rate as f32;
+                buffer[size - len + n] = Complex::from_polar(window / len as f32, phase);
+            }
+            fft.process_with_scratch(&mut buffer, &mut scratch);
+
+            // Only the positive frequencies matter for real input
+            let half = &buffer[..=size / 2];
+            let peak = half.iter().map(|c| c.norm()).fold(0.0, f32::max);
+            let keep = |c: &Complex<f32>| c.norm() >= KERNEL_THRESHOLD * peak;
+            let start = half.iter().position(keep).unwrap_or(0);
+            let end = half.iter().rposition(keep).map_or(start, |i| i + 1);
+            kernels.push(Kernel {
+                start,
+                weights: half[start..end]
+                    .iter()
+                    .map(|c| c.conj() / size as f32)
+                    .collect(),
+            });
+        }
+
+        Self {
+            fft,
+            size,
+            num_bars,
+            scale,
+            sample_rate,
+            bins_per_octave,
+            bins: vec![0.0; kernels.len()],
+            kernels,
+            buffer,
+            scratch,
+            spectrum: vec![0.0; size / 2],
+        }
+    }
+
+    pub fn bins_per_octave(&self) -> usize {
+        self.bins_per_octave
+    }
+
+    /// Constant-Q magnitudes of the last frame, in the same units as the
+    /// bars, lowest bin first.
+    pub fn bins(&self) -> &[f32] {
+        &self.bins
+    }
+
+    /// Centre frequency in Hz of each of [`CqtProcessor::bins`].
+    pub fn bin_frequencies(&self) -> Vec<f32> {
+        (0..self.bins.len())
+            .map(|k| bin_frequency(k, self.bins_per_octave))
+            .collect()
+    }
+
+    /// Fractional index of the bin at `frequency`.
+    fn bin_position(&self, frequency: f32) -> f32 {
+        self.bins_per_octave as f32 * (frequency / MIN_FREQUENCY).log2()
+    }
+
+    /// Frequencies in Hz bounding bar `i`, the same as the FFT's bars on
+    /// each scale.
+    fn bar_range(&self, i: usize) -> (f32, f32) {
+        match self.scale {
+            BarScale::PowerLaw => {
+                let nyquist = self.sample_rate as f32 / 2.0;
+                let t0 = i as f32 / self.num_bars as f32;
+                let t1 = (i + 1) as f32 / self.num_bars as f32;
+                (t0.powf(2.0) * nyquist, t1.powf(2.0) * nyquist)
+            }
+            BarScale::Piano => {
+                let (low, high) = fft::bar_keys(i, self.num_bars);
+                (pitch::midi_frequency(low), pitch::midi_frequency(high))
+            }
+        }
+    }
+
+    /// Middle of bar `i`: halfway in Hz on the power-law scale, and in
+    /// pitch on the piano scale.
+    fn bar_centre(&self, i: usize) -> f32 {
+        let (low, high) = self.bar_range(i);
+        match self.scale {
+            BarScale::PowerLaw => (low + high) / 2.0,
+            BarScale::Piano => (low * high).sqrt(),
+        }
+    }
+
+    /// Average the bins centred within each bar. Bars narrower than a bin
+    /// take the bins interpolated at their centre instead.
+    fn group_into_bars(&self) -> Vec<f32> {
+        let mut bars = vec![0.0f32; self.num_bars];
+        let Some(last) = self.bins.len().checked_sub(1) else {
+            return bars;
+        };
+        for (i, bar) in bars.iter_mut().enumerate() {
+            let (low, high) = self.bar_range(i);
+            let start = self.bin_position(low).ceil().max(0.0) as usize;
+            let end = (self.bin_position(high).ceil().max(0.0) as usize).min(self.bins.len());
+
+            *bar = if end > start {
+                self.bins[start..end].iter().sum::<f32>() / (end - start) as f32
+            } else {
+                let centre = self.bar_centre(i);
+                let at = self.bin_position(centre).clamp(0.0, last as f32);
+                let below = at.floor() as usize;
+                let above = (below + 1).min(last);
+                let t = at - below as f32;
+                self.bins[below] * (1.0 - t) + self.bins[above] * t
+            };
+        }
+        bars
+    }
+}
+
+impl SpectrumAnalyzer for CqtProcessor {
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn num_bars(&self) -> usize {
+        self.num_bars
+    }
+
+    fn scale(&self) -> BarScale {
+        self.scale
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Analyze the last `size` of `samples`; fewer are zero-padded before
+    /// them, so the newest are always at the end of the frame.
+    fn process(&mut self, samples: &[f32]) -> Vec<f32> {
+        let n = self.size;
+        let samples = &samples[samples.len().saturating_sub(n)..];
+        self.buffer.clear();
+        self.buffer
+            .resize(n - samples.len(), Complex::new(0.0, 0.0));
+        self.buffer
+            .extend(samples.iter().map(|&s| Complex::new(s, 0.0)));
+        self.fft
+            .process_with_scratch(&mut self.buffer, &mut self.scratch);
+
+        for (bin, kernel) in self.bins.iter_mut().zip(&self.kernels) {
+            let frame = &self.buffer[kernel.start..];
+            *bin = kernel
+                .weights
+                .iter()
+                .zip(frame)
+                .map(|(w, x)| w * x)
+                .sum::<Complex<f32>>()
+                .norm();
+        }
+
+        // A Hann window is a three-tap filter in frequency, so the
+        // windowed spectrum comes from the same FFT
+        let buffer = &self.buffer;
+        for (j, bin) in self.spectrum.iter_mut().enumerate() {
+            let windowed = buffer[j] * 0.5 - (buffer[(j + n - 1) % n] + buffer[j + 1]) * 0.25;
+            *bin = windowed.norm() / n as f32;
+        }
+
+        self.group_into_bars()
+    }
+
+    fn spectrum(&self) -> &[f32] {
+        &self.spectrum
+    }
+
+    fn bar_frequencies(&self) -> Vec<f32> {
+        (0..self.num_bars).map(|i| self.bar_centre(i)).collect()
+    }
+}
+
+/// Centre frequency in Hz of bin `k`.
+fn bin_frequency(k: usize, bins_per_octave: usize) -> f32 {
+    MIN_FREQUENCY * 2f32.powf(k as f32 / bins_per_octave as f32)
+}
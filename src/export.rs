@@ -0,0 +1,210 @@
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::analysis::Analyzer;
+use crate::audio::DecodedAudio;
+use crate::pipeline::FramePipeline;
+use crate::renderer::Renderer;
+use crate::target::OffscreenTarget;
+
+/// Where exported frames go.
+pub enum ExportOutput {
+    /// `frame_000000.png`, `frame_000001.png`, ... in a directory.
+    PngSequence(PathBuf),
+    /// A raw YUV4MPEG2 stream (4:2:0) written to stdout, for piping into an
+    /// encoder, e.g. `... | ffmpeg -i - -i song.wav out.mp4`.
+    Y4mStdout,
+}
+
+/// Render every frame of `audio` offscreen at exactly `fps`, independent of
+/// how long each frame takes to render.
+///
+/// The FFT for frame `n` ends at sample `n / fps * sample_rate`, and the
+/// smoothing and shader clock are advanced by `1 / fps` each frame, so the
+/// output is the same on every run and every machine. The rest of the
+/// analysis goes through a [`FramePipeline`]: beats, key, chord and
+/// loudness are tracked from the start of the file, the pitch from the
+/// samples up to each frame, and the spectral features from each frame's
+/// FFT and the one before.
+pub fn export(
+    audio: &DecodedAudio,
+    renderer: &mut Renderer<OffscreenTarget>,
+    analyzer: &mut Analyzer,
+    fps: u32,
+    output: &ExportOutput,
+) -> io::Result<()> {
+    let fps = fps.max(1);
+    let (width, height) = renderer.size();
+    let total_frames = (audio.frames() as u64 * fps as u64).div_ceil(audio.sample_rate as u64);
+
+    let stdout = io::stdout();
+    let mut y4m = match output {
+        ExportOutput::PngSequence(dir) => {
+            std::fs::create_dir_all(dir)?;
+            None
+        }
+        ExportOutput::Y4mStdout => {
+            let mut out = io::BufWriter::new(stdout.lock());
+            write_y4m_header(&mut out, width, height, fps)?;
+            Some(out)
+        }
+    };
+
+    let mut pipeline = FramePipeline::new(audio.sample_rate, audio.channels);
+    let window = pipeline.window_size(analyzer) as isize;
+    let mut previous_end = 0isize;
+    for frame in 0..total_frames {
+        // Simulated clock: the sample at the moment this frame is shown
+        let end = (frame * audio.sample_rate as u64 / fps as u64) as isize;
+
+        let stereo = audio.stereo_range(previous_end, end);
+        let latest = audio.mono_range(end - window, end);
+        pipeline.feed(&stereo);
+        pipeline.process(analyzer, &latest, 1.0 / fps as f32);
+        pipeline.update_renderer(renderer);
+        renderer.update_stereo(&stereo);
+        previous_end = end;
+        renderer.set_time(frame as f32 / fps as f32, 1.0 / fps as f32);
+        renderer.render(analyzer.values());
+        let pixels = renderer.read_pixels();
+
+        match (&mut y4m, output) {
+            (Some(out), _) => write_y4m_frame(out, width, height, &pixels)?,
+            (None, ExportOutput::PngSequence(dir)) => {
+                let path = frame_path(dir, frame);
+                crate::target::write_png(&path, width, height, &pixels)
+                    .map_err(io::Error::other)?;
+            }
+            (None, ExportOutput::Y4mStdout) => unreachable!(),
+        }
+
+        // Progress on stderr so it doesn't corrupt a Y4M stream on stdout
+        if frame % fps as u64 == 0 || frame + 1 == total_frames {
+            eprint!("\rExporting frame {}/{}", frame + 1, total_frames);
+        }
+    }
+    eprintln!();
+
+    if let Some(mut out) = y4m {
+        out.flush()?;
+    }
+    Ok(())
+}
+
+fn frame_path(dir: &Path, frame: u64) -> PathBuf {
+    dir.join(format!("frame_{frame:06}.png"))
+}
+
+fn write_y4m_header(out: &mut impl Write, width: u32, height: u32, fps: u32) -> io::Result<()> {
+    writeln!(
+        out,
+        "YUV4MPEG2 W{width} H{height} F{fps}:1 Ip A1:1 C420jpeg"
+    )
+}
+
+/// Convert one RGBA8 frame to BT.601 limited-range 4:2:0 and write it.
+fn write_y4m_frame(out: &mut impl Write, width: u32, height: u32, rgba: &[u8]) -> io::Result<()> {
+    let (w, h) = (width as usize, height as usize);
+    let (cw, ch) = (w.div_ceil(2), h.div_ceil(2));
+
+    let rgb = |x: usize, y: usize| {
+        let i = (y * w + x) * 4;
+        (
+            rgba[i] as f32 / 255.0,
+            rgba[i + 1] as f32 / 255.0,
+            rgba[i + 2] as f32 / 255.0,
+        )
+    };
+
+    let mut y_plane = Vec::with_capacity(w * h);
+    for y in 0..h {
+        for x in 0..w {
+            let (r, g, b) = rgb(x, y);
+            y_plane.push((16.0 + 65.481 * r + 128.553 * g + 24.966 * b).round() as u8);
+        }
+    }
+
+    // Chroma is averaged over each 2x2 block (clamped at odd edges)
+    let mut u_plane = Vec::with_capacity(cw * ch);
+    let mut v_plane = Vec::with_capacity(cw * ch);
+    for cy in 0..ch {
+        for cx in 0..cw {
+            let (mut r, mut g, mut b) = (0.0, 0.0, 0.0);
+            for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                let (pr, pg, pb) = rgb((cx * 2 + dx).min(w - 1), (cy * 2 + dy).min(h - 1));
+                r += pr / 4.0;
+                g += pg / 4.0;
+                b += pb / 4.0;
+            }
+            u_plane.push((128.0 - 37.797 * r - 74.203 * g + 112.0 * b).round() as u8);
+            v_plane.push((128.0 + 112.0 * r - 93.786 * g - 18.214 * b).round() as u8);
+        }
+    }
+
+    out.write_all(b"FRAME\n")?;
+    out.write_all(&y_plane)?;
+    out.write_all(&u_plane)?;
+    out.write_all(&v_plane)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `width`×`height` RGBA frame of one colour.
+    fn solid(width: u32, height: u32, [r, g, b]: [u8; 3]) -> Vec<u8> {
+        [r, g, b, 255].repeat((width * height) as usize)
+    }
+
+    /// The Y, U and V planes of one frame.
+    fn planes(width: u32, height: u32, rgba: &[u8]) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+        let mut out = Vec::new();
+        write_y4m_frame(&mut out, width, height, rgba).unwrap();
+        let data = out.strip_prefix(b"FRAME\n").expect("frame header");
+        let luma = (width * height) as usize;
+        let chroma = (width.div_ceil(2) * height.div_ceil(2)) as usize;
+        assert_eq!(data.len(), luma + 2 * chroma);
+        let (y, uv) = data.split_at(luma);
+        let (u, v) = uv.split_at(chroma);
+        (y.to_vec(), u.to_vec(), v.to_vec())
+    }
+
+    #[test]
+    fn header_describes_the_stream() {
+        let mut out = Vec::new();
+        write_y4m_header(&mut out, 1280, 720, 60).unwrap();
+        assert_eq!(out, b"YUV4MPEG2 W1280 H720 F60:1 Ip A1:1 C420jpeg\n");
+    }
+
+    #[test]
+    fn colours_convert_to_limited_range_bt601() {
+        for (rgb, yuv) in [
+            ([0, 0, 0], [16, 128, 128]),
+            ([255, 255, 255], [235, 128, 128]),
+            ([255, 0, 0], [81, 90, 240]),
+            ([0, 255, 0], [145, 54, 34]),
+            ([0, 0, 255], [41, 240, 110]),
+        ] {
+            let (y, u, v) = planes(2, 2, &solid(2, 2, rgb));
+            assert_eq!(y, [yuv[0]; 4], "{rgb:?}");
+            assert_eq!((u[0], v[0]), (yuv[1], yuv[2]), "{rgb:?}");
+        }
+    }
+
+    #[test]
+    fn chroma_averages_each_two_by_two_block() {
+        // Left column white, right column black
+        let mut rgba = solid(2, 2, [255, 255, 255]);
+        for i in [1, 3] {
+            rgba[i * 4..i * 4 + 3].fill(0);
+        }
+        let (y, u, v) = planes(2, 2, &rgba);
+        assert_eq!(y, [235, 16, 235, 16]);
+        assert_eq!((u, v), (vec![128], vec![128]));
+
+        // Odd sizes round the chroma planes up, repeating the edge
+        let (y, u, v) = planes(3, 3, &solid(3, 3, [255, 0, 0]));
+        assert_eq!(y.len(), 9);
+        assert_eq!((u, v), (vec![90; 4], vec![240; 4]));
+    }
+}
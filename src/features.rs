@@ -0,0 +1,201 @@
+// ---- Tuning knobs (change these to taste) ----------------------------------
+
+/// Upper edges in Hz of the bass and mid bands; the treble is the rest.
+pub const BASS_MAX_FREQUENCY: f32 = 250.0;
+pub const MID_MAX_FREQUENCY: f32 = 4000.0;
+/// Part of the spectrum's magnitude below the rolloff frequency.
+const ROLLOFF_FRACTION: f32 = 0.85;
+/// Added to each power before the flatness' logarithm, so empty bins don't
+/// pull the geometric mean to 0.
+const FLATNESS_FLOOR: f32 = 1e-12;
+
+// ----------------------------------------------------------------------------
+
+/// Standard descriptors of one frame, to drive visuals with (e.g. a colour
+/// temperature from the centroid) or to export.
+///
+/// The spectral shape (centroid to flatness) is 0 in silence.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Features {
+    /// Centre of mass of the magnitude spectrum in Hz: higher is brighter.
+    pub centroid: f32,
+    /// Standard deviation of the spectrum around the centroid in Hz.
+    pub spread: f32,
+    /// Asymmetry around the centroid: positive when most of the energy is
+    /// below it with a tail above, negative the other way round.
+    pub skewness: f32,
+    /// Frequency in Hz below which `ROLLOFF_FRACTION` of the magnitude is.
+    pub rolloff: f32,
+    /// Geometric over arithmetic mean of the power spectrum, 0 for a pure
+    /// tone to 1 for flat noise.
+    pub flatness: f32,
+    /// How much the magnitudes rose since the previous frame, in the
+    /// spectrum's units; falls are ignored, so it peaks at onsets.
+    pub flux: f32,
+    /// Sign changes per sample, 0 to 1: `2 * frequency / sample_rate` for
+    /// a sine.
+    pub zero_crossing_rate: f32,
+    /// Root mean square of the samples.
+    pub rms: f32,
+    /// Energy (summed squared magnitude) below `BASS_MAX_FREQUENCY`, up to
+    /// `MID_MAX_FREQUENCY`, and above.
+    pub bass: f32,
+    pub mid: f32,
+    pub treble: f32,
+}
+
+impl Features {
+    /// Each band's share of the energy, bass first, summing to 1, or all 0
+    /// in silence.
+    pub fn band_balance(&self) -> [f32; 3] {
+        let total = self.bass + self.mid + self.treble;
+        if total > 0.0 {
+            [self.bass / total, self.mid / total, self.treble / total]
+        } else {
+            [0.0; 3]
+        }
+    }
+}
+
+/// Computes [`Features`] frame by frame from the samples and their
+/// magnitude spectrum, remembering the last spectrum for the flux.
+///
+/// ```
+/// use audio_visualizer::features::FeatureExtractor;
+/// use audio_visualizer::fft::FftProcessor;
+///
+/// let sample_rate = 48_000;
+/// let tone: Vec<f32> = (0..4096)
+///     .map(|i| (std::f32::consts::TAU * 1000.0 * i as f32 / sample_rate as f32).sin())
+///     .collect();
+///
+/// let mut fft = FftProcessor::new(4096, 64);
+/// fft.process(&tone);
+/// let features = FeatureExtractor::new(sample_rate).process(&tone, fft.spectrum());
+/// assert!((features.centroid - 1000.0).abs() < 20.0);
+/// assert!(features.mid > features.bass + features.treble);
+/// ```
+pub struct FeatureExtractor {
+    sample_rate: u32,
+    previous: Vec<f32>,
+    features: Features,
+}
+
+impl FeatureExtractor {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate: sample_rate.max(1),
+            previous: Vec::new(),
+            features: Features::default(),
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Take in a frame of `samples` and its magnitude `spectrum` (bins from
+    /// 0 Hz up to Nyquist, as from [`crate::Analyzer::spectrum`]). The flux
+    /// is 0 on the first frame, and whenever the spectrum changes size.
+    pub fn process(&mut self, samples: &[f32], spectrum: &[f32]) -> Features {
+        let mut features = spectral_shape(spectrum, self.sample_rate);
+
+        if self.previous.len() == spectrum.len() {
+            features.flux = spectrum
+                .iter()
+                .zip(&self.previous)
+                .map(|(m, p)| (m - p).max(0.0).powi(2))
+                .sum::<f32>()
+                .sqrt();
+        }
+        self.previous.clear();
+        self.previous.extend_from_slice(spectrum);
+
+        if samples.len() > 1 {
+            let crossings = samples
+                .windows(2)
+                .filter(|w| (w[0] >= 0.0) != (w[1] >= 0.0))
+                .count();
+            features.zero_crossing_rate = crossings as f32 / (samples.len() - 1) as f32;
+        }
+        if !samples.is_empty() {
+            let power = samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32;
+            features.rms = power.sqrt();
+        }
+
+        self.features = features;
+        features
+    }
+
+    /// The features of the last frame.
+    pub fn features(&self) -> Features {
+        self.features
+    }
+
+    /// Forget the last frame, e.g. after seeking.
+    pub fn reset(&mut self) {
+        self.previous.clear();
+        self.features = Features::default();
+    }
+}
+
+/// Everything [`Features`] gets from the spectrum alone, except the flux.
+fn spectral_shape(spectrum: &[f32], sample_rate: u32) -> Features {
+    let mut features = Features::default();
+    if spectrum.is_empty() {
+        return features;
+    }
+    let bin_hz = sample_rate as f32 / (2 * spectrum.len()) as f32;
+    let frequency = |k: usize| k as f32 * bin_hz;
+
+    for (k, m) in spectrum.iter().enumerate() {
+        let energy = m * m;
+        match frequency(k) {
+            f if f < BASS_MAX_FREQUENCY => features.bass += energy,
+            f if f < MID_MAX_FREQUENCY => features.mid += energy,
+            _ => features.treble += energy,
+        }
+    }
+
+    let total: f32 = spectrum.iter().sum();
+    if total <= 0.0 {
+        return features;
+    }
+
+    // Moments of the spectrum as a distribution over frequency
+    let moment = |order: i32, centre: f32| {
+        spectrum
+            .iter()
+            .enumerate()
+            .map(|(k, m)| (frequency(k) - centre).powi(order) * m)
+            .sum::<f32>()
+            / total
+    };
+    features.centroid = moment(1, 0.0);
+    features.spread = moment(2, features.centroid).sqrt();
+    if features.spread > 0.0 {
+        features.skewness = moment(3, features.centroid) / features.spread.powi(3);
+    }
+
+    let mut cumulative = 0.0;
+    let rolloff_bin = spectrum
+        .iter()
+        .position(|m| {
+            cumulative += m;
+            cumulative >= ROLLOFF_FRACTION * total
+        })
+        .unwrap_or(spectrum.len() - 1);
+    features.rolloff = frequency(rolloff_bin);
+
+    // DC says nothing about the sound's colour
+    let powers = &spectrum[1.min(spectrum.len() - 1)..];
+    let log_mean = powers
+        .iter()
+        .map(|m| (m * m + FLATNESS_FLOOR).ln())
+        .sum::<f32>()
+        / powers.len() as f32;
+    let mean = powers.iter().map(|m| m * m + FLATNESS_FLOOR).sum::<f32>() / powers.len() as f32;
+    features.flatness = (log_mean.exp() / mean).min(1.0);
+
+    features
+}
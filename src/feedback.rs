@@ -0,0 +1,362 @@
+/// Reference frame time the per-frame settings are expressed in, matching
+/// the smoother.
+const REFERENCE_FPS: f32 = 60.0;
+
+/// Trail settings until changed.
+pub const DEFAULT_DECAY: f32 = 0.9;
+pub const DEFAULT_ZOOM: f32 = 1.01;
+
+/// Uniforms shared with `feedback.wgsl`.
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct FeedbackParams {
+    decay: f32,
+    zoom: f32,
+    rotation: f32,
+    aspect: f32,
+    offset: [f32; 2],
+    _pad: [f32; 2],
+}
+
+/// Milkdrop-style trails. Bars are drawn into one of two ping-pong textures
+/// over a faded, zoomed and rotated copy of the other (the previous frame),
+/// and the result is added onto the background.
+///
+/// Settings are per 1/60 s and scaled by the real frame time, so trails
+/// look the same at any frame rate.
+pub struct Feedback {
+    enabled: bool,
+    /// Fraction of the previous frame kept (0 = no trails, 1 = forever).
+    pub decay: f32,
+    /// Scale applied to the previous frame (> 1 = trails move outward).
+    pub zoom: f32,
+    /// Rotation of the previous frame in radians, counter-clockwise.
+    pub rotation: f32,
+    /// Drift of the previous frame in screen fractions (y up).
+    pub offset: [f32; 2],
+    size: (u32, u32),
+    format: wgpu::TextureFormat,
+
+    /// Index of the texture written this frame.
+    current: usize,
+    /// Whether the previous texture holds a frame worth fading in.
+    has_history: bool,
+    /// Whether a frame has been drawn since the last reset.
+    primed: bool,
+
+    params_buffer: wgpu::Buffer,
+    texture_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    views: [wgpu::TextureView; 2],
+    /// `[i]` reads texture `i`.
+    bind_groups: [wgpu::BindGroup; 2],
+
+    shader: wgpu::ShaderModule,
+    pipeline_layout: wgpu::PipelineLayout,
+    fade_pipeline: wgpu::RenderPipeline,
+    composite_pipeline: wgpu::RenderPipeline,
+}
+
+impl Feedback {
+    /// `format` and `sample_count` are those of the bar passes, so bars can
+    /// be drawn straight into the trail textures.
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Feedback Params"),
+            size: std::mem::size_of::<FeedbackParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Feedback Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let texture_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Feedback Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Feedback Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("feedback.wgsl").into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Feedback Pipeline Layout"),
+            bind_group_layouts: &[&texture_layout],
+            immediate_size: 0,
+        });
+
+        let (fade_pipeline, composite_pipeline) =
+            create_pipelines(device, &pipeline_layout, &shader, format, sample_count);
+
+        let (width, height) = (width.max(1), height.max(1));
+        let (views, bind_groups) = create_textures(
+            device,
+            &texture_layout,
+            &sampler,
+            &params_buffer,
+            format,
+            width,
+            height,
+        );
+
+        Self {
+            enabled: false,
+            decay: DEFAULT_DECAY,
+            zoom: DEFAULT_ZOOM,
+            rotation: 0.0,
+            offset: [0.0, 0.0],
+            size: (width, height),
+            format,
+            current: 0,
+            has_history: false,
+            primed: false,
+            params_buffer,
+            texture_layout,
+            sampler,
+            views,
+            bind_groups,
+            shader,
+            pipeline_layout,
+            fade_pipeline,
+            composite_pipeline,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Turn trails on or off. Turning them on starts from a clean slate.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.primed = false;
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        let (width, height) = (width.max(1), height.max(1));
+        if (width, height) == self.size {
+            return;
+        }
+        let (views, bind_groups) = create_textures(
+            device,
+            &self.texture_layout,
+            &self.sampler,
+            &self.params_buffer,
+            self.format,
+            width,
+            height,
+        );
+        self.views = views;
+        self.bind_groups = bind_groups;
+        self.size = (width, height);
+        self.primed = false;
+    }
+
+    /// Rebuild the pipelines for bar passes with a new MSAA sample count.
+    pub fn set_sample_count(&mut self, device: &wgpu::Device, sample_count: u32) {
+        (self.fade_pipeline, self.composite_pipeline) = create_pipelines(
+            device,
+            &self.pipeline_layout,
+            &self.shader,
+            self.format,
+            sample_count,
+        );
+    }
+
+    /// Swap the ping-pong textures. Call once per frame before drawing.
+    pub fn advance(&mut self) {
+        self.has_history = self.primed;
+        self.primed = true;
+        self.current = 1 - self.current;
+    }
+
+    /// Texture the bar pass should resolve into this frame, drawing
+    /// [`Self::fade`] first.
+    pub fn current_view(&self) -> &wgpu::TextureView {
+        &self.views[self.current]
+    }
+
+    /// Draw the faded, transformed previous frame into `pass`, which covers
+    /// the whole of [`Self::current_view`]. `dt` is the time since the
+    /// previous frame in seconds.
+    pub fn fade(&self, queue: &wgpu::Queue, pass: &mut wgpu::RenderPass, dt: f32) {
+        let frames = dt * REFERENCE_FPS;
+        let (width, height) = self.size;
+        let params = FeedbackParams {
+            decay: if self.has_history {
+                self.decay.powf(frames)
+            } else {
+                0.0
+            },
+            zoom: self.zoom.powf(frames),
+            rotation: self.rotation * frames,
+            aspect: width as f32 / height as f32,
+            offset: self.offset.map(|o| o * frames),
+            _pad: [0.0; 2],
+        };
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(&params));
+
+        pass.set_pipeline(&self.fade_pipeline);
+        pass.set_bind_group(0, &self.bind_groups[1 - self.current], &[]);
+        pass.draw(0..3, 0..1);
+    }
+
+    /// Add the current texture (bars plus trails) onto whatever `pass`
+    /// has drawn so far.
+    pub fn composite(&self, pass: &mut wgpu::RenderPass) {
+        pass.set_pipeline(&self.composite_pipeline);
+        pass.set_bind_group(0, &self.bind_groups[self.current], &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+fn create_textures(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    sampler: &wgpu::Sampler,
+    params_buffer: &wgpu::Buffer,
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+) -> ([wgpu::TextureView; 2], [wgpu::BindGroup; 2]) {
+    let views = [0, 1].map(|_| {
+        device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("Feedback Texture"),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default())
+    });
+
+    let bind_groups = [0, 1].map(|i| {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Feedback Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&views[i]),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: params_buffer.as_entire_binding(),
+                },
+            ],
+        })
+    });
+
+    (views, bind_groups)
+}
+
+/// The fade and (additive) composite pipelines.
+fn create_pipelines(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+) -> (wgpu::RenderPipeline, wgpu::RenderPipeline) {
+    let additive = wgpu::BlendState {
+        color: wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::One,
+            dst_factor: wgpu::BlendFactor::One,
+            operation: wgpu::BlendOperation::Add,
+        },
+        alpha: wgpu::BlendComponent::REPLACE,
+    };
+    let pipeline = |label, fs_entry, blend| {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: Some(fs_entry),
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(blend),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
+            multiview_mask: None,
+            cache: None,
+        })
+    };
+
+    (
+        pipeline(
+            "Feedback Fade Pipeline",
+            "fs_fade",
+            wgpu::BlendState::REPLACE,
+        ),
+        pipeline("Feedback Composite Pipeline", "fs_composite", additive),
+    )
+}
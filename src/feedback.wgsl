@@ -0,0 +1,59 @@
+// Feedback trails: the previous frame is faded and transformed into the
+// current one, then the result is added onto the scene.
+
+struct FeedbackParams {
+    // Fraction of the previous frame kept this frame
+    decay: f32,
+    // > 1 grows the trails outward, < 1 pulls them in
+    zoom: f32,
+    // Radians this frame, counter-clockwise
+    rotation: f32,
+    // Width / height, so rotation stays circular
+    aspect: f32,
+    // Drift this frame in uv units (y up)
+    offset: vec2<f32>,
+    _pad: vec2<f32>,
+};
+
+@group(0) @binding(0) var previous: texture_2d<f32>;
+@group(0) @binding(1) var linear_sampler: sampler;
+@group(0) @binding(2) var<uniform> params: FeedbackParams;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let x = f32((vertex_index << 1u) & 2u);
+    let y = f32(vertex_index & 2u);
+
+    var output: VertexOutput;
+    output.position = vec4<f32>(x * 2.0 - 1.0, y * 2.0 - 1.0, 0.0, 1.0);
+    output.uv = vec2<f32>(x, 1.0 - y);
+    return output;
+}
+
+@fragment
+fn fs_fade(input: VertexOutput) -> @location(0) vec4<f32> {
+    // Inverse transform: find where this pixel was in the previous frame
+    // (square units, y up, origin at the centre)
+    let to_square = vec2<f32>(params.aspect, -1.0);
+    var p = (input.uv - 0.5) * to_square;
+    p -= params.offset * vec2<f32>(params.aspect, 1.0);
+    let c = cos(params.rotation);
+    let s = sin(params.rotation);
+    p = vec2<f32>(p.x * c + p.y * s, -p.x * s + p.y * c) / params.zoom;
+    let uv = p / to_square + 0.5;
+
+    let color = textureSample(previous, linear_sampler, uv).rgb;
+    // Nothing comes in from outside the frame
+    let inside = all(uv >= vec2<f32>(0.0)) && all(uv <= vec2<f32>(1.0));
+    return vec4<f32>(select(vec3<f32>(0.0), color * params.decay, inside), 1.0);
+}
+
+@fragment
+fn fs_composite(input: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(textureSample(previous, linear_sampler, input.uv).rgb, 1.0);
+}
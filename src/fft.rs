@@ -1,17 +1,89 @@
 use rustfft::num_complex::Complex;
 use rustfft::FftPlanner;
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
+use crate::pitch;
+use crate::spectrum::SpectrumAnalyzer;
+
+/// Number of keys on a piano, from A0 to C8.
+pub const PIANO_KEYS: usize = 88;
+/// MIDI note number of the lowest piano key, A0.
+const LOWEST_KEY_MIDI: f32 = 21.0;
+
+/// How FFT bins are grouped into bars.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "&'static str")]
+pub enum BarScale {
+    /// Bar edges at the square of their position, from 0 Hz to Nyquist:
+    /// roughly logarithmic, so low frequencies get more bars.
+    #[default]
+    PowerLaw,
+    /// The 88 piano keys from A0 to C8, one per bar at 88 bars, each
+    /// spanning a semitone around the key's pitch.
+    Piano,
+}
+
+impl BarScale {
+    pub const ALL: [BarScale; 2] = [BarScale::PowerLaw, BarScale::Piano];
+
+    /// The scale after this one, wrapping around.
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|&s| s == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BarScale::PowerLaw => "power-law",
+            BarScale::Piano => "piano",
+        }
+    }
+
+    /// The scale called `name` (see [`BarScale::name`]).
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.name() == name)
+    }
+}
+
+impl TryFrom<String> for BarScale {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        BarScale::from_name(&s).ok_or_else(|| {
+            let names: Vec<&str> = BarScale::ALL.iter().map(|s| s.name()).collect();
+            format!("unknown scale {s:?}, expected one of {}", names.join(", "))
+        })
+    }
+}
+
+impl From<BarScale> for &'static str {
+    fn from(scale: BarScale) -> Self {
+        scale.name()
+    }
+}
+
 pub struct FftProcessor {
     fft: Arc<dyn rustfft::Fft<f32>>,
     size: usize,
     num_bars: usize,
+    scale: BarScale,
+    /// Only used by the piano scale, whose bars are at fixed frequencies.
+    sample_rate: u32,
     window: Vec<f32>,
     scratch: Vec<Complex<f32>>,
+    /// Magnitudes of the last frame, bins from 0 Hz up to Nyquist.
+    spectrum: Vec<f32>,
 }
 
 impl FftProcessor {
+    /// Bars on the power-law scale.
     pub fn new(size: usize, num_bars: usize) -> Self {
+        Self::with_scale(size, num_bars, BarScale::PowerLaw, 48_000)
+    }
+
+    /// Bars on `scale`, for audio at `sample_rate`.
+    pub fn with_scale(size: usize, num_bars: usize, scale: BarScale, sample_rate: u32) -> Self {
         let mut planner = FftPlanner::new();
         let fft = planner.plan_fft_forward(size);
         let scratch_len = fft.get_inplace_scratch_len();
@@ -27,11 +99,31 @@ impl FftProcessor {
             fft,
             size,
             num_bars,
+            scale,
+            sample_rate: sample_rate.max(1),
             window,
             scratch: vec![Complex::new(0.0, 0.0); scratch_len],
+            spectrum: vec![0.0; size / 2],
         }
     }
 
+    /// Number of samples per FFT frame.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn num_bars(&self) -> usize {
+        self.num_bars
+    }
+
+    pub fn scale(&self) -> BarScale {
+        self.scale
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
     /// Process raw audio samples and return `num_bars` magnitude values.
     ///
     /// The returned values are in arbitrary units — the caller should scale
@@ -53,38 +145,138 @@ impl FftProcessor {
             .process_with_scratch(&mut buffer, &mut self.scratch);
 
         // Compute magnitude spectrum (only positive frequencies = first half)
-        let spectrum: Vec<f32> = buffer[..self.size / 2]
-            .iter()
-            .map(|c| c.norm() / self.size as f32)
-            .collect();
+        self.spectrum.clear();
+        self.spectrum.extend(
+            buffer[..self.size / 2]
+                .iter()
+                .map(|c| c.norm() / self.size as f32),
+        );
 
         // Group frequency bins into bars
-        self.group_into_bars(&spectrum)
+        self.group_into_bars(&self.spectrum)
+    }
+
+    /// Magnitude spectrum of the last [`FftProcessor::process`], in the
+    /// same units as the bars: `size / 2` bins from 0 Hz, each
+    /// `sample_rate / size` wide.
+    pub fn spectrum(&self) -> &[f32] {
+        &self.spectrum
+    }
+
+    /// Centre frequency in Hz of each bar: the middle of the FFT bins it
+    /// averages, or of its keys on the piano scale.
+    pub fn bar_frequencies(&self) -> Vec<f32> {
+        if self.scale == BarScale::Piano {
+            return (0..self.num_bars)
+                .map(|i| {
+                    let (low, high) = bar_keys(i, self.num_bars);
+                    pitch::midi_frequency((low + high) / 2.0)
+                })
+                .collect();
+        }
+        let bin_hz = self.sample_rate as f32 / self.size as f32;
+        (0..self.num_bars)
+            .map(|i| {
+                let (start, end) = self.bar_bins(i, self.size / 2);
+                (start + end - 1) as f32 / 2.0 * bin_hz
+            })
+            .collect()
     }
 
     /// Group FFT bins into `num_bars` using a power-law (quasi-logarithmic)
     /// mapping so that low frequencies get more bars than high frequencies.
     /// This matches how humans perceive pitch.
     fn group_into_bars(&self, spectrum: &[f32]) -> Vec<f32> {
-        let n = spectrum.len();
         let mut bars = vec![0.0f32; self.num_bars];
+        if self.scale == BarScale::Piano {
+            self.group_into_keys(spectrum, &mut bars);
+            return bars;
+        }
 
-        for i in 0..self.num_bars {
-            let t0 = i as f32 / self.num_bars as f32;
-            let t1 = (i + 1) as f32 / self.num_bars as f32;
-
-            // Power of 2 gives a nice logarithmic-ish spread
-            let start = (t0.powf(2.0) * n as f32) as usize;
-            let end = (t1.powf(2.0) * n as f32) as usize;
-
-            let start = start.min(n - 1);
-            let end = end.max(start + 1).min(n);
+        for (i, bar) in bars.iter_mut().enumerate() {
+            let (start, end) = self.bar_bins(i, spectrum.len());
 
             // Average magnitude across the bin range
             let sum: f32 = spectrum[start..end].iter().sum();
-            bars[i] = sum / (end - start) as f32;
+            *bar = sum / (end - start) as f32;
         }
 
         bars
     }
+
+    /// Range of the `n` spectrum bins that bar `i` averages.
+    fn bar_bins(&self, i: usize, n: usize) -> (usize, usize) {
+        let t0 = i as f32 / self.num_bars as f32;
+        let t1 = (i + 1) as f32 / self.num_bars as f32;
+
+        // Power of 2 gives a nice logarithmic-ish spread
+        let start = (t0.powf(2.0) * n as f32) as usize;
+        let end = (t1.powf(2.0) * n as f32) as usize;
+
+        let start = start.min(n - 1);
+        let end = end.max(start + 1).min(n);
+        (start, end)
+    }
+
+    /// Average the bins within a semitone of each bar's keys. Bars
+    /// narrower than a bin (low keys at small FFT sizes) take the spectrum
+    /// interpolated at their centre instead, so neighbours still differ.
+    fn group_into_keys(&self, spectrum: &[f32], bars: &mut [f32]) {
+        let bin_hz = self.sample_rate as f32 / self.size as f32;
+        let last = spectrum.len() - 1;
+        for (i, bar) in bars.iter_mut().enumerate() {
+            let (low, high) = bar_keys(i, self.num_bars);
+            let start = (pitch::midi_frequency(low) / bin_hz).round() as usize;
+            let end = (pitch::midi_frequency(high) / bin_hz).round() as usize;
+            let (start, end) = (start.min(last), end.min(spectrum.len()));
+
+            *bar = if end > start {
+                spectrum[start..end].iter().sum::<f32>() / (end - start) as f32
+            } else {
+                let at = (pitch::midi_frequency((low + high) / 2.0) / bin_hz).min(last as f32);
+                let below = at.floor() as usize;
+                let above = (below + 1).min(last);
+                let t = at - below as f32;
+                spectrum[below] * (1.0 - t) + spectrum[above] * t
+            };
+        }
+    }
+}
+
+impl SpectrumAnalyzer for FftProcessor {
+    fn size(&self) -> usize {
+        self.size
+    }
+
+    fn num_bars(&self) -> usize {
+        self.num_bars
+    }
+
+    fn scale(&self) -> BarScale {
+        self.scale
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn process(&mut self, samples: &[f32]) -> Vec<f32> {
+        FftProcessor::process(self, samples)
+    }
+
+    fn spectrum(&self) -> &[f32] {
+        &self.spectrum
+    }
+
+    fn bar_frequencies(&self) -> Vec<f32> {
+        FftProcessor::bar_frequencies(self)
+    }
+}
+
+/// MIDI note numbers bounding bar `i` of `num_bars` on the piano scale:
+/// half a semitone either side of the keys it covers.
+pub(crate) fn bar_keys(i: usize, num_bars: usize) -> (f32, f32) {
+    let keys_per_bar = PIANO_KEYS as f32 / num_bars as f32;
+    let low = LOWEST_KEY_MIDI + i as f32 * keys_per_bar - 0.5;
+    (low, low + keys_per_bar)
 }
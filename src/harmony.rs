@@ -0,0 +1,347 @@
+use std::fmt;
+
+use crate::pitch::{self, NOTE_NAMES};
+
+// ---- Tuning knobs (change these to taste) ----------------------------------
+
+/// Frequencies folded into the chroma, in Hz. Bins below where a semitone
+/// is one bin wide are skipped too, as they smear across pitch classes.
+const MIN_FREQUENCY: f32 = 55.0;
+const MAX_FREQUENCY: f32 = 5000.0;
+/// Time constants in seconds of the chroma the chord and the key are
+/// matched against: the chord follows changes, the key the whole passage.
+const CHORD_SECONDS: f32 = 0.25;
+const KEY_SECONDS: f32 = 20.0;
+/// Summed chroma below which a frame is treated as silence.
+const MIN_LEVEL: f32 = 1e-3;
+/// Template similarity below which no chord is reported.
+pub const MIN_CHORD_CONFIDENCE: f32 = 0.7;
+
+// ----------------------------------------------------------------------------
+
+/// Krumhansl-Kessler key profiles: how well each pitch class fits a major
+/// or minor key on C.
+const MAJOR_PROFILE: [f32; 12] = [
+    6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88,
+];
+const MINOR_PROFILE: [f32; 12] = [
+    6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17,
+];
+
+/// Energy per pitch class, C first. Normalized so the strongest is 1.
+pub type Chroma = [f32; 12];
+
+/// Fold a magnitude spectrum (bins from 0 Hz up to Nyquist, as from
+/// [`crate::fft::FftProcessor::spectrum`]) into 12 pitch classes.
+///
+/// Only spectral peaks count, at their interpolated frequency, so a
+/// partial adds the same to its pitch class whatever its octave. Peaks
+/// between two notes count for less, down to nothing halfway.
+pub fn chroma(spectrum: &[f32], sample_rate: u32) -> Chroma {
+    let mut chroma = raw_chroma(spectrum, sample_rate);
+    normalize(&mut chroma);
+    chroma
+}
+
+/// Unnormalized [`chroma`], so louder frames weigh more when averaged.
+fn raw_chroma(spectrum: &[f32], sample_rate: u32) -> Chroma {
+    let mut chroma = [0.0; 12];
+    if spectrum.len() < 3 {
+        return chroma;
+    }
+    let bin_hz = sample_rate as f32 / (2 * spectrum.len()) as f32;
+    let resolvable = bin_hz / (2f32.powf(1.0 / 12.0) - 1.0);
+    let first = ((MIN_FREQUENCY.max(resolvable) / bin_hz).ceil() as usize).max(1);
+    let last = ((MAX_FREQUENCY / bin_hz) as usize).min(spectrum.len() - 2);
+
+    for bin in first..=last {
+        let (left, peak, right) = (spectrum[bin - 1], spectrum[bin], spectrum[bin + 1]);
+        if !(peak > left && peak >= right) {
+            continue;
+        }
+        // Parabola through the peak and its neighbours
+        let curvature = left - 2.0 * peak + right;
+        let shift = if curvature < 0.0 {
+            (0.5 * (left - right) / curvature).clamp(-0.5, 0.5)
+        } else {
+            0.0
+        };
+        let note = pitch::Note::from_frequency((bin as f32 + shift) * bin_hz);
+        let weight = (std::f32::consts::PI * note.cents / 100.0).cos().powi(2);
+        chroma[note.midi.rem_euclid(12) as usize] += peak * weight;
+    }
+    chroma
+}
+
+fn normalize(chroma: &mut Chroma) {
+    let max = chroma.iter().copied().fold(0.0, f32::max);
+    if max > 0.0 {
+        chroma.iter_mut().for_each(|c| *c /= max);
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Major,
+    Minor,
+}
+
+/// A musical key, as estimated from the chroma of the last
+/// `KEY_SECONDS` or so.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Key {
+    /// Pitch class of the tonic, 0 for C.
+    pub tonic: usize,
+    pub mode: Mode,
+    /// Correlation with the key's profile, up to 1.
+    pub confidence: f32,
+}
+
+impl Key {
+    /// Position on the Camelot wheel used for harmonic mixing, e.g. `8A`
+    /// for A minor and `8B` for C major: keys a number apart, or with the
+    /// same number, mix well.
+    pub fn camelot(&self) -> String {
+        // Minor keys share the number of their relative major
+        let (major, letter) = match self.mode {
+            Mode::Major => (self.tonic, 'B'),
+            Mode::Minor => ((self.tonic + 3) % 12, 'A'),
+        };
+        format!("{}{letter}", (major * 7 + 7) % 12 + 1)
+    }
+}
+
+impl fmt::Display for Key {
+    /// Tonic and mode, e.g. `F# minor`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mode = match self.mode {
+            Mode::Major => "major",
+            Mode::Minor => "minor",
+        };
+        write!(f, "{} {mode}", NOTE_NAMES[self.tonic])
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ChordQuality {
+    Major,
+    Minor,
+    Diminished,
+    /// Dominant seventh.
+    Seventh,
+}
+
+impl ChordQuality {
+    const ALL: [ChordQuality; 4] = [
+        ChordQuality::Major,
+        ChordQuality::Minor,
+        ChordQuality::Diminished,
+        ChordQuality::Seventh,
+    ];
+
+    /// Semitones above the root of each note.
+    fn intervals(self) -> &'static [usize] {
+        match self {
+            ChordQuality::Major => &[0, 4, 7],
+            ChordQuality::Minor => &[0, 3, 7],
+            ChordQuality::Diminished => &[0, 3, 6],
+            ChordQuality::Seventh => &[0, 4, 7, 10],
+        }
+    }
+
+    /// Chord symbol suffix, e.g. `min` for minor (not `m`, which reads as
+    /// major in the overlay's uppercase font).
+    fn suffix(self) -> &'static str {
+        match self {
+            ChordQuality::Major => "",
+            ChordQuality::Minor => "min",
+            ChordQuality::Diminished => "dim",
+            ChordQuality::Seventh => "7",
+        }
+    }
+}
+
+/// The chord being played, as estimated from the chroma of the last
+/// `CHORD_SECONDS` or so.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Chord {
+    /// Pitch class of the root, 0 for C.
+    pub root: usize,
+    pub quality: ChordQuality,
+    /// Cosine similarity with the chord's template, up to 1.
+    pub confidence: f32,
+}
+
+impl fmt::Display for Chord {
+    /// Chord symbol, e.g. `Amin` or `G7`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", NOTE_NAMES[self.root], self.quality.suffix())
+    }
+}
+
+/// What the tracker knows after a frame.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Harmony {
+    /// Chroma of the frame itself, for display; all 0 in silence.
+    pub chroma: Chroma,
+    pub key: Option<Key>,
+    pub chord: Option<Chord>,
+}
+
+/// Running chroma, key and chord estimates from a stream of magnitude
+/// spectra, by matching the chroma against key profiles and chord
+/// templates.
+///
+/// ```
+/// use audio_visualizer::fft::FftProcessor;
+/// use audio_visualizer::harmony::HarmonyTracker;
+///
+/// // A minor: A4, C5 and E5
+/// let sample_rate = 48_000;
+/// let chord: Vec<f32> = (0..8192)
+///     .map(|i| {
+///         let t = i as f32 / sample_rate as f32;
+///         [440.0, 523.25, 659.26]
+///             .iter()
+///             .map(|f| (std::f32::consts::TAU * f * t).sin())
+///             .sum()
+///     })
+///     .collect();
+///
+/// let mut fft = FftProcessor::new(8192, 64);
+/// fft.process(&chord);
+/// let mut tracker = HarmonyTracker::new(sample_rate);
+/// let harmony = tracker.process(fft.spectrum(), 0.0);
+/// assert_eq!(harmony.chord.unwrap().to_string(), "Amin");
+/// ```
+pub struct HarmonyTracker {
+    sample_rate: u32,
+    /// Unnormalized chroma averaged over `CHORD_SECONDS` and `KEY_SECONDS`.
+    short: Chroma,
+    long: Chroma,
+    harmony: Harmony,
+}
+
+impl HarmonyTracker {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate: sample_rate.max(1),
+            short: [0.0; 12],
+            long: [0.0; 12],
+            harmony: Harmony {
+                chroma: [0.0; 12],
+                key: None,
+                chord: None,
+            },
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Take in the magnitude spectrum of a frame, `dt` seconds after the
+    /// previous one. With `dt` 0 (a single frame) the chord and key are
+    /// those of this frame alone.
+    pub fn process(&mut self, spectrum: &[f32], dt: f32) -> Harmony {
+        let raw = raw_chroma(spectrum, self.sample_rate);
+        let mix = |average: &mut Chroma, seconds: f32| {
+            let amount = if dt > 0.0 {
+                1.0 - (-dt / seconds).exp()
+            } else {
+                1.0
+            };
+            for (a, r) in average.iter_mut().zip(&raw) {
+                *a += (r - *a) * amount;
+            }
+        };
+        mix(&mut self.short, CHORD_SECONDS);
+        mix(&mut self.long, KEY_SECONDS);
+
+        let mut chroma = raw;
+        if chroma.iter().sum::<f32>() < MIN_LEVEL {
+            chroma = [0.0; 12];
+        }
+        normalize(&mut chroma);
+        self.harmony = Harmony {
+            chroma,
+            key: estimate_key(&self.long),
+            chord: estimate_chord(&self.short),
+        };
+        self.harmony
+    }
+
+    /// The estimates after the last [`HarmonyTracker::process`].
+    pub fn harmony(&self) -> Harmony {
+        self.harmony
+    }
+
+    /// Start over, forgetting the key.
+    pub fn reset(&mut self) {
+        *self = Self::new(self.sample_rate);
+    }
+}
+
+/// The key whose rotated profile correlates best with `chroma`.
+fn estimate_key(chroma: &Chroma) -> Option<Key> {
+    if chroma.iter().sum::<f32>() < MIN_LEVEL {
+        return None;
+    }
+    let mut best: Option<Key> = None;
+    for (mode, profile) in [(Mode::Major, MAJOR_PROFILE), (Mode::Minor, MINOR_PROFILE)] {
+        for tonic in 0..12 {
+            let rotated: Chroma = std::array::from_fn(|i| profile[(i + 12 - tonic) % 12]);
+            let confidence = correlation(chroma, &rotated);
+            if best.is_none_or(|b| confidence > b.confidence) {
+                best = Some(Key {
+                    tonic,
+                    mode,
+                    confidence,
+                });
+            }
+        }
+    }
+    best
+}
+
+/// The chord whose template is closest in direction to `chroma`, if close
+/// enough.
+fn estimate_chord(chroma: &Chroma) -> Option<Chord> {
+    if chroma.iter().sum::<f32>() < MIN_LEVEL {
+        return None;
+    }
+    let norm = chroma.iter().map(|c| c * c).sum::<f32>().sqrt();
+    let mut best: Option<Chord> = None;
+    for quality in ChordQuality::ALL {
+        let intervals = quality.intervals();
+        for root in 0..12 {
+            let dot: f32 = intervals.iter().map(|i| chroma[(root + i) % 12]).sum();
+            let confidence = dot / (norm * (intervals.len() as f32).sqrt());
+            if best.is_none_or(|b| confidence > b.confidence) {
+                best = Some(Chord {
+                    root,
+                    quality,
+                    confidence,
+                });
+            }
+        }
+    }
+    best.filter(|chord| chord.confidence >= MIN_CHORD_CONFIDENCE)
+}
+
+/// Pearson correlation of two pitch class profiles.
+fn correlation(a: &Chroma, b: &Chroma) -> f32 {
+    let mean = |x: &Chroma| x.iter().sum::<f32>() / 12.0;
+    let (mean_a, mean_b) = (mean(a), mean(b));
+    let (mut cov, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        cov += (x - mean_a) * (y - mean_b);
+        var_a += (x - mean_a).powi(2);
+        var_b += (y - mean_b).powi(2);
+    }
+    if var_a > 0.0 && var_b > 0.0 {
+        cov / (var_a * var_b).sqrt()
+    } else {
+        0.0
+    }
+}
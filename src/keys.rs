@@ -0,0 +1,306 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Deserializer, Serialize};
+use winit::keyboard::{Key, NamedKey};
+
+/// Something a key press does. Names are the keys of the config file's
+/// `[keys]` table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    CycleView,
+    ScopeMode,
+    ScopePersistenceDown,
+    ScopePersistenceUp,
+    CycleTheme,
+    CycleColorMode,
+    ToggleBloom,
+    BloomThresholdDown,
+    BloomThresholdUp,
+    BloomIntensityDown,
+    BloomIntensityUp,
+    ToggleTrails,
+    TrailDecayDown,
+    TrailDecayUp,
+    TrailZoomOut,
+    TrailZoomIn,
+    TrailRotateLeft,
+    TrailRotateRight,
+    CycleMsaa,
+    ToggleFrequencyLabels,
+    ToggleDbGrid,
+    ToggleSourceInfo,
+    ToggleFrameTiming,
+    ToggleTempo,
+    ToggleTuner,
+    ToggleHarmony,
+    ToggleMeters,
+    CycleLayout,
+    RadiusDown,
+    RadiusUp,
+    RotateLeft,
+    RotateRight,
+    GapDown,
+    GapUp,
+    StartAngleDown,
+    StartAngleUp,
+    GainDown,
+    GainUp,
+    CycleAgc,
+    FreezeAgc,
+    DecayDown,
+    DecayUp,
+    FewerBars,
+    MoreBars,
+    SmallerFft,
+    LargerFft,
+    CycleScale,
+    CycleTransform,
+}
+
+impl Action {
+    /// Name in the `[keys]` table, e.g. `gain-up`.
+    pub fn name(self) -> String {
+        toml::Value::try_from(self)
+            .ok()
+            .and_then(|v| v.as_str().map(str::to_string))
+            .unwrap_or_default()
+    }
+}
+
+/// Every action with its default key.
+const DEFAULT_KEYS: [(Action, &str); 48] = [
+    (Action::CycleView, "v"),
+    (Action::ScopeMode, "m"),
+    (Action::ScopePersistenceDown, "Down"),
+    (Action::ScopePersistenceUp, "Up"),
+    (Action::CycleTheme, "t"),
+    (Action::CycleColorMode, "c"),
+    (Action::ToggleBloom, "b"),
+    (Action::BloomThresholdDown, "j"),
+    (Action::BloomThresholdUp, "k"),
+    (Action::BloomIntensityDown, "o"),
+    (Action::BloomIntensityUp, "p"),
+    (Action::ToggleTrails, "f"),
+    (Action::TrailDecayDown, "e"),
+    (Action::TrailDecayUp, "r"),
+    (Action::TrailZoomOut, "z"),
+    (Action::TrailZoomIn, "x"),
+    (Action::TrailRotateLeft, "q"),
+    (Action::TrailRotateRight, "w"),
+    (Action::CycleMsaa, "a"),
+    (Action::ToggleFrequencyLabels, "1"),
+    (Action::ToggleDbGrid, "2"),
+    (Action::ToggleSourceInfo, "3"),
+    (Action::ToggleFrameTiming, "4"),
+    (Action::ToggleTempo, "5"),
+    (Action::ToggleTuner, "6"),
+    (Action::ToggleHarmony, "7"),
+    (Action::ToggleMeters, "8"),
+    (Action::CycleLayout, "l"),
+    (Action::RadiusDown, "["),
+    (Action::RadiusUp, "]"),
+    (Action::RotateLeft, ","),
+    (Action::RotateRight, "."),
+    (Action::GapDown, "-"),
+    (Action::GapUp, "="),
+    (Action::StartAngleDown, "9"),
+    (Action::StartAngleUp, "0"),
+    (Action::GainDown, "g"),
+    (Action::GainUp, "h"),
+    (Action::CycleAgc, "i"),
+    (Action::FreezeAgc, "Space"),
+    (Action::DecayDown, "s"),
+    (Action::DecayUp, "d"),
+    (Action::FewerBars, "Left"),
+    (Action::MoreBars, "Right"),
+    (Action::SmallerFft, "PageDown"),
+    (Action::LargerFft, "PageUp"),
+    (Action::CycleScale, "n"),
+    (Action::CycleTransform, "y"),
+];
+
+/// Named keys that can be bound, by their config name.
+const NAMED_KEYS: [(&str, NamedKey); 14] = [
+    ("Left", NamedKey::ArrowLeft),
+    ("Right", NamedKey::ArrowRight),
+    ("Up", NamedKey::ArrowUp),
+    ("Down", NamedKey::ArrowDown),
+    ("PageUp", NamedKey::PageUp),
+    ("PageDown", NamedKey::PageDown),
+    ("Home", NamedKey::Home),
+    ("End", NamedKey::End),
+    ("Insert", NamedKey::Insert),
+    ("Delete", NamedKey::Delete),
+    ("Backspace", NamedKey::Backspace),
+    ("Enter", NamedKey::Enter),
+    ("Tab", NamedKey::Tab),
+    ("Space", NamedKey::Space),
+];
+
+/// Which key triggers each action. Every action has exactly one key and
+/// no key triggers two actions.
+///
+/// A config `[keys]` table only needs the actions it rebinds; the rest keep
+/// their default keys. Keys are single characters (case-insensitive) or
+/// one of the names in `NAMED_KEYS`, e.g. `gain-up = "PageUp"`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct KeyBindings(BTreeMap<Action, String>);
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self(
+            DEFAULT_KEYS
+                .iter()
+                .map(|&(action, key)| (action, key.to_string()))
+                .collect(),
+        )
+    }
+}
+
+impl KeyBindings {
+    /// Action bound to `key`, if any.
+    pub fn action(&self, key: &Key) -> Option<Action> {
+        let name = key_name(key)?;
+        self.0
+            .iter()
+            .find(|(_, bound)| **bound == name)
+            .map(|(&action, _)| action)
+    }
+
+    /// Bind `key` to `action`, checking the key name.
+    fn bind(&mut self, action: Action, key: &str) -> Result<(), String> {
+        let name = normalize(key).ok_or_else(|| {
+            let named: Vec<&str> = NAMED_KEYS.iter().map(|&(name, _)| name).collect();
+            format!(
+                "unknown key {key:?}, expected one character or one of {}",
+                named.join(", ")
+            )
+        })?;
+        self.0.insert(action, name);
+        Ok(())
+    }
+
+    /// Check that no key is bound to two actions.
+    fn check_unique(&self) -> Result<(), String> {
+        for (a, key_a) in &self.0 {
+            if let Some((b, _)) = self.0.range(a..).skip(1).find(|(_, key_b)| key_a == *key_b) {
+                return Err(format!(
+                    "{key_a:?} is bound to both {} and {}",
+                    a.name(),
+                    b.name()
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'de> Deserialize<'de> for KeyBindings {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let rebound = BTreeMap::<Action, String>::deserialize(deserializer)?;
+        let mut bindings = KeyBindings::default();
+        for (action, key) in rebound {
+            bindings
+                .bind(action, &key)
+                .map_err(serde::de::Error::custom)?;
+        }
+        bindings.check_unique().map_err(serde::de::Error::custom)?;
+        Ok(bindings)
+    }
+}
+
+/// Canonical config name of `key`: a lowercase character or a
+/// `NAMED_KEYS` name.
+fn key_name(key: &Key) -> Option<String> {
+    match key {
+        Key::Character(c) => Some(c.to_lowercase()),
+        Key::Named(named) => NAMED_KEYS
+            .iter()
+            .find(|(_, n)| n == named)
+            .map(|&(name, _)| name.to_string()),
+        _ => None,
+    }
+}
+
+/// Canonical form of a key name from a config file.
+fn normalize(key: &str) -> Option<String> {
+    let mut chars = key.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(c.to_lowercase().collect());
+    }
+    NAMED_KEYS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(key))
+        .map(|&(name, _)| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(table: &str) -> Result<KeyBindings, String> {
+        toml::from_str(table).map_err(|e| e.to_string())
+    }
+
+    fn char_key(c: &str) -> Key {
+        Key::Character(c.into())
+    }
+
+    #[test]
+    fn a_partial_table_keeps_the_other_defaults() {
+        assert_eq!(parse("").unwrap(), KeyBindings::default());
+
+        let bindings = parse(r#"gain-up = "u""#).unwrap();
+        assert_eq!(bindings.action(&char_key("u")), Some(Action::GainUp));
+        assert_eq!(bindings.action(&char_key("h")), None);
+        assert_eq!(bindings.action(&char_key("g")), Some(Action::GainDown));
+        assert_eq!(
+            bindings.action(&Key::Named(NamedKey::PageUp)),
+            Some(Action::LargerFft)
+        );
+    }
+
+    #[test]
+    fn single_characters_and_names_ignore_case() {
+        let bindings = parse(r#"gain-up = "U""#).unwrap();
+        assert_eq!(bindings.action(&char_key("u")), Some(Action::GainUp));
+        assert_eq!(bindings.action(&char_key("U")), Some(Action::GainUp));
+
+        let bindings = parse(r#"gain-up = "home""#).unwrap();
+        assert_eq!(
+            bindings.action(&Key::Named(NamedKey::Home)),
+            Some(Action::GainUp)
+        );
+    }
+
+    #[test]
+    fn unknown_keys_and_actions_are_rejected() {
+        let e = parse(r#"gain-up = "Hyper""#).unwrap_err();
+        assert!(e.contains(r#"unknown key "Hyper""#), "{e}");
+        let e = parse(r#"gain-up = "ab""#).unwrap_err();
+        assert!(e.contains("unknown key"), "{e}");
+        assert!(parse(r#"gain-sideways = "u""#).is_err());
+    }
+
+    #[test]
+    fn a_key_for_two_actions_is_rejected() {
+        // Clashing with a default the table doesn't mention
+        let e = parse(r#"gain-up = "g""#).unwrap_err();
+        assert!(e.contains("bound to both gain-down and gain-up"), "{e}");
+
+        // Clashing within the table, in different cases
+        let e = parse("gain-up = \"u\"\ndecay-up = \"U\"").unwrap_err();
+        assert!(e.contains("bound to both"), "{e}");
+    }
+
+    #[test]
+    fn every_default_key_is_valid_and_unique() {
+        let mut bindings = KeyBindings(BTreeMap::new());
+        for (action, key) in DEFAULT_KEYS {
+            bindings.bind(action, key).unwrap();
+        }
+        bindings.check_unique().unwrap();
+        assert_eq!(bindings, KeyBindings::default());
+    }
+}
@@ -0,0 +1,415 @@
+use glam::{Mat4, Vec2, Vec3};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+/// How bars are arranged on screen.
+///
+/// Every bar is a quad whose base sits at the origin of its transform and
+/// which grows along the transform's +Y axis.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "&'static str")]
+pub enum Layout {
+    /// Left to right along the bottom edge.
+    Linear,
+    /// Left to right through the centre, growing up and down.
+    Mirrored,
+    /// Around a ring, growing both inwards and outwards.
+    Circular,
+    /// Around a ring, growing towards the centre.
+    RadialInward,
+    /// Around a ring, growing away from the centre.
+    RadialOutward,
+    /// Along an Archimedean spiral from the centre outwards.
+    Spiral,
+    /// One cell per bar, filled row by row from the top left.
+    Grid,
+}
+
+impl Layout {
+    pub const ALL: [Layout; 7] = [
+        Layout::Linear,
+        Layout::Mirrored,
+        Layout::Circular,
+        Layout::RadialInward,
+        Layout::RadialOutward,
+        Layout::Spiral,
+        Layout::Grid,
+    ];
+
+    /// The layout after this one, wrapping around.
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|&l| l == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Layout::Linear => "linear",
+            Layout::Mirrored => "mirrored",
+            Layout::Circular => "circular",
+            Layout::RadialInward => "radial-inward",
+            Layout::RadialOutward => "radial-outward",
+            Layout::Spiral => "spiral",
+            Layout::Grid => "grid",
+        }
+    }
+
+    /// The layout called `name` (see [`Layout::name`]).
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|l| l.name() == name)
+    }
+}
+
+impl TryFrom<String> for Layout {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Layout::from_name(&s).ok_or_else(|| {
+            let names: Vec<&str> = Layout::ALL.iter().map(|l| l.name()).collect();
+            format!("unknown layout {s:?}, expected one of {}", names.join(", "))
+        })
+    }
+}
+
+impl From<Layout> for &'static str {
+    fn from(layout: Layout) -> Self {
+        layout.name()
+    }
+}
+
+/// Adjustable parameters shared by the layouts. Not every layout uses every
+/// field (e.g. `radius` is ignored by `Linear`).
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct LayoutParams {
+    /// Ring radius (or outer spiral radius) in layout units.
+    pub radius: f32,
+    /// Rotation of the whole layout around the centre, in radians.
+    pub rotation: f32,
+    /// Fraction of each bar slot left empty (0 = bars touch, 1 = invisible).
+    pub gap: f32,
+    /// Angle of the first bar for ring and spiral layouts, in radians.
+    pub start_angle: f32,
+    /// Number of turns for the spiral layout.
+    pub spiral_turns: f32,
+}
+
+impl Default for LayoutParams {
+    fn default() -> Self {
+        Self {
+            radius: 0.33,
+            rotation: 0.0,
+            gap: 0.15,
+            start_angle: 0.0,
+            spiral_turns: 3.0,
+        }
+    }
+}
+
+/// Everything the bar shader needs to place the bars for one layout.
+pub struct BarGeometry {
+    /// One transform per bar, placing its base and orienting it.
+    pub transforms: Vec<Mat4>,
+    /// Width of every bar in layout units.
+    pub bar_width: f32,
+    /// Multiplier from displayed magnitude (0..MAX_HEIGHT) to layout units.
+    pub height_scale: f32,
+    /// Bars extend equally on both sides of their base.
+    pub mirrored: bool,
+}
+
+/// Half-size of the visible area in layout units for a `width`×`height`
+/// target.
+///
+/// The shorter axis always spans -1..1 so that circles stay circular; the
+/// longer axis extends further.
+pub fn view_extent(width: u32, height: u32) -> Vec2 {
+    let aspect = width.max(1) as f32 / height.max(1) as f32;
+    if aspect >= 1.0 {
+        Vec2::new(aspect, 1.0)
+    } else {
+        Vec2::new(1.0, 1.0 / aspect)
+    }
+}
+
+/// Orthographic projection from layout units to clip space for a view of
+/// half-size `extent` (see [`view_extent`]).
+pub fn projection(extent: Vec2) -> Mat4 {
+    Mat4::orthographic_rh(-extent.x, extent.x, -extent.y, extent.y, -1.0, 1.0)
+}
+
+/// Build the bar transforms for `layout`.
+///
+/// Ring and spiral layouts live in the unit circle; edge-to-edge layouts
+/// (linear, mirrored, grid) fill the whole `extent`.
+pub fn build(layout: Layout, params: &LayoutParams, num_bars: u32, extent: Vec2) -> BarGeometry {
+    let n = num_bars.max(1) as usize;
+    let fill = (1.0 - params.gap).clamp(0.0, 1.0);
+    let radius = params.radius.max(0.01);
+
+    let mut geometry = match layout {
+        Layout::Linear | Layout::Mirrored => {
+            let slot = 2.0 * extent.x / n as f32;
+            let y = if layout == Layout::Linear {
+                -extent.y
+            } else {
+                0.0
+            };
+            BarGeometry {
+                transforms: (0..n)
+                    .map(|i| {
+                        let x = -extent.x + (i as f32 + 0.5) * slot;
+                        Mat4::from_translation(Vec3::new(x, y, 0.0))
+                    })
+                    .collect(),
+                bar_width: slot * fill,
+                // MAX_HEIGHT (2.0) reaches the top edge
+                height_scale: extent.y,
+                mirrored: layout == Layout::Mirrored,
+            }
+        }
+
+        Layout::Circular | Layout::RadialInward | Layout::RadialOutward => {
+            // Outward-pointing bars have their +Y along the radius; inward
+            // ones are flipped to point at the centre.
+            let flip = if layout == Layout::RadialInward {
+                PI
+            } else {
+                0.0
+            };
+            let height_scale = match layout {
+                // Half the bar is inside the ring, so stop at the centre
+                Layout::Circular => radius,
+                Layout::RadialInward => radius / 2.0,
+                _ => (1.0 - radius).max(0.1) / 2.0,
+            };
+            BarGeometry {
+                transforms: (0..n)
+                    .map(|i| {
+                        let angle = params.start_angle + (i as f32 / n as f32) * TAU;
+                        ring_transform(radius, angle, flip)
+                    })
+                    .collect(),
+                bar_width: TAU * radius / n as f32 * fill,
+                height_scale,
+                mirrored: layout == Layout::Circular,
+            }
+        }
+
+        Layout::Spiral => {
+            // Start a little way out so the first bars aren't all on top of
+            // each other.
+            let inner = radius * 0.15;
+            let turns = params.spiral_turns.max(0.1);
+            let outer = radius.max(inner);
+            let mut length = 0.0;
+            let transforms = (0..n)
+                .map(|i| {
+                    let t = i as f32 / n as f32;
+                    let r = inner + (outer - inner) * t;
+                    let angle = params.start_angle + t * turns * TAU;
+                    length += r * turns * TAU / n as f32;
+                    ring_transform(r, angle, 0.0)
+                })
+                .collect();
+            BarGeometry {
+                transforms,
+                // Average arc length per bar
+                bar_width: length / n as f32 * fill,
+                height_scale: (outer - inner) / turns / 2.0,
+                mirrored: false,
+            }
+        }
+
+        Layout::Grid => {
+            let cols = (n as f32).sqrt().ceil() as usize;
+            let rows = n.div_ceil(cols);
+            let cell_w = 2.0 * extent.x / cols as f32;
+            let cell_h = 2.0 * extent.y / rows as f32;
+            BarGeometry {
+                transforms: (0..n)
+                    .map(|i| {
+                        let (row, col) = (i / cols, i % cols);
+                        let x = -extent.x + (col as f32 + 0.5) * cell_w;
+                        let y = extent.y - (row as f32 + 1.0) * cell_h;
+                        Mat4::from_translation(Vec3::new(x, y, 0.0))
+                    })
+                    .collect(),
+                bar_width: cell_w * fill,
+                height_scale: cell_h * fill / 2.0,
+                mirrored: false,
+            }
+        }
+    };
+
+    if params.rotation != 0.0 {
+        let rotation = Mat4::from_rotation_z(params.rotation);
+        for t in &mut geometry.transforms {
+            *t = rotation * *t;
+        }
+    }
+
+    geometry
+}
+
+/// Place a bar on a circle of radius `r` at `angle`, pointing outwards
+/// (plus an extra `flip` rotation).
+fn ring_transform(r: f32, angle: f32, flip: f32) -> Mat4 {
+    let (sin, cos) = angle.sin_cos();
+    Mat4::from_translation(Vec3::new(r * cos, r * sin, 0.0))
+        * Mat4::from_rotation_z(angle - FRAC_PI_2 + flip)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-5;
+
+    /// Where bar `t` starts, in layout units.
+    fn base(t: &Mat4) -> Vec2 {
+        t.transform_point3(Vec3::ZERO).truncate()
+    }
+
+    /// Which way bar `t` grows, as a unit vector.
+    fn direction(t: &Mat4) -> Vec2 {
+        t.transform_vector3(Vec3::Y).truncate().normalize()
+    }
+
+    fn assert_near(a: Vec2, b: Vec2) {
+        assert!(a.distance(b) < EPSILON, "{a} != {b}");
+    }
+
+    fn touching() -> LayoutParams {
+        LayoutParams {
+            gap: 0.0,
+            ..LayoutParams::default()
+        }
+    }
+
+    #[test]
+    fn linear_bars_share_the_bottom_edge_evenly() {
+        let extent = Vec2::new(2.0, 1.0);
+        let geometry = build(Layout::Linear, &touching(), 4, extent);
+        assert_eq!(geometry.transforms.len(), 4);
+        for (t, x) in geometry.transforms.iter().zip([-1.5, -0.5, 0.5, 1.5]) {
+            assert_near(base(t), Vec2::new(x, -1.0));
+            assert_near(direction(t), Vec2::Y);
+        }
+        assert!((geometry.bar_width - 1.0).abs() < EPSILON);
+        assert!(!geometry.mirrored);
+
+        let gapped = build(Layout::Linear, &LayoutParams::default(), 4, extent);
+        assert!((gapped.bar_width - 0.85).abs() < EPSILON);
+
+        let mirrored = build(Layout::Mirrored, &touching(), 4, extent);
+        assert_near(base(&mirrored.transforms[0]), Vec2::new(-1.5, 0.0));
+        assert!(mirrored.mirrored);
+    }
+
+    #[test]
+    fn ring_bars_sit_on_the_radius_and_point_along_it() {
+        let params = LayoutParams {
+            radius: 0.5,
+            start_angle: FRAC_PI_2,
+            ..LayoutParams::default()
+        };
+        for layout in [
+            Layout::Circular,
+            Layout::RadialOutward,
+            Layout::RadialInward,
+        ] {
+            let geometry = build(layout, &params, 8, Vec2::ONE);
+            // The first bar starts at the start angle, straight up
+            assert_near(base(&geometry.transforms[0]), Vec2::new(0.0, 0.5));
+            for t in &geometry.transforms {
+                let outwards = base(t).normalize();
+                assert!((base(t).length() - 0.5).abs() < EPSILON, "{layout:?}");
+                let expected = if layout == Layout::RadialInward {
+                    -outwards
+                } else {
+                    outwards
+                };
+                assert_near(direction(t), expected);
+            }
+            // Anticlockwise, an eighth of a turn apart
+            assert_near(base(&geometry.transforms[2]), Vec2::new(-0.5, 0.0));
+        }
+    }
+
+    #[test]
+    fn grid_fills_rows_from_the_top_left() {
+        let extent = Vec2::new(1.5, 1.0);
+        // Five bars take a 3×2 grid
+        let geometry = build(Layout::Grid, &touching(), 5, extent);
+        let (cell_w, cell_h) = (1.0, 1.0);
+        assert_near(base(&geometry.transforms[0]), Vec2::new(-1.0, 0.0));
+        assert_near(base(&geometry.transforms[2]), Vec2::new(1.0, 0.0));
+        assert_near(base(&geometry.transforms[3]), Vec2::new(-1.0, -1.0));
+        assert!((geometry.bar_width - cell_w).abs() < EPSILON);
+        // A full bar (2.0) fills its cell
+        assert!((geometry.height_scale * 2.0 - cell_h).abs() < EPSILON);
+    }
+
+    #[test]
+    fn spiral_bars_move_outwards() {
+        let geometry = build(Layout::Spiral, &LayoutParams::default(), 32, Vec2::ONE);
+        let radii: Vec<f32> = geometry
+            .transforms
+            .iter()
+            .map(|t| base(t).length())
+            .collect();
+        assert!(radii.windows(2).all(|r| r[1] > r[0]), "{radii:?}");
+        assert!(radii[31] <= LayoutParams::default().radius);
+    }
+
+    #[test]
+    fn the_shorter_side_spans_minus_one_to_one() {
+        assert_near(view_extent(1920, 1080), Vec2::new(16.0 / 9.0, 1.0));
+        assert_near(view_extent(1080, 1920), Vec2::new(1.0, 16.0 / 9.0));
+        assert_near(view_extent(500, 500), Vec2::ONE);
+        // A minimized window doesn't divide by zero
+        assert!(view_extent(0, 0).is_finite());
+    }
+
+    #[test]
+    fn the_projection_maps_the_extent_to_clip_space() {
+        let extent = view_extent(1600, 900);
+        let clip = |p: Vec2| projection(extent).project_point3(p.extend(0.0)).truncate();
+        assert_near(clip(extent), Vec2::ONE);
+        assert_near(clip(-extent), Vec2::NEG_ONE);
+        assert_near(clip(Vec2::ZERO), Vec2::ZERO);
+    }
+
+    #[test]
+    fn rings_stay_round_in_a_wide_window() {
+        let (width, height) = (1600, 900);
+        let extent = view_extent(width, height);
+        let geometry = build(Layout::Circular, &LayoutParams::default(), 16, extent);
+        let pixels = |p: Vec2| {
+            let clip = projection(extent).project_point3(p.extend(0.0));
+            Vec2::new(clip.x * width as f32, clip.y * height as f32) / 2.0
+        };
+        let radii: Vec<f32> = geometry
+            .transforms
+            .iter()
+            .map(|t| pixels(base(t)).length())
+            .collect();
+        let expected = LayoutParams::default().radius * height as f32 / 2.0;
+        for r in radii {
+            assert!((r - expected).abs() < 0.01, "{r} px, expected {expected}");
+        }
+    }
+
+    #[test]
+    fn rotation_turns_the_whole_layout() {
+        let params = LayoutParams {
+            rotation: FRAC_PI_2,
+            ..touching()
+        };
+        let geometry = build(Layout::Linear, &params, 2, Vec2::ONE);
+        // The bottom edge becomes the right edge, bars growing leftwards
+        assert_near(base(&geometry.transforms[0]), Vec2::new(1.0, -0.5));
+        assert_near(direction(&geometry.transforms[0]), Vec2::NEG_X);
+    }
+}
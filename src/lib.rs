@@ -0,0 +1,102 @@
+//! Real-time audio spectrum visualizer.
+//!
+//! The pieces the `audio-visualizer` binary is built from, for use in other
+//! tools:
+//!
+//! - **Sources** ([`audio`]): capture from an input device or play a WAV
+//!   file with [`Source`], or decode a whole file with
+//!   [`audio::decode_wav`] for offline work.
+//! - **Analysis** ([`Analyzer`]): samples in, smoothed bar heights out,
+//!   with bars spread over the spectrum or on the piano keys
+//!   ([`BarScale`]), from an FFT or a constant-Q transform
+//!   ([`Transform`]), and an optional automatic gain ([`agc`]).
+//!   [`beat`] tracks the tempo, [`pitch`] the note being played,
+//!   [`harmony`] the key and chord and [`loudness`] the LUFS and true
+//!   peak, and [`features`] describes each frame's spectrum.
+//!   [`pipeline::FramePipeline`] runs them all for each frame.
+//! - **Rendering** ([`Renderer`]): draws one of several [`Visualizer`]s
+//!   (bars, spectrogram, chromagram, vectorscope, Shadertoy, or your own),
+//!   or a stack of them as blended [`Layer`]s, into a winit window or an
+//!   offscreen target, with themes, bloom, trails and a text overlay.
+//!
+//! Rendering one frame offscreen:
+//!
+//! ```no_run
+//! use audio_visualizer::{Analyzer, Layout, LayoutParams, Renderer};
+//!
+//! let mut analyzer = Analyzer::default();
+//! let mut renderer = pollster::block_on(Renderer::new_headless(
+//!     640,
+//!     360,
+//!     analyzer.num_bars() as u32,
+//!     Layout::Linear,
+//!     LayoutParams::default(),
+//!     true,
+//! ))
+//! .expect("no adapter");
+//!
+//! let tone: Vec<f32> = (0..analyzer.fft_size())
+//!     .map(|i| (i as f32 * 0.05).sin())
+//!     .collect();
+//! renderer.render(analyzer.process(&tone, 0.0));
+//! renderer.save_png("frame.png".as_ref()).unwrap();
+//! ```
+//!
+//! See `examples/embed.rs` for a live visualizer inside another winit app.
+
+pub mod agc;
+pub mod analysis;
+pub mod audio;
+pub mod bars;
+pub mod beat;
+pub mod bloom;
+pub mod compositor;
+pub mod cqt;
+pub mod export;
+pub mod features;
+pub mod feedback;
+pub mod fft;
+pub mod harmony;
+pub mod layout;
+pub mod loudness;
+pub mod pipeline;
+pub mod pitch;
+pub mod renderer;
+pub mod shadertoy;
+pub mod smoothing;
+pub mod spectrogram;
+pub mod spectrum;
+pub mod target;
+mod text;
+pub mod theme;
+pub mod vectorscope;
+pub mod visualizer;
+pub mod watch;
+
+pub use analysis::Analyzer;
+pub use audio::Source;
+pub use compositor::{BlendMode, Layer};
+pub use fft::BarScale;
+pub use layout::{Layout, LayoutParams};
+pub use renderer::{Overlay, Renderer};
+pub use spectrum::{SpectrumAnalyzer, Transform};
+pub use theme::Theme;
+pub use visualizer::{AnalysisFrame, Visualizer};
+
+// ---- Tuning knobs (change these to taste) ----------------------------------
+
+/// Number of samples fed into each FFT frame by default.
+pub const FFT_SIZE: usize = 2048;
+/// Number of bars by default.
+pub const NUM_BARS: usize = 88;
+/// Smoothing factor for bar decay per 1/60 s (0 = instant, 1 = frozen).
+/// Higher = slower.
+pub const DECAY: f32 = 0.88;
+/// Gain applied to raw FFT magnitudes before display.
+pub const GAIN: f32 = 6.0;
+/// Maximum bar height in clip-space units (screen goes from -1 to +1).
+pub const MAX_HEIGHT: f32 = 2.0;
+/// Bar arrangement by default.
+pub const LAYOUT: Layout = Layout::RadialOutward;
+
+// ----------------------------------------------------------------------------
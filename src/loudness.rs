@@ -0,0 +1,426 @@
+use std::collections::VecDeque;
+
+// ---- Tuning knobs (change these to taste) ----------------------------------
+
+/// Seconds between loudness updates and gating blocks (EBU R128: at least
+/// 10 Hz, with 75% overlapping momentary blocks).
+const STEP_SECONDS: f64 = 0.1;
+/// Steps in the momentary (400 ms) and short-term (3 s) windows, and in
+/// the window of the RMS, sample peak and crest factor (300 ms).
+const MOMENTARY_STEPS: usize = 4;
+const SHORT_TERM_STEPS: usize = 30;
+const RMS_STEPS: usize = 3;
+/// Loudness below which blocks never count towards the integrated
+/// loudness or the loudness range, in LUFS.
+const ABSOLUTE_GATE: f64 = -70.0;
+/// How far below the ungated average, in LU, blocks stop counting towards
+/// the integrated loudness (BS.1770) and the loudness range (EBU 3342).
+const INTEGRATED_RELATIVE_GATE: f64 = -10.0;
+const RANGE_RELATIVE_GATE: f64 = -20.0;
+/// Percentiles of the short-term loudness the range spans.
+const RANGE_LOW_PERCENTILE: f64 = 0.10;
+const RANGE_HIGH_PERCENTILE: f64 = 0.95;
+/// Width of the bins the gating blocks are counted in, in LU, and the
+/// loudest bin, in LUFS (louder blocks are counted in it). libebur128 uses
+/// 0.1 LU bins up to +5 LUFS too.
+const HISTOGRAM_STEP: f64 = 0.1;
+const HISTOGRAM_MAX: f64 = 5.0;
+/// True peak oversampling, and taps of the interpolation filter per phase.
+const OVERSAMPLING: usize = 4;
+const TAPS_PER_PHASE: usize = 12;
+
+// ----------------------------------------------------------------------------
+
+/// Offset in the BS.1770 loudness formula, in dB.
+const LOUDNESS_OFFSET: f64 = -0.691;
+
+/// Readings of a [`LoudnessMeter`]. Levels are `f32::NEG_INFINITY` until
+/// there is something to measure.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Loudness {
+    /// K-weighted loudness of the last 400 ms, in LUFS.
+    pub momentary: f32,
+    /// K-weighted loudness of the last 3 s, in LUFS.
+    pub short_term: f32,
+    /// Gated loudness of everything since the start or the last reset, in
+    /// LUFS.
+    pub integrated: f32,
+    /// Loudness range (LRA) since the start, in LU: the spread of the
+    /// gated short-term loudness, without its quietest 10% and loudest 5%.
+    pub range: f32,
+    /// Highest 4× oversampled sample of the last 300 ms, and since the
+    /// start, in dBTP.
+    pub true_peak: f32,
+    pub max_true_peak: f32,
+    /// Unweighted RMS and highest sample of the last 300 ms, in dBFS.
+    pub rms: f32,
+    pub sample_peak: f32,
+}
+
+impl Loudness {
+    /// Peak-to-RMS ratio of the last 300 ms in dB, or `None` in silence.
+    pub fn crest_factor(&self) -> Option<f32> {
+        (self.rms > f32::NEG_INFINITY).then_some(self.sample_peak - self.rms)
+    }
+}
+
+/// A second-order IIR filter section, direct form II transposed.
+#[derive(Copy, Clone, Debug)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    state: [f64; 2],
+}
+
+impl Biquad {
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.state[0];
+        self.state[0] = self.b[1] * x - self.a[0] * y + self.state[1];
+        self.state[1] = self.b[2] * x - self.a[1] * y;
+        y
+    }
+}
+
+/// The BS.1770 K-weighting filter at `sample_rate`: a high shelf for the
+/// head, then a high-pass (the "RLB" curve). The standard gives the
+/// coefficients at 48 kHz; these are the same filters derived for any
+/// rate, as in libebur128.
+fn k_weighting(sample_rate: u32) -> [Biquad; 2] {
+    let rate = sample_rate as f64;
+
+    let (f0, gain_db, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+    let k = (std::f64::consts::PI * f0 / rate).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad {
+        b: [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        state: [0.0; 2],
+    };
+
+    let (f0, q) = (38.13547087602444, 0.5003270373238773);
+    let k = (std::f64::consts::PI * f0 / rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad {
+        b: [1.0, -2.0, 1.0],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        state: [0.0; 2],
+    };
+
+    [shelf, high_pass]
+}
+
+/// Polyphase windowed-sinc filter for 4× oversampling: phase `k` gives the
+/// signal `k / 4` of a sample after the one `TAPS_PER_PHASE / 2` back.
+fn oversampling_filter() -> [[f32; TAPS_PER_PHASE]; OVERSAMPLING] {
+    let half = (TAPS_PER_PHASE / 2) as f32;
+    std::array::from_fn(|k| {
+        let mut taps: [f32; TAPS_PER_PHASE] = std::array::from_fn(|j| {
+            // Distance from tap j back to the interpolated point
+            let u = j as f32 - half + k as f32 / OVERSAMPLING as f32;
+            let sinc = if u == 0.0 {
+                1.0
+            } else {
+                (std::f32::consts::PI * u).sin() / (std::f32::consts::PI * u)
+            };
+            let window = 0.5 + 0.5 * (std::f32::consts::PI * u / (half + 1.0)).cos();
+            sinc * window
+        });
+        // Unity gain at 0 Hz for every phase
+        let sum: f32 = taps.iter().sum();
+        taps.iter_mut().for_each(|t| *t /= sum);
+        taps
+    })
+}
+
+/// Sums over one `STEP_SECONDS` of audio.
+#[derive(Copy, Clone, Debug, Default)]
+struct Step {
+    frames: usize,
+    /// K-weighted squares, summed over the channels.
+    weighted: f64,
+    /// Unweighted squares, summed over the channels.
+    squares: f64,
+    sample_peak: f32,
+    true_peak: f32,
+}
+
+/// Loudness metering after ITU-R BS.1770-4 and EBU R128: momentary,
+/// short-term and integrated loudness, loudness range, true peak, and the
+/// RMS and crest factor.
+///
+/// Readings are updated every 100 ms of audio.
+///
+/// ```
+/// use audio_visualizer::loudness::LoudnessMeter;
+///
+/// // A 1 kHz sine at -23 dBFS in both channels reads -23 LUFS
+/// let sample_rate = 48_000;
+/// let amplitude = 10f32.powf(-23.0 / 20.0);
+/// let frames: Vec<[f32; 2]> = (0..sample_rate)
+///     .map(|i| {
+///         let s = amplitude * (std::f32::consts::TAU * 1000.0 * i as f32 / sample_rate as f32).sin();
+///         [s, s]
+///     })
+///     .collect();
+///
+/// let mut meter = LoudnessMeter::new(sample_rate, 2);
+/// meter.process(&frames);
+/// assert!((meter.loudness().integrated + 23.0).abs() < 0.1);
+/// ```
+pub struct LoudnessMeter {
+    sample_rate: u32,
+    channels: usize,
+    /// K-weighting for each channel.
+    filters: Vec<[Biquad; 2]>,
+    oversampling: [[f32; TAPS_PER_PHASE]; OVERSAMPLING],
+    /// Last `TAPS_PER_PHASE` samples of each channel, newest last.
+    history: Vec<[f32; TAPS_PER_PHASE]>,
+    step_frames: usize,
+    current: Step,
+    /// The last `SHORT_TERM_STEPS` finished steps, newest last.
+    steps: VecDeque<Step>,
+    /// Momentary and short-term blocks above the absolute gate, for the
+    /// integrated loudness and the range.
+    momentary_blocks: BlockHistogram,
+    short_term_blocks: BlockHistogram,
+    max_true_peak: f32,
+    loudness: Loudness,
+}
+
+impl LoudnessMeter {
+    /// A meter for audio at `sample_rate` with 1 or 2 `channels`. Mono
+    /// audio counts once, as BS.1770 asks, even though it is played from
+    /// both speakers.
+    pub fn new(sample_rate: u32, channels: usize) -> Self {
+        let sample_rate = sample_rate.max(1);
+        let channels = channels.clamp(1, 2);
+        Self {
+            sample_rate,
+            channels,
+            filters: vec![k_weighting(sample_rate); channels],
+            oversampling: oversampling_filter(),
+            history: vec![[0.0; TAPS_PER_PHASE]; channels],
+            step_frames: ((sample_rate as f64 * STEP_SECONDS).round() as usize).max(1),
+            current: Step::default(),
+            steps: VecDeque::with_capacity(SHORT_TERM_STEPS),
+            momentary_blocks: BlockHistogram::new(),
+            short_term_blocks: BlockHistogram::new(),
+            max_true_peak: 0.0,
+            loudness: Loudness {
+                momentary: f32::NEG_INFINITY,
+                short_term: f32::NEG_INFINITY,
+                integrated: f32::NEG_INFINITY,
+                range: 0.0,
+                true_peak: f32::NEG_INFINITY,
+                max_true_peak: f32::NEG_INFINITY,
+                rms: f32::NEG_INFINITY,
+                sample_peak: f32::NEG_INFINITY,
+            },
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// Take in `[left, right]` sample pairs; the right is ignored for mono.
+    pub fn process(&mut self, frames: &[[f32; 2]]) {
+        for frame in frames {
+            for (channel, &sample) in frame.iter().take(self.channels).enumerate() {
+                let [shelf, high_pass] = &mut self.filters[channel];
+                let weighted = high_pass.process(shelf.process(sample as f64));
+                self.current.weighted += weighted * weighted;
+                self.current.squares += (sample as f64).powi(2);
+                self.current.sample_peak = self.current.sample_peak.max(sample.abs());
+
+                // Phase 0 falls on a sample, so only the others are needed
+                let history = &mut self.history[channel];
+                history.copy_within(1.., 0);
+                history[TAPS_PER_PHASE - 1] = sample;
+                for taps in &self.oversampling[1..] {
+                    let mut value = 0.0;
+                    for j in 0..TAPS_PER_PHASE {
+                        value += taps[j] * history[TAPS_PER_PHASE - 1 - j];
+                    }
+                    self.current.true_peak = self.current.true_peak.max(value.abs());
+                }
+                self.current.true_peak = self.current.true_peak.max(sample.abs());
+            }
+            self.current.frames += 1;
+            if self.current.frames == self.step_frames {
+                self.finish_step();
+            }
+        }
+    }
+
+    /// Readings as of the last whole 100 ms processed.
+    pub fn loudness(&self) -> Loudness {
+        self.loudness
+    }
+
+    /// Start over, forgetting the integrated loudness, range and peaks.
+    pub fn reset(&mut self) {
+        *self = Self::new(self.sample_rate, self.channels);
+    }
+
+    fn finish_step(&mut self) {
+        if self.steps.len() == SHORT_TERM_STEPS {
+            self.steps.pop_front();
+        }
+        self.steps.push_back(std::mem::take(&mut self.current));
+        self.max_true_peak = self.max_true_peak.max(self.steps.back().unwrap().true_peak);
+
+        let momentary = self.mean_square(MOMENTARY_STEPS);
+        let short_term = self.mean_square(SHORT_TERM_STEPS);
+        // Gating blocks are whole windows, one per step
+        if self.steps.len() >= MOMENTARY_STEPS && loudness(momentary) > ABSOLUTE_GATE {
+            self.momentary_blocks.add(momentary);
+        }
+        if self.steps.len() == SHORT_TERM_STEPS && loudness(short_term) > ABSOLUTE_GATE {
+            self.short_term_blocks.add(short_term);
+        }
+
+        let recent = self.steps.iter().rev().take(RMS_STEPS);
+        let (squares, samples) = recent.clone().fold((0.0, 0), |(s, n), step| {
+            (s + step.squares, n + step.frames * self.channels)
+        });
+        let true_peak = recent.clone().map(|s| s.true_peak).fold(0.0, f32::max);
+        let sample_peak = recent.map(|s| s.sample_peak).fold(0.0, f32::max);
+
+        self.loudness = Loudness {
+            momentary: loudness(momentary) as f32,
+            short_term: loudness(short_term) as f32,
+            integrated: self.momentary_blocks.integrated_loudness() as f32,
+            range: self.short_term_blocks.loudness_range() as f32,
+            true_peak: decibels(true_peak as f64) as f32,
+            max_true_peak: decibels(self.max_true_peak as f64) as f32,
+            rms: decibels((squares / samples.max(1) as f64).sqrt()) as f32,
+            sample_peak: decibels(sample_peak as f64) as f32,
+        };
+    }
+
+    /// Mean square of the K-weighted samples over the last `steps` steps,
+    /// summed over the channels.
+    fn mean_square(&self, steps: usize) -> f64 {
+        let recent = self.steps.iter().rev().take(steps);
+        let (weighted, frames) = recent.fold((0.0, 0), |(w, n), step| {
+            (w + step.weighted, n + step.frames)
+        });
+        weighted / frames.max(1) as f64
+    }
+}
+
+/// Loudness in LUFS of a K-weighted mean square summed over channels.
+fn loudness(mean_square: f64) -> f64 {
+    LOUDNESS_OFFSET + 10.0 * mean_square.log10()
+}
+
+fn decibels(amplitude: f64) -> f64 {
+    20.0 * amplitude.log10()
+}
+
+/// Gating blocks above the absolute gate, counted by loudness in bins of
+/// `HISTOGRAM_STEP` LU, so the gates and percentiles cost the same however
+/// long the meter has run.
+struct BlockHistogram {
+    counts: Vec<u64>,
+    /// Summed mean squares of the blocks in each bin, so the averages are
+    /// exact rather than rounded to the bins.
+    mean_squares: Vec<f64>,
+    count: u64,
+    mean_square: f64,
+}
+
+impl BlockHistogram {
+    fn new() -> Self {
+        let bins = ((HISTOGRAM_MAX - ABSOLUTE_GATE) / HISTOGRAM_STEP).round() as usize;
+        Self {
+            counts: vec![0; bins],
+            mean_squares: vec![0.0; bins],
+            count: 0,
+            mean_square: 0.0,
+        }
+    }
+
+    /// Count a block of `mean_square`, already above the absolute gate.
+    fn add(&mut self, mean_square: f64) {
+        let bin = self.bin(loudness(mean_square));
+        self.counts[bin] += 1;
+        self.mean_squares[bin] += mean_square;
+        self.count += 1;
+        self.mean_square += mean_square;
+    }
+
+    fn bin(&self, loudness: f64) -> usize {
+        let bin = ((loudness - ABSOLUTE_GATE) / HISTOGRAM_STEP)
+            .floor()
+            .max(0.0) as usize;
+        bin.min(self.counts.len() - 1)
+    }
+
+    /// Loudness in the middle of `bin`, in LUFS.
+    fn bin_loudness(bin: usize) -> f64 {
+        ABSOLUTE_GATE + (bin as f64 + 0.5) * HISTOGRAM_STEP
+    }
+
+    /// First bin above `relative_gate` LU below the average of all the
+    /// blocks: a bin counts if its middle is above the gate.
+    fn first_gated_bin(&self, relative_gate: f64) -> usize {
+        let gate = loudness(self.mean_square / self.count as f64) + relative_gate;
+        let bin = self.bin(gate);
+        if Self::bin_loudness(bin) > gate {
+            bin
+        } else {
+            bin + 1
+        }
+    }
+
+    /// BS.1770 gated loudness: the average of the blocks within
+    /// `INTEGRATED_RELATIVE_GATE` of the average of them all.
+    fn integrated_loudness(&self) -> f64 {
+        if self.count == 0 {
+            return f64::NEG_INFINITY;
+        }
+        let first = self.first_gated_bin(INTEGRATED_RELATIVE_GATE);
+        let count: u64 = self.counts[first..].iter().sum();
+        let mean_square: f64 = self.mean_squares[first..].iter().sum();
+        loudness(mean_square / count.max(1) as f64)
+    }
+
+    /// EBU 3342 loudness range of short-term blocks, in LU.
+    fn loudness_range(&self) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        let first = self.first_gated_bin(RANGE_RELATIVE_GATE);
+        let gated = &self.counts[first.min(self.counts.len())..];
+        let count: u64 = gated.iter().sum();
+        if count == 0 {
+            return 0.0;
+        }
+        // Loudness of the `p` percentile block, as if they were sorted
+        let percentile = |p: f64| {
+            let index = ((count - 1) as f64 * p).round() as u64;
+            let mut seen = 0;
+            let bin = gated
+                .iter()
+                .position(|&n| {
+                    seen += n;
+                    seen > index
+                })
+                .unwrap();
+            Self::bin_loudness(first + bin)
+        };
+        percentile(RANGE_HIGH_PERCENTILE) - percentile(RANGE_LOW_PERCENTILE)
+    }
+}
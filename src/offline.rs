@@ -0,0 +1,96 @@
+use std::path::Path;
+
+use audio_visualizer::pipeline::FramePipeline;
+use audio_visualizer::{audio, export, renderer, target};
+
+use crate::config;
+use crate::configure::{configure_renderer, configured_analyzer, startup_theme};
+
+// ---- Tuning knobs (change these to taste) ----------------------------------
+
+/// Frame rate of offline exports.
+const EXPORT_FPS: u32 = 60;
+/// Size of headless and exported frames.
+const EXPORT_SIZE: (u32, u32) = (1280, 720);
+
+// ----------------------------------------------------------------------------
+
+/// Render a single frame without a window and save it as a PNG, e.g. a
+/// thumbnail of a track. Uses the spectrum at the middle of `wav`, and the
+/// loudness up to there, or silence if no file is given.
+pub fn run_headless(out: &str, wav: Option<&str>, config: &config::Config) {
+    let mut analyzer = configured_analyzer(config);
+    let mut renderer = new_headless_renderer(config);
+    if let Some(path) = wav {
+        let decoded = decoded_wav(path);
+        analyzer.set_scale(config.scale, decoded.sample_rate);
+        renderer.set_bar_frequencies(analyzer.bar_frequencies());
+
+        let mut pipeline = FramePipeline::new(decoded.sample_rate, decoded.channels);
+        let end = (decoded.frames() / 2 + config.fft_size / 2) as isize;
+        let window = pipeline.window_size(&analyzer) as isize;
+        pipeline.feed(&decoded.stereo_range(0, end));
+        // From silence, bars jump straight to the frame's heights
+        pipeline.process(&mut analyzer, &decoded.mono_range(end - window, end), 0.0);
+        pipeline.update_renderer(&mut renderer);
+    }
+    renderer.render(analyzer.values());
+    if let Err(e) = renderer.save_png(Path::new(out)) {
+        eprintln!("Failed to write {out}: {e}");
+        std::process::exit(1);
+    }
+    println!("Wrote {out}");
+}
+
+/// `path` decoded, exiting if it can't be read.
+fn decoded_wav(path: &str) -> audio::DecodedAudio {
+    audio::decode_wav(path).unwrap_or_else(|e| {
+        eprintln!("Failed to open {path} (only WAV is supported): {e}");
+        std::process::exit(2);
+    })
+}
+
+/// Create an offscreen renderer of `EXPORT_SIZE`.
+///
+/// Prefers the software adapter so output is the same on every machine,
+/// but uses a real GPU if that's all there is.
+fn new_headless_renderer(config: &config::Config) -> renderer::Renderer<target::OffscreenTarget> {
+    let (width, height) = EXPORT_SIZE;
+    let new_renderer = |software| {
+        pollster::block_on(renderer::Renderer::new_headless(
+            width,
+            height,
+            config.bars as u32,
+            config.layout,
+            config.layout_params,
+            software,
+        ))
+    };
+    let mut renderer = new_renderer(true)
+        .or_else(|| new_renderer(false))
+        .expect("No suitable GPU adapter found");
+    renderer.set_theme(&startup_theme(config));
+    configure_renderer(&mut renderer, config);
+    renderer
+}
+
+/// Render `wav` to a PNG sequence in `out`, or to a Y4M stream on stdout if
+/// `out` is `-`.
+pub fn run_export(out: &str, wav: &str, config: &config::Config) {
+    let decoded = decoded_wav(wav);
+    let output = if out == "-" {
+        export::ExportOutput::Y4mStdout
+    } else {
+        export::ExportOutput::PngSequence(out.into())
+    };
+
+    let mut renderer = new_headless_renderer(config);
+    let mut analyzer = configured_analyzer(config);
+    analyzer.set_scale(config.scale, decoded.sample_rate);
+    renderer.set_bar_frequencies(analyzer.bar_frequencies());
+
+    if let Err(e) = export::export(&decoded, &mut renderer, &mut analyzer, EXPORT_FPS, &output) {
+        eprintln!("Export failed: {e}");
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,196 @@
+use std::sync::{Arc, Mutex};
+
+use crate::analysis::Analyzer;
+use crate::audio::Source;
+use crate::beat::{BeatTracker, Tempo};
+use crate::features::{FeatureExtractor, Features};
+use crate::harmony::{Harmony, HarmonyTracker};
+use crate::loudness::{Loudness, LoudnessMeter};
+use crate::pitch::{Pitch, PitchDetector};
+use crate::renderer::Renderer;
+use crate::target::RenderTarget;
+
+/// The analysis that needs every sample rather than the latest window: the
+/// tempo and the loudness. A [`Source`] runs it in its audio callback, so
+/// nothing is missed when frames are slow to draw.
+pub struct StreamAnalysis {
+    beats: BeatTracker,
+    loudness: LoudnessMeter,
+    /// The mono mix for the beat tracker, kept between calls so the audio
+    /// callback doesn't allocate once it has grown.
+    mono: Vec<f32>,
+}
+
+/// A [`StreamAnalysis`] that the audio thread feeds and the render loop
+/// reads.
+pub type SharedStreamAnalysis = Arc<Mutex<StreamAnalysis>>;
+
+impl StreamAnalysis {
+    /// Trackers for audio at `sample_rate` with `channels` channels.
+    pub fn new(sample_rate: u32, channels: usize) -> Self {
+        Self {
+            beats: BeatTracker::new(sample_rate),
+            loudness: LoudnessMeter::new(sample_rate, channels),
+            mono: Vec::new(),
+        }
+    }
+
+    /// Take in the next `[left, right]` pairs, in chunks of any size.
+    pub fn process(&mut self, stereo: &[[f32; 2]]) {
+        self.mono.clear();
+        self.mono.extend(stereo.iter().map(|[l, r]| (l + r) / 2.0));
+        self.beats.process(&self.mono);
+        self.loudness.process(stereo);
+    }
+
+    pub fn beats(&self) -> &BeatTracker {
+        &self.beats
+    }
+
+    pub fn tempo(&self) -> Option<Tempo> {
+        self.beats.tempo()
+    }
+
+    /// Loudness of everything heard so far.
+    pub fn loudness(&self) -> Loudness {
+        self.loudness.loudness()
+    }
+}
+
+/// The analysis behind each frame of the display, wherever the samples
+/// come from: the bars, the pitch, key and chord and spectral features of
+/// the latest samples, and the tempo and loudness of a
+/// [`StreamAnalysis`], handed to a [`Renderer`] together.
+///
+/// The live visualizer, the headless thumbnail and the export all go
+/// through here, so a frame of a file looks the same whichever drew it.
+///
+/// ```
+/// use audio_visualizer::pipeline::FramePipeline;
+/// use audio_visualizer::Analyzer;
+///
+/// let sample_rate = 48_000;
+/// let mut analyzer = Analyzer::default();
+/// analyzer.set_scale(analyzer.scale(), sample_rate);
+/// let mut pipeline = FramePipeline::new(sample_rate, 1);
+///
+/// let tone: Vec<f32> = (0..pipeline.window_size(&analyzer))
+///     .map(|i| 0.5 * (std::f32::consts::TAU * 440.0 * i as f32 / sample_rate as f32).sin())
+///     .collect();
+/// let stereo: Vec<[f32; 2]> = tone.iter().map(|&s| [s, s]).collect();
+/// pipeline.feed(&stereo);
+/// pipeline.process(&mut analyzer, &tone, 1.0 / 60.0);
+///
+/// assert_eq!(pipeline.pitch().unwrap().note().to_string(), "A4");
+/// assert!(analyzer.values().iter().any(|&v| v > 0.0));
+/// ```
+pub struct FramePipeline {
+    sample_rate: u32,
+    stream: SharedStreamAnalysis,
+    pitch: PitchDetector,
+    harmony: HarmonyTracker,
+    features: FeatureExtractor,
+    /// The last frame's results, `None` until the first.
+    last_pitch: Option<Pitch>,
+    last_harmony: Option<Harmony>,
+    last_features: Option<Features>,
+    /// Samples of the last frame's FFT, for the waveform displays.
+    waveform: Vec<f32>,
+}
+
+impl FramePipeline {
+    /// Trackers for audio at `sample_rate` with `channels` channels, fed
+    /// with [`FramePipeline::feed`], e.g. from a decoded file.
+    pub fn new(sample_rate: u32, channels: usize) -> Self {
+        let stream = Arc::new(Mutex::new(StreamAnalysis::new(sample_rate, channels)));
+        Self::with_stream(sample_rate, stream)
+    }
+
+    /// Trackers for `source`, whose audio callback feeds the tempo and
+    /// loudness.
+    pub fn for_source(source: &Source) -> Self {
+        Self::with_stream(source.info().sample_rate, source.stream_analysis().clone())
+    }
+
+    fn with_stream(sample_rate: u32, stream: SharedStreamAnalysis) -> Self {
+        Self {
+            sample_rate,
+            stream,
+            pitch: PitchDetector::new(sample_rate),
+            harmony: HarmonyTracker::new(sample_rate),
+            features: FeatureExtractor::new(sample_rate),
+            last_pitch: None,
+            last_harmony: None,
+            last_features: None,
+            waveform: Vec::new(),
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Number of mono samples a frame looks at with `analyzer`: its FFT
+    /// or the pitch detector's window, whichever is longer.
+    pub fn window_size(&self, analyzer: &Analyzer) -> usize {
+        analyzer.fft_size().max(self.pitch.window_size())
+    }
+
+    /// Take in every `[left, right]` pair since the previous frame, for the
+    /// tempo and loudness. Not needed [for a source](Self::for_source).
+    pub fn feed(&self, stereo: &[[f32; 2]]) {
+        self.stream.lock().unwrap().process(stereo);
+    }
+
+    /// Analyze one frame, `dt` seconds after the previous one, from
+    /// `latest`, the most recent mono samples, oldest first: ideally
+    /// [`FramePipeline::window_size`] of them (fewer are zero-padded at the
+    /// start).
+    pub fn process(&mut self, analyzer: &mut Analyzer, latest: &[f32], dt: f32) {
+        // The FFT frame: the last `fft_size` samples, padded if short
+        let fft_size = analyzer.fft_size();
+        let recent = &latest[latest.len().saturating_sub(fft_size)..];
+        self.waveform.clear();
+        self.waveform.resize(fft_size - recent.len(), 0.0);
+        self.waveform.extend_from_slice(recent);
+
+        analyzer.process(&self.waveform, dt);
+        let spectrum = analyzer.spectrum();
+        self.last_harmony = Some(self.harmony.process(spectrum, dt));
+        self.last_features = Some(self.features.process(&self.waveform, spectrum));
+        self.last_pitch = self.pitch.process(latest);
+    }
+
+    /// Give `renderer` the last frame's tempo, pitch, harmony, loudness,
+    /// features and waveform. The bars are the analyzer's values.
+    pub fn update_renderer<T: RenderTarget>(&self, renderer: &mut Renderer<T>) {
+        let (tempo, loudness) = {
+            let stream = self.stream.lock().unwrap();
+            (stream.tempo(), stream.loudness())
+        };
+        renderer.set_tempo(tempo);
+        renderer.set_pitch(self.last_pitch);
+        renderer.set_harmony(self.last_harmony);
+        renderer.set_loudness(Some(loudness));
+        renderer.set_features(self.last_features);
+        renderer.update_waveform(&self.waveform);
+    }
+
+    /// The tempo and loudness, shared with the audio thread for a source.
+    pub fn stream(&self) -> &SharedStreamAnalysis {
+        &self.stream
+    }
+
+    /// Pitch of the last frame, `None` for silence.
+    pub fn pitch(&self) -> Option<Pitch> {
+        self.last_pitch
+    }
+
+    pub fn harmony(&self) -> Option<Harmony> {
+        self.last_harmony
+    }
+
+    pub fn features(&self) -> Option<Features> {
+        self.last_features
+    }
+}
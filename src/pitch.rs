@@ -0,0 +1,235 @@
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+use std::fmt;
+use std::sync::Arc;
+
+// ---- Tuning knobs (change these to taste) ----------------------------------
+
+/// Pitch range searched, in Hz: the piano's, A0 to C8.
+pub const MIN_FREQUENCY: f32 = 27.5;
+pub const MAX_FREQUENCY: f32 = 4186.0;
+/// Confidence below which the tuner shows no note.
+pub const MIN_CONFIDENCE: f32 = 0.8;
+/// Part of the highest normalized autocorrelation peak an earlier peak
+/// needs to be picked instead, so a period isn't taken for two.
+const PEAK_THRESHOLD: f32 = 0.9;
+/// RMS level below which a window is treated as silence (about -60 dBFS).
+const MIN_RMS: f32 = 0.001;
+
+// ----------------------------------------------------------------------------
+
+/// Frequency of A4 in Hz, and its MIDI note number.
+const A4_FREQUENCY: f32 = 440.0;
+const A4_MIDI: i32 = 69;
+/// Pitch class names from C, with sharps.
+pub const NOTE_NAMES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+/// A detected fundamental frequency.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Pitch {
+    pub frequency: f32,
+    /// How periodic the window is: 0 = noise, 1 = a pure tone.
+    pub confidence: f32,
+}
+
+impl Pitch {
+    /// The equal-tempered note closest to the pitch.
+    pub fn note(&self) -> Note {
+        Note::from_frequency(self.frequency)
+    }
+}
+
+/// An equal-tempered note (A4 = 440 Hz) and how far a frequency is from it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Note {
+    /// MIDI note number: 69 for A4, 21 for the lowest piano key.
+    pub midi: i32,
+    /// Offset of the frequency from the note, from -50 to +50 cents.
+    pub cents: f32,
+}
+
+impl Note {
+    pub fn from_frequency(frequency: f32) -> Self {
+        let semitones = A4_MIDI as f32 + 12.0 * (frequency / A4_FREQUENCY).log2();
+        let midi = semitones.round();
+        Self {
+            midi: midi as i32,
+            cents: (semitones - midi) * 100.0,
+        }
+    }
+
+    /// Name without the octave, with sharps: `"C#"`.
+    pub fn name(&self) -> &'static str {
+        NOTE_NAMES[self.midi.rem_euclid(12) as usize]
+    }
+
+    /// Scientific pitch notation octave: 4 for middle C up to B4.
+    pub fn octave(&self) -> i32 {
+        self.midi.div_euclid(12) - 1
+    }
+
+    /// Frequency in Hz of the note itself, without the cents offset.
+    pub fn frequency(&self) -> f32 {
+        midi_frequency(self.midi as f32)
+    }
+}
+
+impl fmt::Display for Note {
+    /// Name and octave, e.g. `A4`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.name(), self.octave())
+    }
+}
+
+/// Frequency in Hz of a (fractional) MIDI note number.
+pub fn midi_frequency(midi: f32) -> f32 {
+    A4_FREQUENCY * 2f32.powf((midi - A4_MIDI as f32) / 12.0)
+}
+
+/// Monophonic pitch detection with the McLeod pitch method: the first
+/// strong peak of the normalized square difference function of the latest
+/// samples.
+///
+/// ```
+/// use audio_visualizer::pitch::PitchDetector;
+///
+/// let sample_rate = 48_000;
+/// let mut detector = PitchDetector::new(sample_rate);
+/// let tone: Vec<f32> = (0..detector.window_size())
+///     .map(|i| (std::f32::consts::TAU * 440.0 * i as f32 / sample_rate as f32).sin())
+///     .collect();
+/// let note = detector.process(&tone).unwrap().note();
+/// assert_eq!(note.to_string(), "A4");
+/// assert!(note.cents.abs() < 1.0);
+/// ```
+pub struct PitchDetector {
+    sample_rate: u32,
+    window_size: usize,
+    /// Forward and inverse FFTs of twice the window, for the
+    /// autocorrelation without wrap-around.
+    fft: Arc<dyn rustfft::Fft<f32>>,
+    ifft: Arc<dyn rustfft::Fft<f32>>,
+    buffer: Vec<Complex<f32>>,
+    scratch: Vec<Complex<f32>>,
+    nsdf: Vec<f32>,
+}
+
+impl PitchDetector {
+    pub fn new(sample_rate: u32) -> Self {
+        let sample_rate = sample_rate.max(1);
+        // Two periods of the lowest pitch, within what the ring buffer holds
+        let window_size = ((2.0 * sample_rate as f32 / MIN_FREQUENCY) as usize)
+            .next_power_of_two()
+            .min(crate::audio::MAX_BUFFER_SIZE);
+        let mut planner = FftPlanner::new();
+        let fft = planner.plan_fft_forward(window_size * 2);
+        let ifft = planner.plan_fft_inverse(window_size * 2);
+        let scratch_len = fft
+            .get_inplace_scratch_len()
+            .max(ifft.get_inplace_scratch_len());
+
+        Self {
+            sample_rate,
+            window_size,
+            fft,
+            ifft,
+            buffer: Vec::with_capacity(window_size * 2),
+            scratch: vec![Complex::new(0.0, 0.0); scratch_len],
+            nsdf: Vec::with_capacity(window_size),
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Number of samples [`PitchDetector::process`] looks at.
+    pub fn window_size(&self) -> usize {
+        self.window_size
+    }
+
+    /// Detect the pitch of the last `window_size` of `samples`; fewer are
+    /// zero-padded. `None` for silence or when nothing repeats in range.
+    pub fn process(&mut self, samples: &[f32]) -> Option<Pitch> {
+        let n = self.window_size;
+        let window = &samples[samples.len().saturating_sub(n)..];
+        let energy: f32 = window.iter().map(|s| s * s).sum();
+        if energy < MIN_RMS * MIN_RMS * n as f32 {
+            return None;
+        }
+
+        // Autocorrelation r(τ) as the inverse FFT of the power spectrum
+        self.buffer.clear();
+        self.buffer
+            .extend(window.iter().map(|&s| Complex::new(s, 0.0)));
+        self.buffer.resize(n * 2, Complex::new(0.0, 0.0));
+        self.fft
+            .process_with_scratch(&mut self.buffer, &mut self.scratch);
+        for bin in &mut self.buffer {
+            *bin = Complex::new(bin.norm_sqr(), 0.0);
+        }
+        self.ifft
+            .process_with_scratch(&mut self.buffer, &mut self.scratch);
+
+        // n(τ) = 2 r(τ) / m(τ), where m(τ) is the energy of both the
+        // overlapping parts, updated as τ grows
+        let sample = |i: usize| window.get(i).copied().unwrap_or(0.0);
+        let mut m = 2.0 * energy;
+        self.nsdf.clear();
+        for tau in 0..n {
+            if tau > 0 {
+                m -= sample(tau - 1).powi(2) + sample(n - tau).powi(2);
+            }
+            let r = self.buffer[tau].re / (n * 2) as f32;
+            self.nsdf
+                .push(if m > f32::EPSILON { 2.0 * r / m } else { 0.0 });
+        }
+
+        let min_tau = (self.sample_rate as f32 / MAX_FREQUENCY).floor() as usize;
+        let max_tau = ((self.sample_rate as f32 / MIN_FREQUENCY).ceil() as usize).min(n / 2);
+        let (tau, clarity) = self.pick_period(min_tau.max(1), max_tau)?;
+        Some(Pitch {
+            frequency: self.sample_rate as f32 / tau,
+            confidence: clarity.clamp(0.0, 1.0),
+        })
+    }
+
+    /// The period in samples and its NSDF value: the highest point of the
+    /// first positive lobe that comes within `PEAK_THRESHOLD` of the
+    /// highest lobe, refined with a parabola.
+    fn pick_period(&self, min_tau: usize, max_tau: usize) -> Option<(f32, f32)> {
+        let nsdf = &self.nsdf;
+        // Lobes start where the NSDF crosses zero upwards, after the one
+        // around τ = 0 has ended
+        let first_negative = nsdf.iter().position(|&v| v < 0.0)?;
+        let mut peaks = Vec::new();
+        let mut best: Option<usize> = None;
+        for tau in first_negative..max_tau {
+            if nsdf[tau] > 0.0 {
+                if best.is_none_or(|b| nsdf[tau] > nsdf[b]) {
+                    best = Some(tau);
+                }
+            } else if let Some(b) = best.take() {
+                peaks.push(b);
+            }
+        }
+        peaks.extend(best);
+        peaks.retain(|&tau| tau >= min_tau && tau + 1 < nsdf.len());
+
+        let highest = peaks.iter().map(|&tau| nsdf[tau]).fold(0.0, f32::max);
+        let tau = *peaks
+            .iter()
+            .find(|&&tau| nsdf[tau] >= PEAK_THRESHOLD * highest)?;
+
+        let (left, peak, right) = (nsdf[tau - 1], nsdf[tau], nsdf[tau + 1]);
+        let curvature = left - 2.0 * peak + right;
+        if curvature >= 0.0 {
+            return Some((tau as f32, peak));
+        }
+        let shift = (0.5 * (left - right) / curvature).clamp(-0.5, 0.5);
+        let value = peak - 0.25 * (left - right) * shift;
+        Some((tau as f32 + shift, value))
+    }
+}
@@ -1,43 +1,196 @@
-use glam::{Mat4, Vec3};
+use glam::{Vec2, Vec3, Vec4};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use wgpu::util::DeviceExt;
 use winit::window::Window;
 
-/// Uniform parameters sent to the shader.
+use crate::bars::Bars;
+use crate::beat::Tempo;
+use crate::bloom::{self, Bloom};
+use crate::compositor::{Compositor, Layer};
+use crate::features::Features;
+use crate::feedback::Feedback;
+use crate::harmony::Harmony;
+use crate::layout::{self, Layout, LayoutParams};
+use crate::loudness::Loudness;
+use crate::pitch::{self, Pitch, NOTE_NAMES};
+use crate::shadertoy::ShaderToy;
+use crate::spectrogram::Spectrogram;
+use crate::target::{OffscreenTarget, RenderTarget, SurfaceTarget};
+use crate::text::{self, TextRenderer};
+use crate::theme::{ColorMode, Theme};
+use crate::vectorscope::Vectorscope;
+use crate::visualizer::{AnalysisFrame, Registry, SceneFormat, Visualizer};
+
+/// Largest magnitude the bar shader displays; higher values are clamped.
+const MAX_MAGNITUDE: f32 = 2.0;
+
+// ---- Overlay ----------------------------------------------------------------
+
+/// Screen pixels per font pixel of overlay text.
+const TEXT_SCALE: f32 = 2.0;
+/// Gap in pixels between overlay text and screen edges or bar bases.
+const TEXT_MARGIN: f32 = 8.0;
+const TEXT_COLOR: [f32; 4] = [0.85, 0.85, 0.85, 0.9];
+const GRID_COLOR: [f32; 4] = [0.6, 0.6, 0.6, 0.4];
+/// Frequencies in Hz worth a label; each labels the bar closest to it.
+const LABEL_FREQUENCIES: [f32; 10] = [
+    30.0, 60.0, 100.0, 200.0, 500.0, 1000.0, 2000.0, 5000.0, 10000.0, 20000.0,
+];
+/// Levels of the dB grid, relative to a full-height bar.
+const GRID_LEVELS_DB: [f32; 5] = [0.0, -6.0, -12.0, -18.0, -24.0];
+/// Seconds a notice stays up, the last of which it spends fading out.
+const NOTICE_SECONDS: f32 = 2.0;
+/// Width in pixels of the tuner's cents scale, and offset within which a
+/// note counts as in tune.
+const TUNER_WIDTH: f32 = 200.0;
+const IN_TUNE_CENTS: f32 = 5.0;
+const IN_TUNE_COLOR: [f32; 4] = [0.3, 0.9, 0.4, 1.0];
+/// Size in pixels of each loudness meter, and the level at its bottom in
+/// dB. The loudness target is marked on the LUFS meters in the in-tune
+/// color; the true peak meter turns red above the ceiling.
+const METER_HEIGHT: f32 = 160.0;
+const METER_WIDTH: f32 = 10.0;
+const METER_FLOOR_DB: f32 = -60.0;
+const LOUDNESS_TARGET: f32 = -23.0;
+const TRUE_PEAK_CEILING: f32 = -1.0;
+const OVER_COLOR: [f32; 4] = [0.95, 0.3, 0.25, 1.0];
+
+/// Uniforms of `background.wgsl`, in linear RGBA.
 #[repr(C)]
 #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
-struct Params {
-    num_bars: u32,
-    // Pad to 16 bytes (minimum uniform buffer alignment)
-    _pad: [u32; 3],
+struct BackgroundUniforms {
+    bottom: [f32; 4],
+    top: [f32; 4],
+}
+
+/// Which overlay elements are drawn on top of the views. All off by default.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Overlay {
+    /// Centre frequency labels under the bars, or note names beside the
+    /// chromagram.
+    pub frequencies: bool,
+    /// dB grid through the bars.
+    pub grid: bool,
+    /// Track name and time, or the input device name.
+    pub source: bool,
+    /// Frame rate and frame times.
+    pub timing: bool,
+    /// Tempo in BPM, flashing on the beat.
+    pub tempo: bool,
+    /// Note being played and how far off it is, as a tuner.
+    pub tuner: bool,
+    /// Key, with its Camelot code for harmonic mixing, and chord.
+    pub harmony: bool,
+    /// Loudness meters: momentary, short-term and integrated LUFS, true
+    /// peak and RMS, with the loudness range and crest factor.
+    pub meters: bool,
+}
+
+/// Frame rate and frame times averaged over half a second, so the readout
+/// changes slowly enough to read.
+#[derive(Default)]
+struct FrameStats {
+    frames: u32,
+    elapsed: f32,
+    longest: f32,
+    /// Last full average: frames per second, mean and longest frame in ms.
+    shown: Option<(f32, f32, f32)>,
 }
 
-pub struct Renderer {
-    surface: wgpu::Surface<'static>,
+impl FrameStats {
+    fn add(&mut self, dt: f32) {
+        if dt <= 0.0 {
+            return;
+        }
+        self.frames += 1;
+        self.elapsed += dt;
+        self.longest = self.longest.max(dt);
+        if self.elapsed >= 0.5 {
+            let shown = (
+                self.frames as f32 / self.elapsed,
+                self.elapsed / self.frames as f32 * 1000.0,
+                self.longest * 1000.0,
+            );
+            *self = Self {
+                shown: Some(shown),
+                ..Default::default()
+            };
+        }
+    }
+
+    fn text(&self) -> String {
+        match self.shown {
+            Some((fps, mean, longest)) => {
+                format!("{fps:.0} fps  {mean:.1} ms  max {longest:.1} ms")
+            }
+            None => "-- fps".to_string(),
+        }
+    }
+}
+
+pub struct Renderer<T: RenderTarget = SurfaceTarget> {
+    target: T,
     device: wgpu::Device,
     queue: wgpu::Queue,
-    config: wgpu::SurfaceConfiguration,
-    pipeline: wgpu::RenderPipeline,
-    magnitudes_buffer: wgpu::Buffer,
-    bind_group: wgpu::BindGroup,
-    num_bars: u32,
+    background_pipeline: wgpu::RenderPipeline,
+    background_buffer: wgpu::Buffer,
+    background_bind_group: wgpu::BindGroup,
+    /// MSAA samples per pixel for the scene passes, one of `sample_counts`.
+    sample_count: u32,
+    /// Sample counts the scene format supports on this device, ascending.
+    sample_counts: Vec<u32>,
+    /// Multisampled color target resolved into the scene; `None` at 1×.
+    msaa_view: Option<wgpu::TextureView>,
+    theme: Theme,
+    /// The visual modes, drawn one at a time unless there are layers.
+    visualizers: Registry,
+    /// Several visualizers at once, each in its own viewport.
+    compositor: Compositor,
+    /// `None` when the adapter can't render to float textures.
+    pub bloom: Option<Bloom>,
+    /// Motion trails behind the visualizer.
+    pub feedback: Feedback,
+    /// Seconds since start and since the previous frame, for time-based
+    /// shaders. Driven by the caller so exports can use a simulated clock.
+    time: f32,
+    time_delta: f32,
+    /// Latest mono samples and stereo pairs, handed to the visualizers on
+    /// the next frame.
+    waveform: Vec<f32>,
+    stereo: Vec<[f32; 2]>,
+    pub overlay: Overlay,
+    text: TextRenderer,
+    /// Centre frequency of each bar in Hz; empty until the sample rate is
+    /// known.
+    bar_frequencies: Vec<f32>,
+    /// Overlay line describing the audio source.
+    source_info: String,
+    tempo: Option<Tempo>,
+    pitch: Option<Pitch>,
+    harmony: Option<Harmony>,
+    loudness: Option<Loudness>,
+    features: Option<Features>,
+    /// Transient message and the clock time it was shown at.
+    notice: Option<(String, f32)>,
+    frame_stats: FrameStats,
 }
 
-impl Renderer {
-    /// Initialise wgpu, compile the shader, and create the render pipeline.
-    pub async fn new(window: Arc<Window>, num_bars: u32) -> Self {
+impl Renderer<SurfaceTarget> {
+    /// Initialise wgpu for a window, compile the shader, and create the
+    /// render pipeline.
+    pub async fn new(
+        window: Arc<Window>,
+        num_bars: u32,
+        layout: Layout,
+        layout_params: LayoutParams,
+    ) -> Self {
         let size = window.inner_size();
 
         // --- Instance & Surface ---
-        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::all(),
-            ..Default::default()
-        });
-
-        // `Arc<Window>` → Surface<'static> because Arc is 'static
-        let surface = instance
-            .create_surface(window)
-            .expect("Failed to create surface");
+        let instance = create_instance();
+        let surface = SurfaceTarget::create_surface(&instance, window);
 
         // --- Adapter ---
         let adapter = instance
@@ -49,218 +202,548 @@ impl Renderer {
             .await
             .expect("No suitable GPU adapter found");
 
-        // --- Device & Queue ---
-        let (device, queue) = adapter
-            .request_device(&wgpu::DeviceDescriptor {
-                label: Some("Device"),
-                required_features: wgpu::Features::empty(),
-                required_limits: wgpu::Limits::default(),
-                memory_hints: wgpu::MemoryHints::default(),
-                trace: wgpu::Trace::Off,
-                experimental_features: wgpu::ExperimentalFeatures::disabled(),
-            })
-            .await
-            .expect("Failed to create device");
-
-        // --- Surface config ---
-        let caps = surface.get_capabilities(&adapter);
-        let format = caps
-            .formats
-            .iter()
-            .find(|f| f.is_srgb())
-            .copied()
-            .unwrap_or(caps.formats[0]);
-
-        let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format,
-            width: size.width.max(1),
-            height: size.height.max(1),
-            present_mode: wgpu::PresentMode::AutoVsync,
-            alpha_mode: caps.alpha_modes[0],
-            view_formats: vec![],
-            desired_maximum_frame_latency: 2,
-        };
-        surface.configure(&device, &config);
-
-        // ---------------------------------------------------------------
-        // --- GPU buffers ---
-        // ---------------------------------------------------------------
-        let magnitudes_data = vec![0.0f32; num_bars as usize];
-        let magnitudes_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Magnitudes"),
-            contents: bytemuck::cast_slice(&magnitudes_data),
-            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
-        });
-        let mut transforms_data: Vec<Mat4> = Vec::with_capacity(num_bars as usize);
+        // --- Device, Queue & Surface config ---
+        let (device, queue) = request_device(&adapter).await;
+        let target = SurfaceTarget::configure(surface, &adapter, &device, size.width, size.height);
 
-        let radius = 0.33f32;
-        for i in 0..num_bars {
-            let angle = (i as f32 / num_bars as f32) * std::f32::consts::TAU;
-            let x = radius * angle.cos();
-            let y = radius * angle.sin();
+        Self::with_target(
+            &adapter,
+            device,
+            queue,
+            target,
+            num_bars,
+            layout,
+            layout_params,
+        )
+    }
+}
 
-            let transform = Mat4::from_translation(Vec3::new(x, y, 0.0))
-                * Mat4::from_rotation_z(angle - std::f32::consts::FRAC_PI_2);
-            transforms_data.push(transform);
-        }
+impl Renderer<OffscreenTarget> {
+    /// Initialise wgpu without a window, drawing into a `width`×`height`
+    /// offscreen texture.
+    ///
+    /// With `software` set, only wgpu's fallback (CPU) adapter is used, so
+    /// this works on machines without a GPU and gives reproducible output.
+    /// Returns `None` if no suitable adapter exists.
+    pub async fn new_headless(
+        width: u32,
+        height: u32,
+        num_bars: u32,
+        layout: Layout,
+        layout_params: LayoutParams,
+        software: bool,
+    ) -> Option<Self> {
+        let instance = create_instance();
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: None,
+                force_fallback_adapter: software,
+            })
+            .await
+            .ok()?;
 
-        let transforms_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Transforms"),
-            contents: bytemuck::cast_slice(&transforms_data),
-            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
-        });
+        let (device, queue) = request_device(&adapter).await;
+        let target = OffscreenTarget::new(&device, width, height);
 
-        let params = Params {
+        Some(Self::with_target(
+            &adapter,
+            device,
+            queue,
+            target,
             num_bars,
-            _pad: [0; 3],
-        };
-        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Params"),
-            contents: bytemuck::bytes_of(&params),
-            usage: wgpu::BufferUsages::UNIFORM,
-        });
+            layout,
+            layout_params,
+        ))
+    }
 
-        // --- Bind group ---
-        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: Some("Bind Group Layout"),
-            entries: &[
-                wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Storage { read_only: true },
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: wgpu::ShaderStages::VERTEX,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-                wgpu::BindGroupLayoutEntry {
-                    binding: 2,
-                    visibility: wgpu::ShaderStages::VERTEX,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Storage { read_only: true },
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                },
-            ],
-        });
+    /// Read the last rendered frame back as tightly packed RGBA8 rows.
+    pub fn read_pixels(&self) -> Vec<u8> {
+        self.target.read_pixels(&self.device, &self.queue)
+    }
 
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Bind Group"),
-            layout: &bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: magnitudes_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: params_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: transforms_buffer.as_entire_binding(),
-                },
-            ],
-        });
+    /// Write the last rendered frame to `path` as a PNG.
+    pub fn save_png(&self, path: &Path) -> Result<(), png::EncodingError> {
+        self.target.save_png(&self.device, &self.queue, path)
+    }
+}
 
-        // --- Shader & pipeline ---
-        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into()),
-        });
+impl<T: RenderTarget> Renderer<T> {
+    /// Create the buffers and pipelines shared by every target, and the
+    /// built-in visualizers. If the adapter supports it the scene is drawn
+    /// into a float texture and composited through [`Bloom`]; otherwise
+    /// straight onto the target.
+    fn with_target(
+        adapter: &wgpu::Adapter,
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        target: T,
+        num_bars: u32,
+        layout: Layout,
+        layout_params: LayoutParams,
+    ) -> Self {
+        let format = target.format();
+        let (width, height) = target.size();
+        let hdr = bloom::supported(adapter);
+        let bloom = hdr.then(|| Bloom::new(&device, format, width, height));
+        let scene_format = if hdr { bloom::HDR_FORMAT } else { format };
+
+        // Start with the best anti-aliasing the scene format allows
+        let sample_counts = supported_sample_counts(adapter, &device, scene_format);
+        let sample_count = *sample_counts.last().unwrap_or(&1);
+        let msaa_view = create_msaa_view(&device, scene_format, width, height, sample_count);
 
-        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("Pipeline Layout"),
-            bind_group_layouts: &[&bind_group_layout],
-            immediate_size: 0,
+        // --- Background ---
+        let background_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Background"),
+            size: std::mem::size_of::<BackgroundUniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
         });
+        let (background_pipeline, background_bind_group) =
+            create_background_pipeline(&device, scene_format, sample_count, &background_buffer);
 
-        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: Some("vs_main"),
-                buffers: &[],
-                compilation_options: Default::default(),
+        let feedback = Feedback::new(&device, scene_format, sample_count, width, height);
+        let compositor = Compositor::new(
+            &device,
+            SceneFormat {
+                format: scene_format,
+                sample_count,
             },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: Some("fs_main"),
-                compilation_options: Default::default(),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                ..Default::default()
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState::default(),
-            multiview_mask: None,
-            cache: None,
-        });
+            width,
+            height,
+        );
+        let text = TextRenderer::new(&device, &queue, format);
 
-        Self {
-            surface,
+        let mut renderer = Self {
+            target,
             device,
             queue,
-            config,
-            pipeline,
-            magnitudes_buffer,
-            bind_group,
-            num_bars,
-        }
+            background_pipeline,
+            background_buffer,
+            background_bind_group,
+            sample_count,
+            sample_counts,
+            msaa_view,
+            theme: Theme::builtin()[0].clone(),
+            visualizers: Registry::new(),
+            compositor,
+            bloom,
+            feedback,
+            time: 0.0,
+            time_delta: 0.0,
+            waveform: Vec::new(),
+            stereo: Vec::new(),
+            overlay: Overlay::default(),
+            text,
+            bar_frequencies: Vec::new(),
+            source_info: String::new(),
+            tempo: None,
+            pitch: None,
+            harmony: None,
+            loudness: None,
+            features: None,
+            notice: None,
+            frame_stats: FrameStats::default(),
+        };
+        renderer.set_theme(&renderer.theme.clone());
+
+        // --- Built-in visualizers, in cycling order ---
+        let scene = renderer.scene_format();
+        let device = &renderer.device;
+        let bars = Bars::new(device, scene, num_bars, layout, layout_params);
+        let spectrogram = Spectrogram::new(device, scene);
+        let chromagram = Spectrogram::chromagram(device, scene);
+        let vectorscope = Vectorscope::new(device, scene);
+        let shadertoy = ShaderToy::new(device, scene);
+        renderer.add_visualizer(Box::new(bars));
+        renderer.add_visualizer(Box::new(spectrogram));
+        renderer.add_visualizer(Box::new(chromagram));
+        renderer.add_visualizer(Box::new(vectorscope));
+        renderer.add_visualizer(Box::new(shadertoy));
+        renderer
     }
 
-    /// Call when the window is resized.
+    /// Call when the window (or offscreen target) is resized.
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
         if new_size.width > 0 && new_size.height > 0 {
-            self.config.width = new_size.width;
-            self.config.height = new_size.height;
-            self.surface.configure(&self.device, &self.config);
+            self.target
+                .resize(&self.device, new_size.width, new_size.height);
+            if let Some(bloom) = &mut self.bloom {
+                bloom.resize(&self.device, new_size.width, new_size.height);
+            }
+            self.feedback
+                .resize(&self.device, new_size.width, new_size.height);
+            self.msaa_view = create_msaa_view(
+                &self.device,
+                self.scene_format().format,
+                new_size.width,
+                new_size.height,
+                self.sample_count,
+            );
+            self.compositor
+                .resize(&self.device, &self.queue, new_size.width, new_size.height);
+            self.resize_visualizers();
         }
     }
 
-    /// Upload new magnitudes and draw one frame.
-    pub fn render(&mut self, magnitudes: &[f32]) {
-        // Upload bar magnitudes to GPU
-        self.queue
-            .write_buffer(&self.magnitudes_buffer, 0, bytemuck::cast_slice(magnitudes));
+    /// Size each visualizer to its layer's viewport, or the whole target
+    /// if it isn't in a layer.
+    fn resize_visualizers(&mut self) {
+        let (width, height) = self.target.size();
+        let layers = self.compositor.layers();
+        for visualizer in self.visualizers.iter_mut() {
+            let (w, h) = match layers.iter().find(|l| l.view == visualizer.name()) {
+                Some(layer) => {
+                    let [_, _, w, h] = layer.viewport(width, height);
+                    (w, h)
+                }
+                None => (width, height),
+            };
+            visualizer.resize(&self.device, &self.queue, w, h);
+        }
+    }
 
-        let output = match self.surface.get_current_texture() {
-            Ok(tex) => tex,
-            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
-                self.surface.configure(&self.device, &self.config);
-                return;
+    /// Size of the render target in physical pixels.
+    pub fn size(&self) -> (u32, u32) {
+        self.target.size()
+    }
+
+    /// The device visualizers are created on.
+    pub fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    pub fn queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+
+    /// Add a visual mode after the built-in ones (bars, spectrogram,
+    /// chromagram, vectorscope and Shadertoy). Create it for
+    /// [`Self::scene_format`]; it is sized and themed here.
+    ///
+    /// # Panics
+    ///
+    /// If a visualizer with the same name was already added.
+    pub fn add_visualizer(&mut self, mut visualizer: Box<dyn Visualizer>) {
+        let (width, height) = self.target.size();
+        visualizer.resize(&self.device, &self.queue, width, height);
+        visualizer.set_theme(&self.queue, &self.theme);
+        self.visualizers.add(visualizer);
+    }
+
+    /// Names of the visualizers in cycling order.
+    pub fn views(&self) -> impl Iterator<Item = &str> {
+        self.visualizers.names()
+    }
+
+    /// Name of the visualizer drawn when there are no layers.
+    pub fn view(&self) -> &str {
+        self.visualizers.active().name()
+    }
+
+    /// Draw the visualizer called `name` alone from now on, dropping any
+    /// layers. Returns `false`, changing nothing, if there is none.
+    pub fn set_view(&mut self, name: &str) -> bool {
+        if !self.visualizers.set_active(name) {
+            return false;
+        }
+        self.clear_layers();
+        true
+    }
+
+    /// Switch to the next visualizer, wrapping around, and draw it alone.
+    pub fn cycle_view(&mut self) {
+        self.visualizers.cycle();
+        self.clear_layers();
+    }
+
+    /// The layers being drawn, bottom first; empty when a single view is.
+    pub fn layers(&self) -> &[Layer] {
+        self.compositor.layers()
+    }
+
+    /// Draw several visualizers at once, bottom layer first, each in its
+    /// own viewport with its own opacity and blend mode. An empty stack
+    /// goes back to the single view.
+    ///
+    /// Fails, changing nothing, if a layer names no visualizer or two
+    /// layers name the same one.
+    pub fn set_layers(&mut self, layers: Vec<Layer>) -> Result<(), String> {
+        for (i, layer) in layers.iter().enumerate() {
+            if self.visualizers.named(&layer.view).is_none() {
+                return Err(format!("no view called {:?}", layer.view));
             }
-            Err(wgpu::SurfaceError::OutOfMemory) => panic!("GPU out of memory"),
-            Err(e) => {
-                eprintln!("Surface error: {e:?}");
-                return;
+            if layers[..i].iter().any(|l| l.view == layer.view) {
+                return Err(format!("view {:?} is in more than one layer", layer.view));
             }
+        }
+        self.compositor
+            .set_layers(&self.device, &self.queue, layers);
+        self.resize_visualizers();
+        Ok(())
+    }
+
+    fn clear_layers(&mut self) {
+        if !self.compositor.layers().is_empty() {
+            self.compositor
+                .set_layers(&self.device, &self.queue, Vec::new());
+            self.resize_visualizers();
+        }
+    }
+
+    /// The visualizer of type `V`, e.g. a built-in one to change its
+    /// settings.
+    pub fn visualizer<V: Visualizer>(&self) -> Option<&V> {
+        self.visualizers.get()
+    }
+
+    pub fn visualizer_mut<V: Visualizer>(&mut self) -> Option<&mut V> {
+        self.visualizers.get_mut()
+    }
+
+    /// Whether the visualizer being drawn is of type `V`.
+    pub fn is_view<V: Visualizer>(&self) -> bool {
+        self.visualizers.is_active::<V>()
+    }
+
+    fn bars(&self) -> &Bars {
+        self.visualizers.get().expect("bars are built in")
+    }
+
+    pub fn layout(&self) -> Layout {
+        self.bars().layout()
+    }
+
+    pub fn layout_params(&self) -> LayoutParams {
+        self.bars().layout_params()
+    }
+
+    /// Switch bar layout and/or layout parameters.
+    pub fn set_layout(&mut self, layout: Layout, params: LayoutParams) {
+        if let Some(bars) = self.visualizers.get_mut::<Bars>() {
+            bars.set_layout(&self.queue, layout, params);
+        }
+    }
+
+    /// Change the number of bars, reallocating the per-bar buffers.
+    pub fn set_num_bars(&mut self, num_bars: u32) {
+        if let Some(bars) = self.visualizers.get_mut::<Bars>() {
+            bars.set_num_bars(&self.device, &self.queue, num_bars);
+        }
+    }
+
+    /// Use `theme`'s background, and its palette and color mode for the
+    /// visualizers.
+    pub fn set_theme(&mut self, theme: &Theme) {
+        let [bottom, top] = theme.background;
+        let background = BackgroundUniforms {
+            bottom: bottom.to_linear(),
+            top: top.to_linear(),
         };
+        self.queue
+            .write_buffer(&self.background_buffer, 0, bytemuck::bytes_of(&background));
+
+        for visualizer in self.visualizers.iter_mut() {
+            visualizer.set_theme(&self.queue, theme);
+        }
+        self.theme = theme.clone();
+    }
+
+    pub fn color_mode(&self) -> ColorMode {
+        self.bars().color_mode()
+    }
+
+    /// Override the current theme's color mode for the bars.
+    pub fn set_color_mode(&mut self, color_mode: ColorMode) {
+        if let Some(bars) = self.visualizers.get_mut::<Bars>() {
+            bars.set_color_mode(&self.queue, color_mode);
+        }
+    }
+
+    /// Draw bars with a user WGSL file instead of the built-in shader, and
+    /// recompile it whenever the file changes (see [`Self::reload_shaders`]).
+    ///
+    /// The file must declare the same bindings and `vs_main` / `fs_main`
+    /// entry points as `shader.wgsl`. `None` returns to the built-in shader.
+    pub fn set_bar_shader(&mut self, path: Option<PathBuf>) {
+        if let Some(bars) = self.visualizers.get_mut::<Bars>() {
+            bars.set_shader(&self.device, path);
+        }
+    }
+
+    /// Recompile user shaders whose files changed. Cheap enough to call
+    /// every frame.
+    pub fn reload_shaders(&mut self) {
+        for visualizer in self.visualizers.iter_mut() {
+            visualizer.reload(&self.device);
+        }
+    }
+
+    /// The compile error of a user shader, if the last (re)load failed.
+    /// The previous pipeline stays in use until the file compiles again.
+    pub fn shader_error(&self) -> Option<&str> {
+        self.visualizers.iter().find_map(|v| v.error())
+    }
+
+    /// Use a user `mainImage` shader file for the Shadertoy view, reloaded
+    /// on change. `None` returns to the built-in one.
+    pub fn set_shadertoy_shader(&mut self, path: Option<PathBuf>) {
+        if let Some(shadertoy) = self.visualizers.get_mut::<ShaderToy>() {
+            shadertoy.set_shader(&self.device, path);
+        }
+    }
+
+    /// Advance the clock seen by time-based shaders.
+    pub fn set_time(&mut self, time: f32, time_delta: f32) {
+        self.time = time;
+        self.time_delta = time_delta;
+        self.frame_stats.add(time_delta);
+    }
+
+    /// Centre frequency of each bar in Hz, for the frequency labels.
+    pub fn set_bar_frequencies(&mut self, frequencies: Vec<f32>) {
+        self.bar_frequencies = frequencies;
+    }
+
+    /// Text of the source overlay line, e.g. the track name and time.
+    pub fn set_source_info(&mut self, info: String) {
+        self.source_info = info;
+    }
 
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+    /// Tempo and beat phase for the next frame, e.g. from a
+    /// [`crate::beat::BeatTracker`]; `None` when there is none.
+    pub fn set_tempo(&mut self, tempo: Option<Tempo>) {
+        self.tempo = tempo;
+    }
+
+    /// Pitch of the latest samples for the next frame, e.g. from a
+    /// [`crate::pitch::PitchDetector`]; `None` when there is none.
+    pub fn set_pitch(&mut self, pitch: Option<Pitch>) {
+        self.pitch = pitch;
+    }
+
+    /// Chroma, key and chord for the next frame, e.g. from a
+    /// [`crate::harmony::HarmonyTracker`]; `None` when there are none.
+    pub fn set_harmony(&mut self, harmony: Option<Harmony>) {
+        self.harmony = harmony;
+    }
+
+    /// Loudness readings for the next frame, e.g. from a
+    /// [`crate::loudness::LoudnessMeter`]; `None` when there are none.
+    pub fn set_loudness(&mut self, loudness: Option<Loudness>) {
+        self.loudness = loudness;
+    }
+
+    /// Spectral features for the next frame, e.g. from a
+    /// [`crate::features::FeatureExtractor`]; `None` when there are none.
+    pub fn set_features(&mut self, features: Option<Features>) {
+        self.features = features;
+    }
+
+    /// Show `text` at the top of the screen for a couple of seconds,
+    /// whatever the overlay settings, e.g. a setting that just changed.
+    pub fn show_notice(&mut self, text: String) {
+        self.notice = Some((text, self.time));
+    }
+
+    /// Set the most recent mono samples for waveform displays.
+    pub fn update_waveform(&mut self, samples: &[f32]) {
+        self.waveform.clear();
+        self.waveform.extend_from_slice(samples);
+    }
+
+    /// Set the `[left, right]` sample pairs since the previous frame, for
+    /// the stereo views.
+    pub fn update_stereo(&mut self, pairs: &[[f32; 2]]) {
+        self.stereo.clear();
+        self.stereo.extend_from_slice(pairs);
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// Sample counts [`Self::set_sample_count`] accepts on this device.
+    pub fn sample_counts(&self) -> &[u32] {
+        &self.sample_counts
+    }
+
+    /// Set the MSAA sample count for the scene. Unsupported counts fall
+    /// back to the highest supported one below them. Returns the count
+    /// actually used.
+    pub fn set_sample_count(&mut self, requested: u32) -> u32 {
+        let sample_count = self
+            .sample_counts
+            .iter()
+            .copied()
+            .filter(|&n| n <= requested)
+            .max()
+            .unwrap_or(1);
+        if sample_count == self.sample_count {
+            return sample_count;
+        }
+        self.sample_count = sample_count;
+
+        let scene = self.scene_format();
+        let (width, height) = self.target.size();
+        self.msaa_view = create_msaa_view(&self.device, scene.format, width, height, sample_count);
+        let (background_pipeline, background_bind_group) = create_background_pipeline(
+            &self.device,
+            scene.format,
+            sample_count,
+            &self.background_buffer,
+        );
+        self.background_pipeline = background_pipeline;
+        self.background_bind_group = background_bind_group;
+        self.feedback.set_sample_count(&self.device, sample_count);
+        self.compositor
+            .set_scene_format(&self.device, &self.queue, scene);
+        for visualizer in self.visualizers.iter_mut() {
+            visualizer.set_scene_format(&self.device, scene);
+        }
+        sample_count
+    }
+
+    /// What visualizers draw into: HDR when bloom is available, at the
+    /// current MSAA sample count.
+    pub fn scene_format(&self) -> SceneFormat {
+        SceneFormat {
+            format: match self.bloom {
+                Some(_) => bloom::HDR_FORMAT,
+                None => self.target.format(),
+            },
+            sample_count: self.sample_count,
+        }
+    }
+
+    /// Hand `magnitudes` (the bar heights) and the latest waveform and
+    /// stereo pairs to the visualizers, and draw one frame of the active one
+    /// or of the layers.
+    pub fn render(&mut self, magnitudes: &[f32]) {
+        let frame = AnalysisFrame {
+            bars: magnitudes,
+            max_height: MAX_MAGNITUDE,
+            bar_frequencies: &self.bar_frequencies,
+            waveform: &self.waveform,
+            stereo: &self.stereo,
+            time: self.time,
+            dt: self.time_delta,
+            tempo: self.tempo,
+            pitch: self.pitch,
+            harmony: self.harmony,
+            loudness: self.loudness,
+            features: self.features,
+        };
+        for visualizer in self.visualizers.iter_mut() {
+            visualizer.update(&self.device, &self.queue, &frame);
+        }
+        // Each pair is drawn once
+        self.stereo.clear();
+
+        let Some(frame) = self.target.acquire(&self.device) else {
+            return;
+        };
 
         let mut encoder = self
             .device
@@ -268,19 +751,73 @@ impl Renderer {
                 label: Some("Encoder"),
             });
 
-        {
+        if self.compositor.layers().is_empty() {
+            self.visualizers
+                .active_mut()
+                .prepare(&self.queue, &mut encoder);
+        } else {
+            for layer in self.compositor.layers() {
+                if let Some(visualizer) = self.visualizers.named_mut(&layer.view) {
+                    visualizer.prepare(&self.queue, &mut encoder);
+                }
+            }
+        }
+        if self.feedback.enabled() {
+            self.feedback.advance();
+        }
+
+        match &self.bloom {
+            Some(bloom) => {
+                self.draw_scene(&mut encoder, bloom.scene_view());
+                bloom.render(&self.queue, &mut encoder, &frame.view);
+            }
+            None => self.draw_scene(&mut encoder, &frame.view),
+        }
+
+        self.queue_overlay();
+        self.text
+            .draw(&self.queue, &mut encoder, &frame.view, self.target.size());
+
+        self.queue.submit(std::iter::once(encoder.finish()));
+        self.target.present(frame);
+    }
+
+    fn draw_scene(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let msaa = self.msaa_view.as_ref();
+        self.draw_layers(encoder);
+        if self.feedback.enabled() {
+            // The visualizers over the faded previous frame, then all of it
+            // onto the background so the background itself never smears
+            let mut pass = begin_pass(encoder, "Trails Pass", self.feedback.current_view(), msaa);
+            self.feedback.fade(&self.queue, &mut pass, self.time_delta);
+            self.draw_visualizers(&mut pass);
+            drop(pass);
+
+            let mut pass = begin_pass(encoder, "Render Pass", view, msaa);
+            self.draw_background(&mut pass);
+            self.feedback.composite(&mut pass);
+        } else {
+            let mut pass = begin_pass(encoder, "Render Pass", view, msaa);
+            self.draw_background(&mut pass);
+            self.draw_visualizers(&mut pass);
+        }
+    }
+
+    /// Render each layer's visualizer into the layer's own texture, over
+    /// transparent black.
+    fn draw_layers(&self, encoder: &mut wgpu::CommandEncoder) {
+        for (i, layer) in self.compositor.layers().iter().enumerate() {
+            let Some(visualizer) = self.visualizers.named(&layer.view) else {
+                continue;
+            };
+            let (view, msaa) = self.compositor.target(i);
             let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
+                label: Some("Layer Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
+                    view: msaa.unwrap_or(view),
+                    resolve_target: msaa.is_some().then_some(view),
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.0,
-                            g: 0.0,
-                            b: 0.0,
-                            a: 1.0,
-                        }),
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
                         store: wgpu::StoreOp::Store,
                     },
                     depth_slice: None,
@@ -290,14 +827,589 @@ impl Renderer {
                 occlusion_query_set: None,
                 multiview_mask: None,
             });
+            visualizer.render(&mut pass);
+        }
+    }
 
-            pass.set_pipeline(&self.pipeline);
-            pass.set_bind_group(0, &self.bind_group, &[]);
-            // 6 vertices per quad, one instance per bar
-            pass.draw(0..6, 0..self.num_bars);
+    /// The active visualizer, or the layers drawn by [`Self::draw_layers`].
+    fn draw_visualizers(&self, pass: &mut wgpu::RenderPass) {
+        if self.compositor.layers().is_empty() {
+            self.visualizers.active().render(pass);
+        } else {
+            self.compositor.composite(pass);
         }
+    }
 
-        self.queue.submit(std::iter::once(encoder.finish()));
-        output.present();
+    fn draw_background(&self, pass: &mut wgpu::RenderPass) {
+        pass.set_pipeline(&self.background_pipeline);
+        pass.set_bind_group(0, &self.background_bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+
+    /// Queue the enabled overlay elements for [`TextRenderer::draw`]. The
+    /// frequency labels and dB grid only make sense over full-screen bars;
+    /// over a full-screen chromagram the labels are note names.
+    fn queue_overlay(&mut self) {
+        let (width, _) = self.target.size();
+        // Status lines first; bar labels are skipped where they would
+        // cover anything already placed
+        let mut placed = Vec::new();
+
+        if let Some((notice, shown_at)) = &self.notice {
+            let age = self.time - shown_at;
+            if (0.0..NOTICE_SECONDS).contains(&age) {
+                let scale = TEXT_SCALE * 1.5;
+                let x = (width as f32 - text::text_width(notice, scale)) / 2.0;
+                let y = TEXT_MARGIN * 2.0 + text::text_height(TEXT_SCALE);
+                let alpha = (NOTICE_SECONDS - age).min(1.0);
+                let color = [1.0, 1.0, 1.0, alpha];
+                self.text.text(notice, [x, y], scale, color);
+            } else {
+                self.notice = None;
+            }
+        }
+
+        let mut corner = Vec2::splat(TEXT_MARGIN);
+        if self.overlay.source && !self.source_info.is_empty() {
+            let size = label_size(&self.source_info);
+            place(&mut placed, corner, size);
+            self.text
+                .text(&self.source_info, corner.into(), TEXT_SCALE, TEXT_COLOR);
+            corner.y += size.y + TEXT_MARGIN;
+        }
+        if self.overlay.tempo {
+            let (label, pulse) = match self.tempo {
+                Some(tempo) => (format!("{:.0} BPM", tempo.bpm), tempo.pulse()),
+                None => ("-- BPM".to_string(), 0.0),
+            };
+            let size = label_size(&label);
+            place(&mut placed, corner, size);
+            let color = Vec4::from(TEXT_COLOR).lerp(Vec4::ONE, pulse);
+            self.text
+                .text(&label, corner.into(), TEXT_SCALE, color.into());
+            corner.y += size.y + TEXT_MARGIN;
+        }
+        if self.overlay.harmony {
+            let harmony = self.harmony.as_ref();
+            let key = harmony
+                .and_then(|h| h.key)
+                .map_or("--".to_string(), |k| format!("{k} ({})", k.camelot()));
+            let chord = harmony
+                .and_then(|h| h.chord)
+                .map_or("--".to_string(), |c| c.to_string());
+            let label = format!("Key {key}  Chord {chord}");
+            let size = label_size(&label);
+            place(&mut placed, corner, size);
+            self.text
+                .text(&label, corner.into(), TEXT_SCALE, TEXT_COLOR);
+            corner.y += size.y + TEXT_MARGIN;
+        }
+        if self.overlay.tuner {
+            self.queue_tuner(&mut placed, corner);
+        }
+        if self.overlay.timing {
+            let timing = self.frame_stats.text();
+            let size = label_size(&timing);
+            let corner = Vec2::new(width as f32 - size.x - TEXT_MARGIN, TEXT_MARGIN);
+            place(&mut placed, corner, size);
+            self.text
+                .text(&timing, corner.into(), TEXT_SCALE, TEXT_COLOR);
+        }
+        if self.overlay.meters {
+            self.queue_meters(&mut placed);
+        }
+
+        if self.visualizers.active().name() == "chromagram"
+            && self.compositor.layers().is_empty()
+            && self.overlay.frequencies
+        {
+            self.queue_pitch_class_labels(&mut placed);
+        }
+        if self.visualizers.is_active::<Bars>() && self.compositor.layers().is_empty() {
+            if self.overlay.grid {
+                self.queue_db_grid(&mut placed);
+            }
+            if self.overlay.frequencies {
+                self.queue_frequency_labels(&mut placed);
+            }
+        }
+    }
+
+    /// Name each band of the chromagram at the left edge.
+    fn queue_pitch_class_labels(&mut self, placed: &mut Vec<[f32; 4]>) {
+        let (_, height) = self.target.size();
+        let band = height as f32 / NOTE_NAMES.len() as f32;
+        for (i, name) in NOTE_NAMES.iter().enumerate() {
+            let size = label_size(name);
+            // C at the bottom
+            let centre = height as f32 - (i as f32 + 0.5) * band;
+            let corner = Vec2::new(TEXT_MARGIN, centre - size.y / 2.0);
+            if place(placed, corner, size) {
+                self.text.text(name, corner.into(), TEXT_SCALE, TEXT_COLOR);
+            }
+        }
+    }
+
+    /// Note name, cents and frequency, over a scale of -50 to +50 cents
+    /// with a needle that turns green when the note is in tune.
+    fn queue_tuner(&mut self, placed: &mut Vec<[f32; 4]>, corner: Vec2) {
+        let pitch = self.pitch.filter(|p| p.confidence >= pitch::MIN_CONFIDENCE);
+        let label = match pitch {
+            Some(pitch) => {
+                let note = pitch.note();
+                format!(
+                    "{note}  {:+.0} cents  {:.1} Hz",
+                    note.cents, pitch.frequency
+                )
+            }
+            None => "--".to_string(),
+        };
+        let text_size = label_size(&label);
+        let scale_y = corner.y + text_size.y + TEXT_MARGIN / 2.0;
+        let size = Vec2::new(text_size.x.max(TUNER_WIDTH), scale_y + 8.0 - corner.y);
+        place(placed, corner, size);
+        self.text
+            .text(&label, corner.into(), TEXT_SCALE, TEXT_COLOR);
+
+        // Track with ticks at -50, 0 and +50 cents
+        self.text
+            .rect([corner.x, scale_y + 3.0], [TUNER_WIDTH, 2.0], GRID_COLOR);
+        for tick in [0.0, 0.5, 1.0] {
+            let x = corner.x + tick * (TUNER_WIDTH - 2.0);
+            self.text.rect([x, scale_y], [2.0, 8.0], GRID_COLOR);
+        }
+        if let Some(pitch) = pitch {
+            let cents = pitch.note().cents;
+            let x = corner.x + (cents / 100.0 + 0.5) * (TUNER_WIDTH - 4.0);
+            let color = if cents.abs() <= IN_TUNE_CENTS {
+                IN_TUNE_COLOR
+            } else {
+                TEXT_COLOR
+            };
+            self.text.rect([x, scale_y - 2.0], [4.0, 12.0], color);
+        }
+    }
+
+    /// Loudness meters at the right edge, under the frame timing: a bar per
+    /// reading with its value above and name below, and the loudness range
+    /// and crest factor underneath.
+    fn queue_meters(&mut self, placed: &mut Vec<[f32; 4]>) {
+        let (width, _) = self.target.size();
+        let loudness = self.loudness;
+        let meters = [
+            ("M", loudness.map(|l| l.momentary)),
+            ("S", loudness.map(|l| l.short_term)),
+            ("I", loudness.map(|l| l.integrated)),
+            ("TP", loudness.map(|l| l.true_peak)),
+            ("RMS", loudness.map(|l| l.rms)),
+        ];
+        let summary = match loudness {
+            Some(l) => format!(
+                "LRA {:.1} LU  Crest {}",
+                l.range,
+                l.crest_factor()
+                    .map_or("--".to_string(), |c| format!("{c:.1} dB"))
+            ),
+            None => "LRA --  Crest --".to_string(),
+        };
+
+        let line = text::text_height(TEXT_SCALE);
+        // Columns fit the widest reading, with a gap between them
+        let reading = label_size("-00.0").x;
+        let column = reading + TEXT_MARGIN * 2.0;
+        let size = Vec2::new(
+            (column * meters.len() as f32 - TEXT_MARGIN * 2.0).max(label_size(&summary).x),
+            line * 3.0 + METER_HEIGHT + TEXT_MARGIN * 2.5,
+        );
+        let corner = Vec2::new(
+            width as f32 - size.x - TEXT_MARGIN,
+            TEXT_MARGIN * 2.0 + line,
+        );
+        place(placed, corner, size);
+
+        let top = corner.y + line + TEXT_MARGIN / 2.0;
+        let height_of =
+            |db: f32| ((db - METER_FLOOR_DB) / -METER_FLOOR_DB).clamp(0.0, 1.0) * METER_HEIGHT;
+        for (i, (name, level)) in meters.into_iter().enumerate() {
+            let centre = corner.x + i as f32 * column + reading / 2.0;
+            let level = level.filter(|l| l.is_finite());
+            let value = level.map_or("--".to_string(), |l| format!("{l:.1}"));
+            let value_x = centre - label_size(&value).x / 2.0;
+            self.text
+                .text(&value, [value_x, corner.y], TEXT_SCALE, TEXT_COLOR);
+            let name_x = centre - label_size(name).x / 2.0;
+            let name_y = top + METER_HEIGHT + TEXT_MARGIN / 2.0;
+            self.text
+                .text(name, [name_x, name_y], TEXT_SCALE, TEXT_COLOR);
+
+            let bar_x = centre - METER_WIDTH / 2.0;
+            self.text
+                .rect([bar_x, top], [METER_WIDTH, METER_HEIGHT], GRID_COLOR);
+            let fill = level.map_or(0.0, height_of);
+            let color = if name == "TP" && level.is_some_and(|l| l > TRUE_PEAK_CEILING) {
+                OVER_COLOR
+            } else {
+                TEXT_COLOR
+            };
+            self.text.rect(
+                [bar_x, top + METER_HEIGHT - fill],
+                [METER_WIDTH, fill],
+                color,
+            );
+            // The target on the LUFS meters
+            if i < 3 {
+                let y = top + METER_HEIGHT - height_of(LOUDNESS_TARGET);
+                self.text.rect(
+                    [bar_x - 3.0, y - 1.0],
+                    [METER_WIDTH + 6.0, 2.0],
+                    IN_TUNE_COLOR,
+                );
+            }
+        }
+        let summary_y = top + METER_HEIGHT + line + TEXT_MARGIN * 1.5;
+        self.text
+            .text(&summary, [corner.x, summary_y], TEXT_SCALE, TEXT_COLOR);
+    }
+
+    /// Label the bars closest to a few round frequencies with their centre
+    /// frequency, just past the base of each bar.
+    fn queue_frequency_labels(&mut self, placed: &mut Vec<[f32; 4]>) {
+        let Some(bars) = self.visualizers.get::<Bars>() else {
+            return;
+        };
+        let geometry = bars.geometry();
+        let mut last_bar = None;
+
+        for target in LABEL_FREQUENCIES {
+            let Some(bar) = closest_bar(&self.bar_frequencies, target) else {
+                continue;
+            };
+            if last_bar == Some(bar) {
+                continue;
+            }
+            last_bar = Some(bar);
+
+            let label = format_frequency(self.bar_frequencies[bar]);
+            let size = label_size(&label);
+            let transform = geometry.transforms[bar];
+            let base = self.to_pixels(transform.transform_point3(Vec3::ZERO));
+            // Bars grow along +Y, so "under" is -Y (flipped for pixels)
+            let down = transform.transform_vector3(Vec3::NEG_Y);
+            let down = Vec2::new(down.x, -down.y).normalize_or_zero();
+
+            // Far enough along `down` that the label's box clears the base
+            let reach = TEXT_MARGIN + (down.abs() * size).element_sum() / 2.0;
+            let corner = self.clamp_to_screen(base + down * reach - size / 2.0, size);
+
+            if place(placed, corner, size) {
+                self.text
+                    .text(&label, corner.into(), TEXT_SCALE, TEXT_COLOR);
+            }
+        }
+    }
+
+    /// Dotted lines at fixed levels below a full-height bar, one dot per
+    /// bar so they follow any layout, labelled at the first bar.
+    fn queue_db_grid(&mut self, placed: &mut Vec<[f32; 4]>) {
+        const DOT: f32 = 2.0;
+        let Some(bars) = self.visualizers.get::<Bars>() else {
+            return;
+        };
+        let geometry = bars.geometry();
+        let sides: &[f32] = if geometry.mirrored {
+            &[1.0, -1.0]
+        } else {
+            &[1.0]
+        };
+
+        for db in GRID_LEVELS_DB {
+            let level = MAX_MAGNITUDE * 10f32.powf(db / 20.0) * geometry.height_scale;
+            for transform in &geometry.transforms {
+                for side in sides {
+                    let point = Vec3::new(0.0, level * side, 0.0);
+                    let dot = self.to_pixels(transform.transform_point3(point));
+                    self.text
+                        .rect((dot - DOT / 2.0).into(), [DOT; 2], GRID_COLOR);
+                }
+            }
+
+            let Some(first) = geometry.transforms.first() else {
+                continue;
+            };
+            let label = format!("{db:.0} dB");
+            let size = label_size(&label);
+            let point = self.to_pixels(first.transform_point3(Vec3::new(0.0, level, 0.0)));
+            let corner = self.clamp_to_screen(point + Vec2::new(TEXT_MARGIN, -size.y - DOT), size);
+            if place(placed, corner, size) {
+                self.text
+                    .text(&label, corner.into(), TEXT_SCALE, TEXT_COLOR);
+            }
+        }
+    }
+
+    /// Move a label box at `corner` of `size` fully on screen, at least a
+    /// margin from the edges.
+    fn clamp_to_screen(&self, corner: Vec2, size: Vec2) -> Vec2 {
+        let (width, height) = self.target.size();
+        let screen = Vec2::new(width as f32, height as f32);
+        // Not `clamp`, which panics when the label is wider than the screen
+        corner
+            .min(screen - size - TEXT_MARGIN)
+            .max(Vec2::splat(TEXT_MARGIN))
+    }
+
+    /// Layout units → pixels from the top left of the target.
+    fn to_pixels(&self, point: Vec3) -> Vec2 {
+        let (width, height) = self.target.size();
+        let extent = layout::view_extent(width, height);
+        Vec2::new(
+            (point.x / extent.x + 1.0) / 2.0 * width as f32,
+            (1.0 - point.y / extent.y) / 2.0 * height as f32,
+        )
     }
 }
+
+/// Size in pixels of `label` drawn as overlay text.
+fn label_size(label: &str) -> Vec2 {
+    Vec2::new(
+        text::text_width(label, TEXT_SCALE),
+        text::text_height(TEXT_SCALE),
+    )
+}
+
+/// Index of the bar whose centre frequency is closest to `target` in
+/// pitch, or `None` if `target` is outside the bars' range.
+fn closest_bar(frequencies: &[f32], target: f32) -> Option<usize> {
+    let lowest = frequencies.iter().copied().find(|&f| f > 0.0)?;
+    let highest = frequencies.last().copied()?;
+    if target < lowest / 1.5 || target > highest * 1.5 {
+        return None;
+    }
+    frequencies
+        .iter()
+        .enumerate()
+        .filter(|(_, &f)| f > 0.0)
+        .min_by(|(_, &a), (_, &b)| {
+            let distance = |f: f32| (f / target).ln().abs();
+            distance(a).total_cmp(&distance(b))
+        })
+        .map(|(i, _)| i)
+}
+
+/// Short label for a frequency: `"440"`, `"2.5k"`, `"12k"`.
+fn format_frequency(hz: f32) -> String {
+    let khz = hz / 1000.0;
+    if hz < 1000.0 {
+        format!("{hz:.0}")
+    } else if khz < 10.0 {
+        format!("{khz:.1}k")
+    } else {
+        format!("{khz:.0}k")
+    }
+}
+
+/// Record a label box at `corner` of `size` unless it comes within half a
+/// margin of one in `placed`. Returns whether it was placed.
+fn place(placed: &mut Vec<[f32; 4]>, corner: Vec2, size: Vec2) -> bool {
+    let [x0, y0] = (corner - TEXT_MARGIN / 2.0).into();
+    let [x1, y1] = (corner + size + TEXT_MARGIN / 2.0).into();
+    let overlaps = placed
+        .iter()
+        .any(|&[a0, b0, a1, b1]| x0 < a1 && a0 < x1 && y0 < b1 && b0 < y1);
+    if !overlaps {
+        placed.push([x0, y0, x1, y1]);
+    }
+    !overlaps
+}
+
+/// Start a render pass that clears and draws into `view`, through `msaa`
+/// (resolved into `view`) if given.
+fn begin_pass<'a>(
+    encoder: &'a mut wgpu::CommandEncoder,
+    label: &str,
+    view: &wgpu::TextureView,
+    msaa: Option<&wgpu::TextureView>,
+) -> wgpu::RenderPass<'a> {
+    let (attachment, resolve_target, store) = match msaa {
+        // Only the resolved image is kept
+        Some(msaa) => (msaa, Some(view), wgpu::StoreOp::Discard),
+        None => (view, None, wgpu::StoreOp::Store),
+    };
+    encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some(label),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: attachment,
+            resolve_target,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                store,
+            },
+            depth_slice: None,
+        })],
+        depth_stencil_attachment: None,
+        timestamp_writes: None,
+        occlusion_query_set: None,
+        multiview_mask: None,
+    })
+}
+
+/// Sample counts (ascending) usable for `format` render targets. Counts
+/// other than 1 and 4 need the adapter-specific format feature, which
+/// [`request_device`] enables when available.
+fn supported_sample_counts(
+    adapter: &wgpu::Adapter,
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+) -> Vec<u32> {
+    let flags = if device
+        .features()
+        .contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+    {
+        adapter.get_texture_format_features(format).flags
+    } else {
+        format.guaranteed_format_features(device.features()).flags
+    };
+    [1, 2, 4, 8]
+        .into_iter()
+        .filter(|&n| flags.sample_count_supported(n))
+        .collect()
+}
+
+/// Multisampled color target for the bar passes, or `None` for 1 sample.
+fn create_msaa_view(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+    sample_count: u32,
+) -> Option<wgpu::TextureView> {
+    if sample_count <= 1 {
+        return None;
+    }
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("MSAA"),
+        size: wgpu::Extent3d {
+            width: width.max(1),
+            height: height.max(1),
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+    Some(texture.create_view(&wgpu::TextureViewDescriptor::default()))
+}
+
+/// Build the full-screen background gradient pipeline and its bind group.
+fn create_background_pipeline(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    uniforms: &wgpu::Buffer,
+) -> (wgpu::RenderPipeline, wgpu::BindGroup) {
+    let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Background Bind Group Layout"),
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }],
+    });
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Background Bind Group"),
+        layout: &bind_group_layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: uniforms.as_entire_binding(),
+        }],
+    });
+
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Background Pipeline Layout"),
+        bind_group_layouts: &[&bind_group_layout],
+        immediate_size: 0,
+    });
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Background Shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("background.wgsl").into()),
+    });
+
+    let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Background Pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vs_main"),
+            buffers: &[],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some("fs_main"),
+            compilation_options: Default::default(),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            ..Default::default()
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            ..Default::default()
+        },
+        multiview_mask: None,
+        cache: None,
+    });
+
+    (pipeline, bind_group)
+}
+
+/// Run `f` (typically shader and pipeline creation) and return any wgpu
+/// validation error it caused, including naga's annotated source excerpt,
+/// instead of panicking.
+pub fn with_validation<T>(device: &wgpu::Device, f: impl FnOnce() -> T) -> Result<T, String> {
+    let scope = device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let value = f();
+    match pollster::block_on(scope.pop()) {
+        Some(error) => Err(error.to_string()),
+        None => Ok(value),
+    }
+}
+
+fn create_instance() -> wgpu::Instance {
+    wgpu::Instance::new(&wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::all(),
+        ..Default::default()
+    })
+}
+
+async fn request_device(adapter: &wgpu::Adapter) -> (wgpu::Device, wgpu::Queue) {
+    adapter
+        .request_device(&wgpu::DeviceDescriptor {
+            label: Some("Device"),
+            // Allows 2× and 8× MSAA where the adapter supports them
+            required_features: adapter.features()
+                & wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES,
+            required_limits: wgpu::Limits::default(),
+            memory_hints: wgpu::MemoryHints::default(),
+            trace: wgpu::Trace::Off,
+            experimental_features: wgpu::ExperimentalFeatures::disabled(),
+        })
+        .await
+        .expect("Failed to create device")
+}
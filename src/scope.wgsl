@@ -0,0 +1,166 @@
+// Stereo vectorscope / goniometer shader
+//
+// Three passes share this file:
+//   1. fade   — full-screen triangle that darkens the accumulation texture
+//   2. points — one small additive quad per [left, right] sample pair
+//   3. blit   — copies the accumulation texture to the screen and draws the
+//               phase-correlation meter along the bottom edge
+
+struct ScopeParams {
+    // 0 = L/R (x = left, y = right), 1 = M/S (rotated 45°, mono is vertical)
+    mode: u32,
+    num_points: u32,
+    // Fraction of the previous frame kept each frame (persistence)
+    decay: f32,
+    // Brightness added by each point
+    intensity: f32,
+    // Half-size of each point in clip-space units (x, y)
+    point_size: vec2<f32>,
+    // Phase correlation in -1..+1
+    correlation: f32,
+    _pad: f32,
+    // Shrinks the longer axis so the scope stays square
+    aspect_scale: vec2<f32>,
+    _pad2: vec2<f32>,
+};
+
+@group(0) @binding(0) var<uniform> params: ScopeParams;
+@group(0) @binding(1) var<storage, read> points: array<vec2<f32>>;
+
+@group(1) @binding(0) var accum_texture: texture_2d<f32>;
+@group(1) @binding(1) var accum_sampler: sampler;
+
+// Full-screen triangle covering clip space, with matching UVs
+fn fullscreen_position(vertex_index: u32) -> vec2<f32> {
+    let x = f32((vertex_index << 1u) & 2u);
+    let y = f32(vertex_index & 2u);
+    return vec2<f32>(x * 2.0 - 1.0, y * 2.0 - 1.0);
+}
+
+// --------------------------------------------------------------
+// Fade
+// --------------------------------------------------------------
+
+@vertex
+fn vs_fade(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<f32> {
+    return vec4<f32>(fullscreen_position(vertex_index), 0.0, 1.0);
+}
+
+@fragment
+fn fs_fade() -> @location(0) vec4<f32> {
+    // Alpha-blended black: dst = dst * decay
+    return vec4<f32>(0.0, 0.0, 0.0, 1.0 - params.decay);
+}
+
+// --------------------------------------------------------------
+// Points
+// --------------------------------------------------------------
+
+struct PointOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec3<f32>,
+};
+
+@vertex
+fn vs_points(
+    @builtin(vertex_index) vertex_index: u32,
+    @builtin(instance_index) instance_index: u32,
+) -> PointOutput {
+    let sample = points[instance_index];
+    let left = sample.x;
+    let right = sample.y;
+
+    var center: vec2<f32>;
+    if params.mode == 1u {
+        // Mid on the vertical axis, side on the horizontal axis
+        let inv_sqrt2 = 0.70710678;
+        center = vec2<f32>((right - left) * inv_sqrt2, (left + right) * inv_sqrt2);
+    } else {
+        center = vec2<f32>(left, right);
+    }
+    center = clamp(center, vec2<f32>(-1.0), vec2<f32>(1.0));
+
+    var corner: vec2<f32>;
+    switch vertex_index {
+        case 0u: { corner = vec2<f32>(-1.0, -1.0); }
+        case 1u: { corner = vec2<f32>(1.0, -1.0); }
+        case 2u: { corner = vec2<f32>(1.0, 1.0); }
+        case 3u: { corner = vec2<f32>(-1.0, -1.0); }
+        case 4u: { corner = vec2<f32>(1.0, 1.0); }
+        case 5u: { corner = vec2<f32>(-1.0, 1.0); }
+        default: { corner = vec2<f32>(0.0, 0.0); }
+    }
+
+    var output: PointOutput;
+    output.position = vec4<f32>(center * params.aspect_scale + corner * params.point_size, 0.0, 1.0);
+    // Phosphor green
+    output.color = vec3<f32>(0.35, 1.0, 0.45) * params.intensity;
+    return output;
+}
+
+@fragment
+fn fs_points(input: PointOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(input.color, 1.0);
+}
+
+// --------------------------------------------------------------
+// Blit
+// --------------------------------------------------------------
+
+struct BlitOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_blit(@builtin(vertex_index) vertex_index: u32) -> BlitOutput {
+    let pos = fullscreen_position(vertex_index);
+    var output: BlitOutput;
+    output.position = vec4<f32>(pos, 0.0, 1.0);
+    output.uv = vec2<f32>(pos.x * 0.5 + 0.5, 0.5 - pos.y * 0.5);
+    return output;
+}
+
+@fragment
+fn fs_blit(input: BlitOutput) -> @location(0) vec4<f32> {
+    let color = min(textureSample(accum_texture, accum_sampler, input.uv).rgb, vec3<f32>(1.0));
+    // Opaque only where it's lit, so the trace can sit on a layer above
+    // other views
+    return vec4<f32>(color, max(color.r, max(color.g, color.b)));
+}
+
+// --------------------------------------------------------------
+// Correlation meter
+// --------------------------------------------------------------
+
+@vertex
+fn vs_meter(@builtin(vertex_index) vertex_index: u32) -> PointOutput {
+    let c = clamp(params.correlation, -1.0, 1.0);
+    let bottom = -0.97;
+    let top = -0.93;
+    // Grows from the centre towards the correlation value
+    let x0 = min(0.0, c * 0.8);
+    let x1 = max(0.0, c * 0.8);
+
+    var pos: vec2<f32>;
+    switch vertex_index {
+        case 0u: { pos = vec2<f32>(x0, bottom); }
+        case 1u: { pos = vec2<f32>(x1, bottom); }
+        case 2u: { pos = vec2<f32>(x1, top); }
+        case 3u: { pos = vec2<f32>(x0, bottom); }
+        case 4u: { pos = vec2<f32>(x1, top); }
+        case 5u: { pos = vec2<f32>(x0, top); }
+        default: { pos = vec2<f32>(0.0, 0.0); }
+    }
+
+    var output: PointOutput;
+    output.position = vec4<f32>(pos, 0.0, 1.0);
+    // Green when in phase, red when out of phase
+    output.color = mix(vec3<f32>(1.0, 0.2, 0.2), vec3<f32>(0.2, 1.0, 0.3), c * 0.5 + 0.5);
+    return output;
+}
+
+@fragment
+fn fs_meter(input: PointOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(input.color, 1.0);
+}
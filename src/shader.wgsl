@@ -3,17 +3,35 @@
 
 struct VertexOutput {
     @builtin(position) position: vec4<f32>,
-    @location(0) color: vec3<f32>,
+    // 0 = low freq, 1 = high freq
+    @location(0) freq_t: f32,
+    // -1..1 from tip to tip of this bar (0..1 unless mirrored)
+    @location(1) along: f32,
+    // Same, but relative to the tallest possible bar
+    @location(2) level: f32,
 };
 
 struct Params {
+    // Layout units -> clip space (keeps aspect ratio on resize)
+    projection: mat4x4<f32>,
     num_bars: u32,
+    // Bar width in layout units (set by the active layout)
+    bar_width: f32,
+    // Layout units per unit of magnitude
+    height_scale: f32,
+    // 1 = bars extend equally on both sides of their base
+    mirrored: u32,
+    // Palette lookup: 0 = by frequency, 1 = by height, 2 = both
+    color_mode: u32,
 };
 
 // This array is of size num_bars
 @group(0) @binding(0) var<storage, read> magnitudes: array<f32>;
 @group(0) @binding(1) var<uniform> params: Params;
 @group(0) @binding(2) var<storage, read> transforms: array<mat4x4<f32>>;
+// Theme gradient, low -> high along x (one texel tall)
+@group(0) @binding(3) var palette: texture_2d<f32>;
+@group(0) @binding(4) var palette_sampler: sampler;
 
 @vertex
 fn vs_main(
@@ -21,71 +39,83 @@ fn vs_main(
     @builtin(instance_index) instance_index: u32,
 ) -> VertexOutput {
     let num_bars = f32(params.num_bars);
-    let bar_width = 2.0 / num_bars;
-    let gap = bar_width * 0.15; // The space between bars
-    let actual_width = bar_width - gap;
-
-    // Get magnitude for this bar (clamped to 0..2 range in clip space)
-    let height = clamp(magnitudes[instance_index], 0.0, 2.0);
-
-    // Bar X position (left edge, centered in its slot)
-    let x_base = -1.0 + f32(instance_index) * bar_width + gap * 0.5;
-
+    let half_width = params.bar_width * 0.5;
+
+    // Get magnitude for this bar (clamped to 0..2), scaled to layout units
+    let height = clamp(magnitudes[instance_index], 0.0, 2.0) * params.height_scale;
+    let max_height = 2.0 * params.height_scale;
+
+    // Mirrored bars are centred on their base instead of standing on it
+    var bottom = 0.0;
+    var top = height;
+    var reach = max_height;
+    if params.mirrored == 1u {
+        bottom = -height * 0.5;
+        top = height * 0.5;
+        reach = max_height * 0.5;
+    }
 
     // --------------------------------------------------------------
     // Quad vertices: 2 triangles forming a rectangle
     // --------------------------------------------------------------
 
-    // Local position of the vertex within each instance
+    // Local position of the vertex within each instance. The base of the
+    // bar is centred on the origin of its transform.
     var local_pos: vec2<f32>;
     switch vertex_index {
         // bottom-left
-        case 0u: { local_pos = vec2<f32>(0.0, 0.0); }
+        case 0u: { local_pos = vec2<f32>(-half_width, bottom); }
         // bottom-right
-        case 1u: { local_pos = vec2<f32>(actual_width, 0.0); }
-        // top-right  
-        case 2u: { local_pos = vec2<f32>(actual_width, height); }
+        case 1u: { local_pos = vec2<f32>(half_width, bottom); }
+        // top-right
+        case 2u: { local_pos = vec2<f32>(half_width, top); }
         // bottom-left
-        case 3u: { local_pos = vec2<f32>(0.0, 0.0); }
+        case 3u: { local_pos = vec2<f32>(-half_width, bottom); }
         // top-right
-        case 4u: { local_pos = vec2<f32>(actual_width, height); }
+        case 4u: { local_pos = vec2<f32>(half_width, top); }
         // top-left
-        case 5u: { local_pos = vec2<f32>(0.0, height); }
+        case 5u: { local_pos = vec2<f32>(-half_width, top); }
 
         default: { local_pos = vec2<f32>(0.0, 0.0); }
     }
 
-    // Apply per-instance transform to local quad position
+    // Apply per-instance transform to local quad position (layout units)
     let world_pos = transforms[instance_index] * vec4<f32>(local_pos, 0.0, 1.0);
 
     // --------------------------------------------------------------
-    // Color gradient based on frequency bin and bar height
+    // Palette coordinates, colored per fragment in fs_main
     // --------------------------------------------------------------
-    let freq_t = f32(instance_index) / num_bars;      // 0 = low freq, 1 = high freq
-    let height_t = local_pos.y / max(height, 0.001);  // 0 = bottom, 1 = top of bar
-
-    // Low frequencies: cyan, mid: green/yellow, high: magenta/pink
-    let r = smoothstep(0.3, 0.8, freq_t) + height_t * 0.2;
-    let g = 1.0 - abs(freq_t - 0.35) * 2.5;
-    let b = (1.0 - smoothstep(0.0, 0.5, freq_t)) + smoothstep(0.7, 1.0, freq_t) * 0.6;
-
-    // Brighten toward top of each bar
-    let brightness = 0.5 + 0.5 * height_t;
-
     var output: VertexOutput;
-    output.position = world_pos;
-    output.color = vec3<f32>(
-        clamp(r, 0.0, 1.0),
-        clamp(g, 0.0, 1.0),
-        clamp(b, 0.0, 1.0),
-    ) * brightness;
+    output.position = params.projection * world_pos;
+    output.freq_t = f32(instance_index) / num_bars;
+    // Signed so mirrored bars interpolate through 0 at their base
+    output.along = local_pos.y / max(top, 0.001);
+    output.level = local_pos.y / max(reach, 0.001);
 
     // Returns output of type VertexOutput, a struct containing the position and
-    // color of a single vertex. This is passed to the input of the fragment shader.
+    // palette coordinates of a single vertex. This is passed to the input of
+    // the fragment shader.
     return output;
 }
 
 @fragment
 fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
-    return vec4<f32>(input.color, 1.0);
+    var color: vec3<f32>;
+    switch params.color_mode {
+        // By frequency: one color per bar
+        case 0u: {
+            color = textureSample(palette, palette_sampler, vec2<f32>(input.freq_t, 0.5)).rgb;
+        }
+        // By height: the palette runs from the base up to full scale
+        case 1u: {
+            color = textureSample(palette, palette_sampler, vec2<f32>(abs(input.level), 0.5)).rgb;
+        }
+        // Both: color by frequency, brighten toward the top of each bar
+        default: {
+            let brightness = 0.5 + 0.5 * abs(input.along);
+            let base = textureSample(palette, palette_sampler, vec2<f32>(input.freq_t, 0.5)).rgb;
+            color = base * brightness;
+        }
+    }
+    return vec4<f32>(color, 1.0);
 }
@@ -0,0 +1,350 @@
+use std::path::PathBuf;
+
+use crate::renderer::with_validation;
+use crate::visualizer::{AnalysisFrame, SceneFormat, Visualizer};
+use crate::watch::FileWatcher;
+
+/// Width of the audio texture (both rows), as on Shadertoy.
+pub const AUDIO_TEXTURE_WIDTH: u32 = 512;
+
+/// Uniforms shared with `shadertoy_prelude.wgsl`.
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct ShaderToyUniforms {
+    resolution: [f32; 3],
+    time: f32,
+    time_delta: f32,
+    frame: u32,
+    centroid: f32,
+    flatness: f32,
+    bands: [f32; 3],
+    rms: f32,
+}
+
+/// Full-screen fragment shader mode fed by the spectrum and waveform, so
+/// audio-reactive shaders from Shadertoy can be ported with few changes.
+///
+/// The user shader provides `fn mainImage(fragCoord: vec2<f32>) -> vec4<f32>`;
+/// uniforms, the audio texture and the entry points come from
+/// `shadertoy_prelude.wgsl`.
+pub struct ShaderToy {
+    scene: SceneFormat,
+    size: (u32, u32),
+    pipeline: wgpu::RenderPipeline,
+    pipeline_layout: wgpu::PipelineLayout,
+    bind_group: wgpu::BindGroup,
+    uniforms_buffer: wgpu::Buffer,
+    audio_texture: wgpu::Texture,
+    /// Row 0 = spectrum, row 1 = waveform, 0..255.
+    audio_data: Vec<u8>,
+    watcher: Option<FileWatcher>,
+    error: Option<String>,
+    frame: u32,
+}
+
+impl ShaderToy {
+    pub fn new(device: &wgpu::Device, scene: SceneFormat) -> Self {
+        let uniforms_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("ShaderToy Uniforms"),
+            size: std::mem::size_of::<ShaderToyUniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // 8-bit like Shadertoy's audio channel, so ported shaders see the
+        // same value ranges
+        let audio_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("ShaderToy Audio"),
+            size: wgpu::Extent3d {
+                width: AUDIO_TEXTURE_WIDTH,
+                height: 2,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let audio_view = audio_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("ShaderToy Audio Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("ShaderToy Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("ShaderToy Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniforms_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&audio_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("ShaderToy Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            immediate_size: 0,
+        });
+
+        let pipeline = create_pipeline(
+            device,
+            &pipeline_layout,
+            scene,
+            include_str!("shadertoy_default.wgsl"),
+        );
+
+        let mut audio_data = vec![0u8; AUDIO_TEXTURE_WIDTH as usize * 2];
+        // Silent waveform sits in the middle
+        audio_data[AUDIO_TEXTURE_WIDTH as usize..].fill(128);
+
+        Self {
+            scene,
+            size: (1, 1),
+            pipeline,
+            pipeline_layout,
+            bind_group,
+            uniforms_buffer,
+            audio_texture,
+            audio_data,
+            watcher: None,
+            error: None,
+            frame: 0,
+        }
+    }
+
+    /// Use a user shader file (reloaded on change) instead of the built-in
+    /// one. `None` returns to the built-in shader.
+    pub fn set_shader(&mut self, device: &wgpu::Device, path: Option<PathBuf>) {
+        match path {
+            Some(path) => {
+                self.watcher = Some(FileWatcher::new(path));
+                self.load(device);
+            }
+            None => {
+                self.watcher = None;
+                self.error = None;
+                self.pipeline = create_pipeline(
+                    device,
+                    &self.pipeline_layout,
+                    self.scene,
+                    include_str!("shadertoy_default.wgsl"),
+                );
+            }
+        }
+    }
+
+    fn load(&mut self, device: &wgpu::Device) {
+        let Some(watcher) = &self.watcher else {
+            return;
+        };
+        let path = watcher.path().display().to_string();
+
+        let result = std::fs::read_to_string(watcher.path())
+            .map_err(|e| format!("Failed to read {path}: {e}"))
+            .and_then(|source| {
+                with_validation(device, || {
+                    create_pipeline(device, &self.pipeline_layout, self.scene, &source)
+                })
+            });
+
+        match result {
+            Ok(pipeline) => {
+                println!("Loaded Shadertoy shader {path}");
+                self.pipeline = pipeline;
+                self.error = None;
+            }
+            Err(e) => {
+                eprintln!("Shader error in {path}:\n{e}");
+                self.error = Some(e);
+            }
+        }
+    }
+}
+
+impl Visualizer for ShaderToy {
+    fn name(&self) -> &str {
+        "shadertoy"
+    }
+
+    fn set_scene_format(&mut self, device: &wgpu::Device, scene: SceneFormat) {
+        self.scene = scene;
+        // The built-in shader first, in case the user one no longer compiles
+        self.pipeline = create_pipeline(
+            device,
+            &self.pipeline_layout,
+            scene,
+            include_str!("shadertoy_default.wgsl"),
+        );
+        self.load(device);
+    }
+
+    fn resize(&mut self, _device: &wgpu::Device, _queue: &wgpu::Queue, width: u32, height: u32) {
+        self.size = (width, height);
+    }
+
+    /// Resample the bars (0..`max_height`) into the spectrum row and the
+    /// waveform (-1..1) into the waveform row, and upload them with this
+    /// frame's uniforms.
+    fn update(&mut self, _device: &wgpu::Device, queue: &wgpu::Queue, frame: &AnalysisFrame) {
+        let (spectrum, waveform) = self.audio_data.split_at_mut(AUDIO_TEXTURE_WIDTH as usize);
+        let max_height = frame.max_height.max(f32::EPSILON);
+        resample_into(spectrum, frame.bars, |m| m / max_height);
+        resample_into(waveform, frame.waveform, |s| s * 0.5 + 0.5);
+
+        let (width, height) = self.size;
+        let features = frame.features.unwrap_or_default();
+        let uniforms = ShaderToyUniforms {
+            resolution: [width as f32, height as f32, 1.0],
+            time: frame.time,
+            time_delta: frame.dt,
+            frame: self.frame,
+            centroid: features.centroid,
+            flatness: features.flatness,
+            bands: features.band_balance(),
+            rms: features.rms,
+        };
+        queue.write_buffer(&self.uniforms_buffer, 0, bytemuck::bytes_of(&uniforms));
+        queue.write_texture(
+            self.audio_texture.as_image_copy(),
+            &self.audio_data,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(AUDIO_TEXTURE_WIDTH),
+                rows_per_image: Some(2),
+            },
+            self.audio_texture.size(),
+        );
+        self.frame = self.frame.wrapping_add(1);
+    }
+
+    fn render(&self, pass: &mut wgpu::RenderPass) {
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+
+    /// Recompile the user shader if its file changed.
+    fn reload(&mut self, device: &wgpu::Device) {
+        if self.watcher.as_mut().is_some_and(|w| w.changed()) {
+            self.load(device);
+        }
+    }
+
+    /// The compile error of the user shader, if the last (re)load failed.
+    fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+/// Linearly resample `src` into `row`, mapping each value through `to_unit`
+/// (→ 0..1) and quantising to 0..255.
+fn resample_into(row: &mut [u8], src: &[f32], to_unit: impl Fn(f32) -> f32) {
+    if src.is_empty() {
+        return;
+    }
+    let last = (src.len() - 1) as f32;
+    let n = row.len();
+    for (i, texel) in row.iter_mut().enumerate() {
+        let pos = i as f32 / (n - 1).max(1) as f32 * last;
+        let i0 = pos.floor() as usize;
+        let i1 = (i0 + 1).min(src.len() - 1);
+        let t = pos - i0 as f32;
+        let value = src[i0] * (1.0 - t) + src[i1] * t;
+        *texel = (to_unit(value).clamp(0.0, 1.0) * 255.0).round() as u8;
+    }
+}
+
+/// Build the full-screen pipeline from a user `mainImage` source plus the
+/// prelude.
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    scene: SceneFormat,
+    user_source: &str,
+) -> wgpu::RenderPipeline {
+    // Prelude goes last so error line numbers match the user's file
+    let source = format!("{user_source}\n{}", include_str!("shadertoy_prelude.wgsl"));
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("ShaderToy Shader"),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("ShaderToy Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("shadertoy_vs"),
+            buffers: &[],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some("shadertoy_fs"),
+            compilation_options: Default::default(),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: scene.format,
+                blend: Some(wgpu::BlendState::REPLACE),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            ..Default::default()
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: scene.sample_count,
+            ..Default::default()
+        },
+        multiview_mask: None,
+        cache: None,
+    })
+}
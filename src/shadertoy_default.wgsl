@@ -0,0 +1,26 @@
+// Built-in shader for the Shadertoy mode: a spectrum-driven glow with the
+// waveform traced across the middle. Also a starting point for your own.
+
+fn mainImage(fragCoord: vec2<f32>) -> vec4<f32> {
+    let uv = fragCoord / uniforms.iResolution.xy;
+
+    // Spectrum as soft vertical columns
+    let level = spectrum(uv.x);
+    let column = 1.0 - smoothstep(level - 0.15, level, uv.y);
+    let hue = vec3<f32>(0.5 + 0.5 * cos(6.2831 * (uv.x + uniforms.iTime * 0.05) + vec3<f32>(0.0, 2.1, 4.2)));
+    // Warmer for dark sounds, cooler for bright ones
+    let brightness = smoothstep(8.0, 12.0, log2(max(uniforms.iCentroid, 1.0)));
+    let temperature = mix(vec3<f32>(1.0, 0.7, 0.45), vec3<f32>(0.6, 0.8, 1.0), brightness);
+    var color = hue * temperature * column * 0.6;
+
+    // Waveform as a bright line
+    let wave = waveform(uv.x);
+    let line = 1.0 - smoothstep(0.0, 0.01, abs(uv.y - wave));
+    color += vec3<f32>(1.0) * line;
+
+    // Bass pulses the background
+    let bass = spectrum(0.02);
+    color += vec3<f32>(0.05, 0.02, 0.1) * bass * (1.0 - uv.y);
+
+    return vec4<f32>(color, 1.0);
+}
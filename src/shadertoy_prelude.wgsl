@@ -0,0 +1,60 @@
+// Shadertoy-style prelude, appended after the user's shader so that error
+// line numbers match their file. The user provides:
+//
+//     fn mainImage(fragCoord: vec2<f32>) -> vec4<f32>
+//
+// and can use:
+//
+//     uniforms.iResolution   viewport size in pixels (z = 1)
+//     uniforms.iTime         seconds since start
+//     uniforms.iTimeDelta    seconds since the previous frame
+//     uniforms.iFrame        frame counter
+//     uniforms.iCentroid     spectral centroid in Hz: higher is brighter
+//     uniforms.iFlatness     0 for pure tones to 1 for noise
+//     uniforms.iBands        share of the energy in the bass, mid and
+//                            treble, summing to 1 (0 in silence)
+//     uniforms.iRms          root mean square of the latest samples
+//     iChannel0 / iChannel0Sampler
+//                            512x2 audio texture: row 0 (y = 0.25) is the
+//                            spectrum, row 1 (y = 0.75) the waveform
+//                            (0.5 = silence), like Shadertoy's audio input
+//     spectrum(x), waveform(x)
+//                            helpers sampling the two rows at x in 0..1
+
+struct ShaderToyUniforms {
+    iResolution: vec3<f32>,
+    iTime: f32,
+    iTimeDelta: f32,
+    iFrame: u32,
+    iCentroid: f32,
+    iFlatness: f32,
+    iBands: vec3<f32>,
+    iRms: f32,
+};
+
+@group(0) @binding(0) var<uniform> uniforms: ShaderToyUniforms;
+@group(0) @binding(1) var iChannel0: texture_2d<f32>;
+@group(0) @binding(2) var iChannel0Sampler: sampler;
+
+fn spectrum(x: f32) -> f32 {
+    return textureSampleLevel(iChannel0, iChannel0Sampler, vec2<f32>(x, 0.25), 0.0).r;
+}
+
+fn waveform(x: f32) -> f32 {
+    return textureSampleLevel(iChannel0, iChannel0Sampler, vec2<f32>(x, 0.75), 0.0).r;
+}
+
+@vertex
+fn shadertoy_vs(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<f32> {
+    // Full-screen triangle
+    let x = f32((vertex_index << 1u) & 2u);
+    let y = f32(vertex_index & 2u);
+    return vec4<f32>(x * 2.0 - 1.0, y * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn shadertoy_fs(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    // Shadertoy's origin is the bottom-left corner
+    let frag_coord = vec2<f32>(position.x, uniforms.iResolution.y - position.y);
+    return vec4<f32>(mainImage(frag_coord).rgb, 1.0);
+}
@@ -0,0 +1,58 @@
+/// Frame rate the decay factor is specified at. At other frame rates the
+/// per-frame factor is adjusted so bars fall at the same speed in seconds.
+const REFERENCE_FPS: f32 = 60.0;
+
+/// Turns raw bar magnitudes into display heights: scales by `gain`, clamps
+/// to `max_height`, jumps up instantly and decays exponentially.
+///
+/// Decay depends only on the elapsed time passed to [`Smoother::update`],
+/// so the same input at the same simulated clock always gives the same
+/// output, whatever the real frame rate.
+pub struct Smoother {
+    values: Vec<f32>,
+    /// Gain applied to raw FFT magnitudes before display.
+    pub gain: f32,
+    /// Fraction of the height kept per 1/60 s (0 = instant, 1 = frozen).
+    pub decay: f32,
+    /// Maximum bar height in magnitude units.
+    pub max_height: f32,
+}
+
+impl Smoother {
+    pub fn new(num_bars: usize, gain: f32, decay: f32, max_height: f32) -> Self {
+        Self {
+            values: vec![0.0; num_bars],
+            gain,
+            decay,
+            max_height,
+        }
+    }
+
+    /// Start over with `num_bars` bars, all at zero.
+    pub fn set_num_bars(&mut self, num_bars: usize) {
+        self.values = vec![0.0; num_bars];
+    }
+
+    /// Current smoothed heights.
+    pub fn values(&self) -> &[f32] {
+        &self.values
+    }
+
+    /// Feed one frame of raw magnitudes, `dt` seconds after the previous one.
+    pub fn update(&mut self, raw: &[f32], dt: f32) -> &[f32] {
+        let decay = self.decay.powf(dt.max(0.0) * REFERENCE_FPS);
+
+        for (value, &mag) in self.values.iter_mut().zip(raw) {
+            let scaled = (mag * self.gain).min(self.max_height);
+            if scaled > *value {
+                // Attack: jump up instantly
+                *value = scaled;
+            } else {
+                // Decay: fade down smoothly
+                *value *= decay;
+            }
+        }
+
+        &self.values
+    }
+}
@@ -0,0 +1,388 @@
+use crate::theme::{self, Theme};
+use crate::visualizer::{AnalysisFrame, SceneFormat, Visualizer};
+
+/// Columns of history on screen, oldest at the left edge.
+pub const HISTORY: u32 = 512;
+/// Columns added per second, so the scroll speed doesn't depend on the
+/// frame rate.
+pub const COLUMNS_PER_SECOND: f32 = 60.0;
+/// Levels this far below a full-height bar show as silence.
+const RANGE_DB: f32 = 36.0;
+
+/// Uniforms shared with `spectrogram.wgsl`.
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct SpectrogramParams {
+    head: u32,
+    columns: u32,
+    rows: u32,
+    /// 1 to blend neighbouring rows, 0 to draw them as bands.
+    blend_rows: u32,
+}
+
+/// What the rows of the history are.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Rows {
+    /// One per bar, by loudness in dB.
+    Bars,
+    /// One per pitch class from C up, by chroma.
+    Chroma,
+}
+
+/// Scrolling spectrogram: bar levels over the last few seconds, colored
+/// from the theme palette by loudness in dB.
+///
+/// Each column is one moment, kept in a ring buffer texture with a row per
+/// bar, so adding a column is a single small upload.
+///
+/// [`Spectrogram::chromagram`] makes the same view of the 12 pitch classes
+/// instead, from [`AnalysisFrame::harmony`].
+pub struct Spectrogram {
+    source: Rows,
+    /// Column written last.
+    head: u32,
+    /// Fraction of a column owed from previous frames.
+    pending: f32,
+    rows: u32,
+
+    params_buffer: wgpu::Buffer,
+    history: wgpu::Texture,
+    palette_texture: wgpu::Texture,
+    palette_view: wgpu::TextureView,
+    palette_sampler: wgpu::Sampler,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    pipeline_layout: wgpu::PipelineLayout,
+    shader: wgpu::ShaderModule,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl Spectrogram {
+    /// The history is sized to the bar count on the first update.
+    pub fn new(device: &wgpu::Device, scene: SceneFormat) -> Self {
+        Self::with_rows(device, scene, Rows::Bars)
+    }
+
+    /// Scrolling chromagram, C in the bottom band and B in the top one.
+    pub fn chromagram(device: &wgpu::Device, scene: SceneFormat) -> Self {
+        Self::with_rows(device, scene, Rows::Chroma)
+    }
+
+    fn with_rows(device: &wgpu::Device, scene: SceneFormat, source: Rows) -> Self {
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Spectrogram Params"),
+            size: std::mem::size_of::<SpectrogramParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let palette_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Spectrogram Palette"),
+            size: wgpu::Extent3d {
+                width: theme::PALETTE_SIZE,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let palette_view = palette_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let palette_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Spectrogram Palette Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Spectrogram Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                texture_entry(1),
+                texture_entry(2),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let rows = 1;
+        let history = create_history(device, rows);
+        let bind_group = create_bind_group(
+            device,
+            &bind_group_layout,
+            &params_buffer,
+            &history,
+            &palette_view,
+            &palette_sampler,
+        );
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Spectrogram Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            immediate_size: 0,
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Spectrogram Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("spectrogram.wgsl").into()),
+        });
+        let pipeline = create_pipeline(device, &pipeline_layout, &shader, scene);
+
+        Self {
+            source,
+            head: 0,
+            pending: 0.0,
+            rows,
+            params_buffer,
+            history,
+            palette_texture,
+            palette_view,
+            palette_sampler,
+            bind_group_layout,
+            bind_group,
+            pipeline_layout,
+            shader,
+            pipeline,
+        }
+    }
+
+    /// Start over with an empty history of `rows` rows.
+    fn reset(&mut self, device: &wgpu::Device, rows: u32) {
+        self.rows = rows;
+        self.head = 0;
+        self.pending = 0.0;
+        self.history = create_history(device, rows);
+        self.bind_group = create_bind_group(
+            device,
+            &self.bind_group_layout,
+            &self.params_buffer,
+            &self.history,
+            &self.palette_view,
+            &self.palette_sampler,
+        );
+    }
+}
+
+impl Visualizer for Spectrogram {
+    fn name(&self) -> &str {
+        match self.source {
+            Rows::Bars => "spectrogram",
+            Rows::Chroma => "chromagram",
+        }
+    }
+
+    fn set_scene_format(&mut self, device: &wgpu::Device, scene: SceneFormat) {
+        self.pipeline = create_pipeline(device, &self.pipeline_layout, &self.shader, scene);
+    }
+
+    fn set_theme(&mut self, queue: &wgpu::Queue, theme: &Theme) {
+        queue.write_texture(
+            self.palette_texture.as_image_copy(),
+            &theme.palette(),
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(theme::PALETTE_SIZE * 4),
+                rows_per_image: None,
+            },
+            self.palette_texture.size(),
+        );
+    }
+
+    /// Add a column of the current bar levels (or chroma) for each
+    /// 1/`COLUMNS_PER_SECOND` that passed, or one if there's no clock (a
+    /// single headless frame).
+    fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, frame: &AnalysisFrame) {
+        let rows = match self.source {
+            Rows::Bars => frame.bars.len().max(1) as u32,
+            Rows::Chroma => 12,
+        };
+        if rows != self.rows {
+            self.reset(device, rows);
+        }
+
+        let columns = if frame.dt > 0.0 {
+            self.pending += frame.dt * COLUMNS_PER_SECOND;
+            let whole = self.pending.floor();
+            self.pending -= whole;
+            (whole as u32).min(HISTORY)
+        } else {
+            1
+        };
+        if columns == 0 {
+            return;
+        }
+
+        let max_height = frame.max_height.max(f32::EPSILON);
+        let levels: Vec<f32> = match self.source {
+            Rows::Bars => frame
+                .bars
+                .iter()
+                .map(|m| 1.0 + 20.0 * (m / max_height).log10() / RANGE_DB)
+                .collect(),
+            Rows::Chroma => frame.harmony.map_or([0.0; 12], |h| h.chroma).to_vec(),
+        };
+        let column: Vec<u8> = levels
+            .iter()
+            .map(|level| (level.clamp(0.0, 1.0) * 255.0).round() as u8)
+            .collect();
+        for _ in 0..columns {
+            self.head = (self.head + 1) % HISTORY;
+            if column.is_empty() {
+                continue;
+            }
+            queue.write_texture(
+                wgpu::TexelCopyTextureInfo {
+                    texture: &self.history,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: self.head,
+                        y: 0,
+                        z: 0,
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                &column,
+                wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(1),
+                    rows_per_image: Some(rows),
+                },
+                wgpu::Extent3d {
+                    width: 1,
+                    height: rows,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+
+        let params = SpectrogramParams {
+            head: self.head,
+            columns: HISTORY,
+            rows,
+            blend_rows: (self.source == Rows::Bars) as u32,
+        };
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(&params));
+    }
+
+    fn render(&self, pass: &mut wgpu::RenderPass) {
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+/// Zeroed (silent) history texture with `rows` rows.
+fn create_history(device: &wgpu::Device, rows: u32) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Spectrogram History"),
+        size: wgpu::Extent3d {
+            width: HISTORY,
+            height: rows,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::R8Unorm,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    })
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    params: &wgpu::Buffer,
+    history: &wgpu::Texture,
+    palette_view: &wgpu::TextureView,
+    palette_sampler: &wgpu::Sampler,
+) -> wgpu::BindGroup {
+    let history_view = history.create_view(&wgpu::TextureViewDescriptor::default());
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Spectrogram Bind Group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(&history_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(palette_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::Sampler(palette_sampler),
+            },
+        ],
+    })
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    scene: SceneFormat,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Spectrogram Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some("vs_main"),
+            buffers: &[],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: Some("fs_main"),
+            compilation_options: Default::default(),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: scene.format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            ..Default::default()
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: scene.sample_count,
+            ..Default::default()
+        },
+        multiview_mask: None,
+        cache: None,
+    })
+}
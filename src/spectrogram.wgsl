@@ -0,0 +1,60 @@
+// Scrolling spectrogram: time runs left to right, frequency bottom to top
+
+struct Params {
+    // Column of the history texture written last
+    head: u32,
+    columns: u32,
+    // One row per bar (or pitch class), lowest frequency in row 0
+    rows: u32,
+    // 1 to blend neighbouring rows, 0 for flat bands
+    blend_rows: u32,
+};
+
+@group(0) @binding(0) var<uniform> params: Params;
+// Bar levels 0..1, a ring buffer of columns
+@group(0) @binding(1) var history: texture_2d<f32>;
+// Theme gradient, quiet -> loud along x (one texel tall)
+@group(0) @binding(2) var palette: texture_2d<f32>;
+@group(0) @binding(3) var palette_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    // 0..1 from the bottom left
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let x = f32((vertex_index << 1u) & 2u) * 2.0 - 1.0;
+    let y = f32(vertex_index & 2u) * 2.0 - 1.0;
+
+    var output: VertexOutput;
+    output.position = vec4<f32>(x, y, 0.0, 1.0);
+    output.uv = vec2<f32>(x, y) * 0.5 + 0.5;
+    return output;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    // Oldest column at the left edge, newest at the right
+    let age = min(u32(max(input.uv.x, 0.0) * f32(params.columns)), params.columns - 1u);
+    let column = (params.head + 1u + age) % params.columns;
+
+    // Blend neighbouring bars so rows don't show as blocks
+    let row = clamp(input.uv.y * f32(params.rows) - 0.5, 0.0, f32(params.rows - 1u));
+    let row0 = u32(floor(row));
+    let row1 = min(row0 + 1u, params.rows - 1u);
+    var level = mix(
+        textureLoad(history, vec2<u32>(column, row0), 0).r,
+        textureLoad(history, vec2<u32>(column, row1), 0).r,
+        fract(row),
+    );
+    if params.blend_rows == 0u {
+        let band = min(u32(max(input.uv.y, 0.0) * f32(params.rows)), params.rows - 1u);
+        level = textureLoad(history, vec2<u32>(column, band), 0).r;
+    }
+
+    // Quiet parts fade out to the background
+    let color = textureSample(palette, palette_sampler, vec2<f32>(level, 0.5)).rgb;
+    return vec4<f32>(color, clamp(level * 2.0, 0.0, 1.0));
+}
@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+
+use crate::cqt::CqtProcessor;
+use crate::fft::{BarScale, FftProcessor};
+
+/// Turns a frame of samples into a magnitude spectrum and bars: the part of
+/// [`crate::Analyzer`] that [`Transform`] picks.
+pub trait SpectrumAnalyzer: Send {
+    /// Number of samples per frame.
+    fn size(&self) -> usize;
+
+    fn num_bars(&self) -> usize;
+
+    fn scale(&self) -> BarScale;
+
+    fn sample_rate(&self) -> u32;
+
+    /// Process a frame of raw samples and return `num_bars` magnitudes, in
+    /// the same arbitrary units whatever the transform.
+    fn process(&mut self, samples: &[f32]) -> Vec<f32>;
+
+    /// Magnitude spectrum of the last frame: `size / 2` linear bins from
+    /// 0 Hz, each `sample_rate / size` wide, as from a Hann-windowed FFT.
+    fn spectrum(&self) -> &[f32];
+
+    /// Centre frequency in Hz of each bar, at [`SpectrumAnalyzer::sample_rate`].
+    fn bar_frequencies(&self) -> Vec<f32>;
+}
+
+/// How the spectrum behind the bars is computed.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "&'static str")]
+pub enum Transform {
+    /// One FFT over the whole frame: the same resolution in Hz at every
+    /// frequency, so the bass gets few bins and the treble reacts slowly.
+    #[default]
+    Fft,
+    /// Constant-Q: a fixed number of bins per octave, each looking only at
+    /// as many of the latest samples as it needs, so the bass is resolved
+    /// as finely as the frame allows and the treble still reacts quickly.
+    Cqt,
+}
+
+impl Transform {
+    pub const ALL: [Transform; 2] = [Transform::Fft, Transform::Cqt];
+
+    /// The transform after this one, wrapping around.
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|&t| t == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Transform::Fft => "fft",
+            Transform::Cqt => "cqt",
+        }
+    }
+
+    /// The transform called `name` (see [`Transform::name`]).
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.name() == name)
+    }
+
+    /// An analyzer of frames of `size` samples at `sample_rate`, grouped
+    /// into `num_bars` on `scale`. Only the constant-Q transform has
+    /// `bins_per_octave`.
+    pub fn analyzer(
+        self,
+        size: usize,
+        num_bars: usize,
+        bins_per_octave: usize,
+        scale: BarScale,
+        sample_rate: u32,
+    ) -> Box<dyn SpectrumAnalyzer> {
+        match self {
+            Transform::Fft => {
+                Box::new(FftProcessor::with_scale(size, num_bars, scale, sample_rate))
+            }
+            Transform::Cqt => Box::new(CqtProcessor::with_bins_per_octave(
+                size,
+                num_bars,
+                bins_per_octave,
+                scale,
+                sample_rate,
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for Transform {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Transform::from_name(&s).ok_or_else(|| {
+            let names: Vec<&str> = Transform::ALL.iter().map(|t| t.name()).collect();
+            format!(
+                "unknown transform {s:?}, expected one of {}",
+                names.join(", ")
+            )
+        })
+    }
+}
+
+impl From<Transform> for &'static str {
+    fn from(transform: Transform) -> Self {
+        transform.name()
+    }
+}
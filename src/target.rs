@@ -0,0 +1,278 @@
+use std::path::Path;
+use std::sync::Arc;
+use winit::window::Window;
+
+/// One frame acquired from a [`RenderTarget`], ready to be drawn into.
+pub struct Frame {
+    pub view: wgpu::TextureView,
+    /// Set for swapchain frames, which must be presented when done.
+    surface_texture: Option<wgpu::SurfaceTexture>,
+}
+
+/// Somewhere the renderer can draw frames: a window surface or an
+/// offscreen texture.
+pub trait RenderTarget {
+    fn format(&self) -> wgpu::TextureFormat;
+
+    /// Size in physical pixels.
+    fn size(&self) -> (u32, u32);
+
+    fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32);
+
+    /// Get the next frame to draw into, or `None` if no frame is available
+    /// right now (e.g. the surface was lost and has been reconfigured).
+    fn acquire(&mut self, device: &wgpu::Device) -> Option<Frame>;
+
+    /// Called after the frame's commands have been submitted.
+    fn present(&mut self, frame: Frame) {
+        if let Some(texture) = frame.surface_texture {
+            texture.present();
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Window surface
+// ---------------------------------------------------------------------------
+
+/// Draws to a window's swapchain.
+pub struct SurfaceTarget {
+    surface: wgpu::Surface<'static>,
+    config: wgpu::SurfaceConfiguration,
+}
+
+impl SurfaceTarget {
+    /// Create the surface for `window`. Must be configured with
+    /// [`SurfaceTarget::configure`] once an adapter has been chosen.
+    pub fn create_surface(
+        instance: &wgpu::Instance,
+        window: Arc<Window>,
+    ) -> wgpu::Surface<'static> {
+        // `Arc<Window>` → Surface<'static> because Arc is 'static
+        instance
+            .create_surface(window)
+            .expect("Failed to create surface")
+    }
+
+    /// Pick an sRGB format and configure `surface` for drawing.
+    pub fn configure(
+        surface: wgpu::Surface<'static>,
+        adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let caps = surface.get_capabilities(adapter);
+        let format = caps
+            .formats
+            .iter()
+            .find(|f| f.is_srgb())
+            .copied()
+            .unwrap_or(caps.formats[0]);
+
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format,
+            width: width.max(1),
+            height: height.max(1),
+            present_mode: wgpu::PresentMode::AutoVsync,
+            alpha_mode: caps.alpha_modes[0],
+            view_formats: vec![],
+            desired_maximum_frame_latency: 2,
+        };
+        surface.configure(device, &config);
+
+        Self { surface, config }
+    }
+}
+
+impl RenderTarget for SurfaceTarget {
+    fn format(&self) -> wgpu::TextureFormat {
+        self.config.format
+    }
+
+    fn size(&self) -> (u32, u32) {
+        (self.config.width, self.config.height)
+    }
+
+    fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        if width > 0 && height > 0 {
+            self.config.width = width;
+            self.config.height = height;
+            self.surface.configure(device, &self.config);
+        }
+    }
+
+    fn acquire(&mut self, device: &wgpu::Device) -> Option<Frame> {
+        let output = match self.surface.get_current_texture() {
+            Ok(tex) => tex,
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                self.surface.configure(device, &self.config);
+                return None;
+            }
+            Err(wgpu::SurfaceError::OutOfMemory) => panic!("GPU out of memory"),
+            Err(e) => {
+                eprintln!("Surface error: {e:?}");
+                return None;
+            }
+        };
+
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        Some(Frame {
+            view,
+            surface_texture: Some(output),
+        })
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Offscreen texture
+// ---------------------------------------------------------------------------
+
+/// Draws to an offscreen texture whose pixels can be read back to the CPU,
+/// e.g. for thumbnails, video export or golden-image tests.
+pub struct OffscreenTarget {
+    texture: wgpu::Texture,
+    width: u32,
+    height: u32,
+}
+
+impl OffscreenTarget {
+    /// sRGB, to match the window surface and what PNG viewers expect.
+    pub const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+    pub fn new(device: &wgpu::Device, width: u32, height: u32) -> Self {
+        let (width, height) = (width.max(1), height.max(1));
+        Self {
+            texture: create_offscreen_texture(device, width, height),
+            width,
+            height,
+        }
+    }
+
+    /// Copy the current contents of the texture back to the CPU as tightly
+    /// packed RGBA8 rows, top row first. Blocks until the GPU is done.
+    pub fn read_pixels(&self, device: &wgpu::Device, queue: &wgpu::Queue) -> Vec<u8> {
+        // Rows in the staging buffer must be 256-byte aligned
+        let unpadded_row = 4 * self.width;
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_row = unpadded_row.div_ceil(align) * align;
+
+        let staging = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Readback"),
+            size: (padded_row * self.height) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Readback Encoder"),
+        });
+        encoder.copy_texture_to_buffer(
+            self.texture.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &staging,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row),
+                    rows_per_image: Some(self.height),
+                },
+            },
+            self.texture.size(),
+        );
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = staging.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |result| {
+            result.expect("Failed to map readback buffer");
+        });
+        device
+            .poll(wgpu::PollType::wait_indefinitely())
+            .expect("Failed to wait for readback");
+
+        let mapped = slice.get_mapped_range();
+        let mut pixels = Vec::with_capacity((unpadded_row * self.height) as usize);
+        for row in mapped.chunks(padded_row as usize) {
+            pixels.extend_from_slice(&row[..unpadded_row as usize]);
+        }
+        drop(mapped);
+        staging.unmap();
+
+        pixels
+    }
+
+    /// Read the current frame back and write it to `path` as an RGBA PNG.
+    pub fn save_png(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: &Path,
+    ) -> Result<(), png::EncodingError> {
+        let pixels = self.read_pixels(device, queue);
+        write_png(path, self.width, self.height, &pixels)
+    }
+}
+
+impl RenderTarget for OffscreenTarget {
+    fn format(&self) -> wgpu::TextureFormat {
+        Self::FORMAT
+    }
+
+    fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        if width > 0 && height > 0 && (width, height) != (self.width, self.height) {
+            self.texture = create_offscreen_texture(device, width, height);
+            self.width = width;
+            self.height = height;
+        }
+    }
+
+    fn acquire(&mut self, _device: &wgpu::Device) -> Option<Frame> {
+        Some(Frame {
+            view: self
+                .texture
+                .create_view(&wgpu::TextureViewDescriptor::default()),
+            surface_texture: None,
+        })
+    }
+}
+
+fn create_offscreen_texture(device: &wgpu::Device, width: u32, height: u32) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Offscreen Target"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: OffscreenTarget::FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    })
+}
+
+/// Write tightly packed RGBA8 pixels to a PNG file.
+pub fn write_png(
+    path: &Path,
+    width: u32,
+    height: u32,
+    pixels: &[u8],
+) -> Result<(), png::EncodingError> {
+    let file = std::fs::File::create(path)?;
+    let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(pixels)?;
+    writer.finish()
+}
@@ -0,0 +1,72 @@
+// Overlay text and rectangles: one instanced quad per glyph, colored by
+// the glyph's bitmap in the font atlas.
+
+// Must match GLYPH_WIDTH / GLYPH_HEIGHT in text.rs
+const GLYPH_SIZE: vec2<u32> = vec2<u32>(5u, 7u);
+
+struct Screen {
+    // Target size in pixels
+    size: vec2<f32>,
+    _pad: vec2<f32>,
+};
+
+struct Quad {
+    // Top-left corner in pixels, y down
+    position: vec2<f32>,
+    size: vec2<f32>,
+    color: vec4<f32>,
+    glyph: u32,
+    // Scalars, not a vec3, which would be 16-byte aligned
+    _pad0: u32,
+    _pad1: u32,
+    _pad2: u32,
+};
+
+@group(0) @binding(0) var<uniform> screen: Screen;
+@group(0) @binding(1) var<storage, read> quads: array<Quad>;
+@group(0) @binding(2) var atlas: texture_2d<f32>;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    // Position inside the glyph in font pixels
+    @location(0) texel: vec2<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) @interpolate(flat) glyph: u32,
+};
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+    @builtin(instance_index) instance_index: u32,
+) -> VertexOutput {
+    // Two triangles covering the unit square
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(0.0, 1.0),
+        vec2<f32>(0.0, 1.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+    );
+    let corner = corners[vertex_index];
+    let quad = quads[instance_index];
+
+    let pixel = quad.position + corner * quad.size;
+    let clip = vec2<f32>(pixel.x / screen.size.x * 2.0 - 1.0, 1.0 - pixel.y / screen.size.y * 2.0);
+
+    var output: VertexOutput;
+    output.position = vec4<f32>(clip, 0.0, 1.0);
+    output.texel = corner * vec2<f32>(GLYPH_SIZE);
+    output.color = quad.color;
+    output.glyph = quad.glyph;
+    return output;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    // Nearest texel of this glyph's cell, so neighbours never bleed in
+    let local = min(vec2<u32>(input.texel), GLYPH_SIZE - vec2<u32>(1u));
+    let texel = vec2<u32>(input.glyph * GLYPH_SIZE.x + local.x, local.y);
+    let coverage = textureLoad(atlas, texel, 0).r;
+    return vec4<f32>(input.color.rgb, input.color.a * coverage);
+}
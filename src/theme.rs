@@ -0,0 +1,328 @@
+use std::path::Path;
+
+use serde::Deserialize;
+
+/// Number of texels in the palette texture sampled by the bar shader.
+pub const PALETTE_SIZE: u32 = 256;
+
+/// Which quantity picks a bar's color from the palette.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    /// Low frequencies take the start of the palette, high ones the end.
+    Frequency,
+    /// Base of each bar takes the start, full height the end.
+    Height,
+    /// Palette by frequency, brightened toward the tip of each bar.
+    Both,
+}
+
+impl ColorMode {
+    pub const ALL: [ColorMode; 3] = [ColorMode::Frequency, ColorMode::Height, ColorMode::Both];
+
+    /// The mode after this one, wrapping around.
+    pub fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|&m| m == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorMode::Frequency => "frequency",
+            ColorMode::Height => "height",
+            ColorMode::Both => "both",
+        }
+    }
+
+    /// Value of `Params::color_mode` in `shader.wgsl`.
+    pub fn index(self) -> u32 {
+        match self {
+            ColorMode::Frequency => 0,
+            ColorMode::Height => 1,
+            ColorMode::Both => 2,
+        }
+    }
+}
+
+/// An sRGB color, written `"#rrggbb"` in theme files.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Rgb(pub [u8; 3]);
+
+impl Rgb {
+    /// Parse `#rrggbb` (the `#` is optional).
+    pub fn parse(s: &str) -> Option<Self> {
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        Some(Rgb([channel(0)?, channel(2)?, channel(4)?]))
+    }
+
+    /// Linear RGBA for uniforms; the sRGB target re-encodes on write.
+    pub fn to_linear(self) -> [f32; 4] {
+        let [r, g, b] = self.0.map(|c| {
+            let c = c as f32 / 255.0;
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        });
+        [r, g, b, 1.0]
+    }
+}
+
+impl TryFrom<String> for Rgb {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Rgb::parse(&s).ok_or_else(|| format!("invalid color {s:?}, expected \"#rrggbb\""))
+    }
+}
+
+/// Bar palette and background. Custom themes are TOML files:
+///
+/// ```toml
+/// name = "Main stage"
+/// stops = ["#1b0036", "#ff2e88", "#ffd319"]   # evenly spaced, low → high
+/// color_by = "both"                            # frequency | height | both
+/// background = ["#000000", "#1b0036"]          # bottom → top, or one color
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    /// Gradient stops, evenly spaced from the start of the palette to the end.
+    pub stops: Vec<Rgb>,
+    pub color_mode: ColorMode,
+    /// Bottom and top of the background gradient (equal for a solid color).
+    pub background: [Rgb; 2],
+}
+
+/// On-disk form of [`Theme`]; only `stops` is required.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThemeFile {
+    name: Option<String>,
+    stops: Vec<Rgb>,
+    #[serde(default = "default_color_mode")]
+    color_by: ColorMode,
+    #[serde(default)]
+    background: Option<Background>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Background {
+    Solid(Rgb),
+    Gradient([Rgb; 2]),
+}
+
+fn default_color_mode() -> ColorMode {
+    ColorMode::Both
+}
+
+impl Theme {
+    fn new(name: &str, stops: &[&str], color_mode: ColorMode, background: [&str; 2]) -> Self {
+        let rgb = |s: &str| Rgb::parse(s).expect("invalid built-in color");
+        Self {
+            name: name.to_string(),
+            stops: stops.iter().map(|s| rgb(s)).collect(),
+            color_mode,
+            background: background.map(rgb),
+        }
+    }
+
+    /// Themes shipped with the visualizer; the first is the default.
+    pub fn builtin() -> Vec<Theme> {
+        vec![
+            // The original cyan → green → magenta look
+            Theme::new(
+                "neon",
+                &[
+                    "#5963ff", "#59cfd3", "#64ff80", "#b3cf00", "#f28900", "#ff006e", "#ff00cb",
+                ],
+                ColorMode::Both,
+                ["#000000", "#000000"],
+            ),
+            Theme::new(
+                "fire",
+                &["#3d0000", "#b31200", "#ff6a00", "#ffc300", "#fff6d5"],
+                ColorMode::Height,
+                ["#000000", "#1a0500"],
+            ),
+            Theme::new(
+                "ocean",
+                &["#00224d", "#005f99", "#00b3c7", "#7df2e6"],
+                ColorMode::Both,
+                ["#000814", "#002040"],
+            ),
+            Theme::new(
+                "sunset",
+                &["#2d1e5f", "#8a2c8a", "#e8465c", "#ff9b54", "#ffe08a"],
+                ColorMode::Frequency,
+                ["#0d0221", "#2d1e5f"],
+            ),
+            Theme::new(
+                "mono",
+                &["#404040", "#ffffff"],
+                ColorMode::Height,
+                ["#000000", "#000000"],
+            ),
+        ]
+    }
+
+    /// Look up a built-in theme by name.
+    pub fn named(name: &str) -> Option<Theme> {
+        Self::builtin().into_iter().find(|t| t.name == name)
+    }
+
+    /// Load a theme file; the file stem is the name unless one is given.
+    pub fn load(path: &Path) -> Result<Theme, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        let file: ThemeFile =
+            toml::from_str(&text).map_err(|e| format!("Invalid theme {}: {e}", path.display()))?;
+        if file.stops.is_empty() {
+            return Err(format!("Theme {} has no stops", path.display()));
+        }
+
+        let name = file.name.unwrap_or_else(|| {
+            path.file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        });
+        let background = match file.background {
+            None => [Rgb([0, 0, 0]); 2],
+            Some(Background::Solid(color)) => [color; 2],
+            Some(Background::Gradient(colors)) => colors,
+        };
+        Ok(Theme {
+            name,
+            stops: file.stops,
+            color_mode: file.color_by,
+            background,
+        })
+    }
+
+    /// The palette as `PALETTE_SIZE` sRGB RGBA8 texels, interpolating
+    /// between evenly spaced stops.
+    pub fn palette(&self) -> Vec<u8> {
+        let last = self.stops.len().saturating_sub(1);
+        (0..PALETTE_SIZE)
+            .flat_map(|i| {
+                let pos = i as f32 / (PALETTE_SIZE - 1) as f32 * last as f32;
+                let i0 = pos.floor() as usize;
+                let i1 = (i0 + 1).min(last);
+                let t = pos - i0 as f32;
+                let (a, b) = (self.stops[i0].0, self.stops[i1].0);
+                let mix = |c: usize| (a[c] as f32 * (1.0 - t) + b[c] as f32 * t).round() as u8;
+                [mix(0), mix(1), mix(2), 255]
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write `toml` to `<name>.toml` in a directory of this test run's own,
+    /// and load it.
+    fn load(name: &str, toml: &str) -> Result<Theme, String> {
+        let dir = std::env::temp_dir().join(format!("theme-tests-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!("{name}.toml"));
+        std::fs::write(&path, toml).unwrap();
+        let theme = Theme::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        theme
+    }
+
+    #[test]
+    fn colors_are_six_hex_digits() {
+        assert_eq!(Rgb::parse("#ff8000"), Some(Rgb([255, 128, 0])));
+        assert_eq!(Rgb::parse("FF8000"), Some(Rgb([255, 128, 0])));
+        for bad in ["#fff", "#ff80000", "#gg8000", "red", "#ffé00", ""] {
+            assert_eq!(Rgb::parse(bad), None, "{bad:?}");
+        }
+    }
+
+    #[test]
+    fn a_full_theme_file_loads() {
+        let theme = load(
+            "full",
+            r##"
+            name = "Main stage"
+            stops = ["#1b0036", "#ff2e88", "#ffd319"]
+            color_by = "height"
+            background = ["#000000", "#1b0036"]
+            "##,
+        )
+        .unwrap();
+        assert_eq!(theme.name, "Main stage");
+        assert_eq!(theme.stops.len(), 3);
+        assert_eq!(theme.stops[1], Rgb([0xff, 0x2e, 0x88]));
+        assert_eq!(theme.color_mode, ColorMode::Height);
+        assert_eq!(theme.background, [Rgb([0, 0, 0]), Rgb([0x1b, 0, 0x36])]);
+    }
+
+    #[test]
+    fn only_the_stops_are_required() {
+        let theme = load("stage-left", r##"stops = ["#ffffff"]"##).unwrap();
+        assert_eq!(theme.name, "stage-left");
+        assert_eq!(theme.color_mode, ColorMode::Both);
+        assert_eq!(theme.background, [Rgb([0, 0, 0]); 2]);
+
+        let solid = load("solid", "stops = [\"#ffffff\"]\nbackground = \"#102030\"").unwrap();
+        assert_eq!(solid.background, [Rgb([0x10, 0x20, 0x30]); 2]);
+    }
+
+    #[test]
+    fn bad_theme_files_are_rejected() {
+        for (toml, expected) in [
+            (r##"stops = ["#12345"]"##, "invalid color"),
+            (
+                "stops = [\"#ffffff\"]\nbackground = \"black\"",
+                "background",
+            ),
+            ("stops = [\"#ffffff\"]\ncolor_by = \"volume\"", "volume"),
+            (
+                "stops = [\"#ffffff\"]\nstop = [\"#000000\"]",
+                "unknown field",
+            ),
+            ("stops = []", "has no stops"),
+            ("name = \"empty\"", "stops"),
+        ] {
+            let e = load("bad", toml).unwrap_err();
+            assert!(e.contains(expected), "{toml}: {e}");
+        }
+    }
+
+    #[test]
+    fn the_palette_runs_from_the_first_stop_to_the_last() {
+        let theme = Theme::new(
+            "test",
+            &["#000000", "#ff0080"],
+            ColorMode::Both,
+            ["#000000"; 2],
+        );
+        let palette = theme.palette();
+        assert_eq!(palette.len(), PALETTE_SIZE as usize * 4);
+        assert_eq!(palette[..4], [0, 0, 0, 255]);
+        assert_eq!(palette[palette.len() - 4..], [255, 0, 128, 255]);
+        // Halfway between texels 127 and 128
+        let middle = &palette[127 * 4..129 * 4];
+        assert!(middle[0].abs_diff(128) <= 1 && middle[4].abs_diff(128) <= 1);
+    }
+
+    #[test]
+    fn builtin_themes_are_found_by_name() {
+        for theme in Theme::builtin() {
+            assert!(!theme.stops.is_empty());
+            assert_eq!(Theme::named(&theme.name), Some(theme));
+        }
+        assert_eq!(Theme::named("plaid"), None);
+    }
+}
@@ -0,0 +1,483 @@
+use wgpu::util::DeviceExt;
+
+use crate::visualizer::{AnalysisFrame, SceneFormat, Visualizer};
+
+/// Maximum number of sample pairs drawn per frame.
+pub const MAX_POINTS: usize = 4096;
+
+/// Format of the offscreen accumulation texture. Float so that many dim,
+/// additive points can build up without clipping or banding.
+const ACCUM_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// dst = dst + src
+const ADDITIVE_BLEND: wgpu::BlendState = wgpu::BlendState {
+    color: wgpu::BlendComponent {
+        src_factor: wgpu::BlendFactor::One,
+        dst_factor: wgpu::BlendFactor::One,
+        operation: wgpu::BlendOperation::Add,
+    },
+    alpha: wgpu::BlendComponent::OVER,
+};
+
+/// How sample pairs are mapped onto the screen.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ScopeMode {
+    /// Classic XY: x = left, y = right.
+    LeftRight,
+    /// Goniometer: rotated 45° so mono is a vertical line.
+    MidSide,
+}
+
+impl ScopeMode {
+    pub fn toggled(self) -> Self {
+        match self {
+            ScopeMode::LeftRight => ScopeMode::MidSide,
+            ScopeMode::MidSide => ScopeMode::LeftRight,
+        }
+    }
+}
+
+/// Phase correlation of a block of `[left, right]` pairs.
+///
+/// +1 = mono / fully in phase, 0 = uncorrelated (wide stereo),
+/// -1 = fully out of phase (cancels when summed to mono).
+/// Silence returns 0.
+pub fn phase_correlation(pairs: &[[f32; 2]]) -> f32 {
+    let mut lr = 0.0f64;
+    let mut ll = 0.0f64;
+    let mut rr = 0.0f64;
+    for &[l, r] in pairs {
+        let (l, r) = (l as f64, r as f64);
+        lr += l * r;
+        ll += l * l;
+        rr += r * r;
+    }
+
+    let denom = (ll * rr).sqrt();
+    if denom < 1e-12 {
+        return 0.0;
+    }
+    (lr / denom).clamp(-1.0, 1.0) as f32
+}
+
+/// Uniform parameters sent to `scope.wgsl`.
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct ScopeParams {
+    mode: u32,
+    num_points: u32,
+    decay: f32,
+    intensity: f32,
+    point_size: [f32; 2],
+    correlation: f32,
+    _pad: f32,
+    aspect_scale: [f32; 2],
+    _pad2: [f32; 2],
+}
+
+/// GPU state for the XY / goniometer view.
+///
+/// Points are drawn additively into an offscreen accumulation texture which
+/// is faded a little every frame, giving a phosphor-like persistence. The
+/// texture is then added onto the scene together with the correlation meter.
+pub struct Vectorscope {
+    pub mode: ScopeMode,
+    /// Fraction of the previous frame kept each frame (0 = no persistence).
+    pub decay: f32,
+    /// Brightness contributed by each point.
+    pub intensity: f32,
+    correlation: f32,
+    num_points: u32,
+    size: (u32, u32),
+
+    params_buffer: wgpu::Buffer,
+    points_buffer: wgpu::Buffer,
+    params_bind_group: wgpu::BindGroup,
+    texture_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    accum_view: wgpu::TextureView,
+    accum_bind_group: wgpu::BindGroup,
+
+    shader: wgpu::ShaderModule,
+    draw_layout: wgpu::PipelineLayout,
+    blit_layout: wgpu::PipelineLayout,
+    fade_pipeline: wgpu::RenderPipeline,
+    points_pipeline: wgpu::RenderPipeline,
+    blit_pipeline: wgpu::RenderPipeline,
+    meter_pipeline: wgpu::RenderPipeline,
+}
+
+impl Vectorscope {
+    /// The accumulation texture is sized on the first [`Visualizer::resize`].
+    pub fn new(device: &wgpu::Device, scene: SceneFormat) -> Self {
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Scope Params"),
+            size: std::mem::size_of::<ScopeParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let points_data = vec![[0.0f32; 2]; MAX_POINTS];
+        let points_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Scope Points"),
+            contents: bytemuck::cast_slice(&points_data),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // --- Bind groups ---
+        let params_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Scope Params Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let params_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Scope Params Bind Group"),
+            layout: &params_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: points_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let texture_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Scope Texture Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Scope Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let (accum_view, accum_bind_group) =
+            create_accum_texture(device, &texture_layout, &sampler, 1, 1);
+
+        // --- Pipelines ---
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Scope Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("scope.wgsl").into()),
+        });
+
+        let draw_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Scope Draw Layout"),
+            bind_group_layouts: &[&params_layout],
+            immediate_size: 0,
+        });
+        let blit_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Scope Blit Layout"),
+            bind_group_layouts: &[&params_layout, &texture_layout],
+            immediate_size: 0,
+        });
+
+        let accum = SceneFormat {
+            format: ACCUM_FORMAT,
+            sample_count: 1,
+        };
+        // dst = dst * (1 - src.a)
+        let fade_pipeline = create_pipeline(
+            device,
+            "Scope Fade Pipeline",
+            &draw_layout,
+            &shader,
+            ("vs_fade", "fs_fade"),
+            accum,
+            wgpu::BlendState::ALPHA_BLENDING,
+        );
+        let points_pipeline = create_pipeline(
+            device,
+            "Scope Points Pipeline",
+            &draw_layout,
+            &shader,
+            ("vs_points", "fs_points"),
+            accum,
+            ADDITIVE_BLEND,
+        );
+        let (blit_pipeline, meter_pipeline) =
+            create_scene_pipelines(device, &draw_layout, &blit_layout, &shader, scene);
+
+        Self {
+            mode: ScopeMode::MidSide,
+            decay: 0.85,
+            intensity: 0.08,
+            correlation: 0.0,
+            num_points: 0,
+            size: (1, 1),
+            params_buffer,
+            points_buffer,
+            params_bind_group,
+            texture_layout,
+            sampler,
+            accum_view,
+            accum_bind_group,
+            shader,
+            draw_layout,
+            blit_layout,
+            fade_pipeline,
+            points_pipeline,
+            blit_pipeline,
+            meter_pipeline,
+        }
+    }
+
+    /// The correlation of the last uploaded block, for numeric display.
+    pub fn correlation(&self) -> f32 {
+        self.correlation
+    }
+}
+
+impl Visualizer for Vectorscope {
+    fn name(&self) -> &str {
+        "vectorscope"
+    }
+
+    fn set_scene_format(&mut self, device: &wgpu::Device, scene: SceneFormat) {
+        (self.blit_pipeline, self.meter_pipeline) = create_scene_pipelines(
+            device,
+            &self.draw_layout,
+            &self.blit_layout,
+            &self.shader,
+            scene,
+        );
+    }
+
+    /// Recreate the accumulation texture to match the new target size.
+    /// The persisted trace is lost, which is fine on a resize.
+    fn resize(&mut self, device: &wgpu::Device, _queue: &wgpu::Queue, width: u32, height: u32) {
+        let (width, height) = (width.max(1), height.max(1));
+        if (width, height) == self.size {
+            return;
+        }
+        let (view, bind_group) =
+            create_accum_texture(device, &self.texture_layout, &self.sampler, width, height);
+        self.accum_view = view;
+        self.accum_bind_group = bind_group;
+        self.size = (width, height);
+    }
+
+    /// Upload the new `[left, right]` pairs. Only the most recent
+    /// `MAX_POINTS` are kept.
+    fn update(&mut self, _device: &wgpu::Device, queue: &wgpu::Queue, frame: &AnalysisFrame) {
+        let pairs = &frame.stereo[frame.stereo.len().saturating_sub(MAX_POINTS)..];
+        self.correlation = phase_correlation(pairs);
+        self.num_points = pairs.len() as u32;
+        if !pairs.is_empty() {
+            queue.write_buffer(&self.points_buffer, 0, bytemuck::cast_slice(pairs));
+        }
+    }
+
+    /// Fade the accumulation texture and add the new points.
+    fn prepare(&mut self, queue: &wgpu::Queue, encoder: &mut wgpu::CommandEncoder) {
+        // Points are ~1.5 px regardless of window size, and the plot is
+        // square in the middle of the window
+        let (width, height) = self.size;
+        let extent = crate::layout::view_extent(width, height);
+        let params = ScopeParams {
+            mode: match self.mode {
+                ScopeMode::LeftRight => 0,
+                ScopeMode::MidSide => 1,
+            },
+            num_points: self.num_points,
+            decay: self.decay,
+            intensity: self.intensity,
+            point_size: [1.5 / width as f32, 1.5 / height as f32],
+            correlation: self.correlation,
+            _pad: 0.0,
+            aspect_scale: [1.0 / extent.x, 1.0 / extent.y],
+            _pad2: [0.0; 2],
+        };
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(&params));
+
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Scope Accumulate Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.accum_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+            multiview_mask: None,
+        });
+
+        pass.set_bind_group(0, &self.params_bind_group, &[]);
+        pass.set_pipeline(&self.fade_pipeline);
+        pass.draw(0..3, 0..1);
+        pass.set_pipeline(&self.points_pipeline);
+        // 6 vertices per point quad, one instance per sample pair
+        pass.draw(0..6, 0..self.num_points);
+    }
+
+    /// Add the accumulated trace onto the scene, with the correlation
+    /// meter on top.
+    fn render(&self, pass: &mut wgpu::RenderPass) {
+        pass.set_bind_group(0, &self.params_bind_group, &[]);
+        pass.set_bind_group(1, &self.accum_bind_group, &[]);
+        pass.set_pipeline(&self.blit_pipeline);
+        pass.draw(0..3, 0..1);
+        pass.set_pipeline(&self.meter_pipeline);
+        pass.draw(0..6, 0..1);
+    }
+}
+
+fn create_accum_texture(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    sampler: &wgpu::Sampler,
+    width: u32,
+    height: u32,
+) -> (wgpu::TextureView, wgpu::BindGroup) {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Scope Accumulation"),
+        size: wgpu::Extent3d {
+            width: width.max(1),
+            height: height.max(1),
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: ACCUM_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Scope Texture Bind Group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+        ],
+    });
+
+    (view, bind_group)
+}
+
+/// The blit and meter pipelines, which draw into the scene.
+fn create_scene_pipelines(
+    device: &wgpu::Device,
+    draw_layout: &wgpu::PipelineLayout,
+    blit_layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    scene: SceneFormat,
+) -> (wgpu::RenderPipeline, wgpu::RenderPipeline) {
+    let blit = create_pipeline(
+        device,
+        "Scope Blit Pipeline",
+        blit_layout,
+        shader,
+        ("vs_blit", "fs_blit"),
+        scene,
+        ADDITIVE_BLEND,
+    );
+    let meter = create_pipeline(
+        device,
+        "Scope Meter Pipeline",
+        draw_layout,
+        shader,
+        ("vs_meter", "fs_meter"),
+        scene,
+        wgpu::BlendState::REPLACE,
+    );
+    (blit, meter)
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    label: &str,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    (vs_entry, fs_entry): (&str, &str),
+    target: SceneFormat,
+    blend: wgpu::BlendState,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some(vs_entry),
+            buffers: &[],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: Some(fs_entry),
+            compilation_options: Default::default(),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: target.format,
+                blend: Some(blend),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            ..Default::default()
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: target.sample_count,
+            ..Default::default()
+        },
+        multiview_mask: None,
+        cache: None,
+    })
+}
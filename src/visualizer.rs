@@ -0,0 +1,218 @@
+use std::any::Any;
+
+use crate::beat::Tempo;
+use crate::features::Features;
+use crate::harmony::Harmony;
+use crate::loudness::Loudness;
+use crate::pitch::Pitch;
+use crate::theme::Theme;
+
+/// Color target every visualizer draws into: the renderer's scene, which
+/// is HDR when bloom is available and multisampled when MSAA is on.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SceneFormat {
+    pub format: wgpu::TextureFormat,
+    pub sample_count: u32,
+}
+
+/// Everything known about the audio for one frame, handed to each
+/// visualizer's [`Visualizer::update`].
+#[derive(Copy, Clone, Debug)]
+#[non_exhaustive]
+pub struct AnalysisFrame<'a> {
+    /// Smoothed bar heights, 0 to `max_height`.
+    pub bars: &'a [f32],
+    /// Height of a full-scale bar.
+    pub max_height: f32,
+    /// Centre frequency of each bar in Hz; empty until the sample rate is
+    /// known.
+    pub bar_frequencies: &'a [f32],
+    /// Most recent mono samples, -1 to 1.
+    pub waveform: &'a [f32],
+    /// `[left, right]` sample pairs since the previous frame.
+    pub stereo: &'a [[f32; 2]],
+    /// Seconds since start, and since the previous frame.
+    pub time: f32,
+    pub dt: f32,
+    /// Tempo and beat phase, if the caller tracks beats.
+    pub tempo: Option<Tempo>,
+    /// Pitch of the latest samples, if the caller detects it.
+    pub pitch: Option<Pitch>,
+    /// Chroma, key and chord, if the caller tracks them.
+    pub harmony: Option<Harmony>,
+    /// Loudness, true peak and RMS, if the caller meters them.
+    pub loudness: Option<Loudness>,
+    /// Spectral centroid, flatness, band energies and the like, if the
+    /// caller extracts them.
+    pub features: Option<Features>,
+}
+
+/// One visual mode. The renderer draws the theme background, then the
+/// active visualizer, then trails, bloom and the overlay on top, so a
+/// visualizer only has to draw its own content.
+///
+/// Implementations create their GPU resources up front, for the
+/// renderer's [`SceneFormat`] (see [`crate::Renderer::scene_format`]), and
+/// are added with [`crate::Renderer::add_visualizer`]. Custom modes can
+/// live in other crates:
+///
+/// ```no_run
+/// use audio_visualizer::visualizer::{AnalysisFrame, SceneFormat, Visualizer};
+///
+/// /// Flashes the whole screen with the loudest bar.
+/// struct Flash {
+///     level: f32,
+/// }
+///
+/// impl Visualizer for Flash {
+///     fn name(&self) -> &str {
+///         "flash"
+///     }
+///     fn set_scene_format(&mut self, _: &wgpu::Device, _: SceneFormat) {}
+///     fn update(&mut self, _: &wgpu::Device, _: &wgpu::Queue, frame: &AnalysisFrame) {
+///         self.level = frame.bars.iter().copied().fold(0.0, f32::max) / frame.max_height;
+///     }
+///     fn render(&self, _pass: &mut wgpu::RenderPass) {
+///         // Draw a full-screen triangle tinted by `self.level`
+///     }
+/// }
+/// ```
+pub trait Visualizer: Any {
+    /// Short unique name, e.g. for selecting it from the config file.
+    fn name(&self) -> &str;
+
+    /// Recreate anything that depends on the scene format, e.g. render
+    /// pipelines. Called when MSAA is changed.
+    fn set_scene_format(&mut self, device: &wgpu::Device, scene: SceneFormat);
+
+    /// Called with the target size in pixels when the visualizer is added
+    /// and whenever the target is resized.
+    fn resize(&mut self, _device: &wgpu::Device, _queue: &wgpu::Queue, _width: u32, _height: u32) {}
+
+    /// Called with the current theme when the visualizer is added and
+    /// whenever the theme changes.
+    fn set_theme(&mut self, _queue: &wgpu::Queue, _theme: &Theme) {}
+
+    /// Take in the audio of a new frame. Called every frame on every
+    /// visualizer, active or not, so ones with history stay current.
+    fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, frame: &AnalysisFrame);
+
+    /// Draw into textures of the visualizer's own before the scene pass
+    /// begins. Called each frame the visualizer is shown: while it is the
+    /// active one or, with layers, while it is in a layer.
+    fn prepare(&mut self, _queue: &wgpu::Queue, _encoder: &mut wgpu::CommandEncoder) {}
+
+    /// Draw into the scene pass over the background, or into a layer's own
+    /// transparent target. Called each frame the visualizer is shown, like
+    /// [`Visualizer::prepare`].
+    fn render(&self, pass: &mut wgpu::RenderPass);
+
+    /// Pick up changed files, e.g. a watched user shader. Called every
+    /// frame.
+    fn reload(&mut self, _device: &wgpu::Device) {}
+
+    /// Why the last reload failed, if it did.
+    fn error(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// The visualizers a renderer can switch between, in cycling order, and
+/// which one is active.
+pub struct Registry {
+    visualizers: Vec<Box<dyn Visualizer>>,
+    active: usize,
+}
+
+impl Registry {
+    pub(crate) fn new() -> Self {
+        Self {
+            visualizers: Vec::new(),
+            active: 0,
+        }
+    }
+
+    /// Add `visualizer` after the others.
+    ///
+    /// # Panics
+    ///
+    /// If one with the same name was already added.
+    pub(crate) fn add(&mut self, visualizer: Box<dyn Visualizer>) {
+        let name = visualizer.name();
+        assert!(
+            self.position(name).is_none(),
+            "visualizer {name:?} added twice"
+        );
+        self.visualizers.push(visualizer);
+    }
+
+    /// Names in cycling order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.visualizers.iter().map(|v| v.name())
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.visualizers.iter().position(|v| v.name() == name)
+    }
+
+    pub fn active(&self) -> &dyn Visualizer {
+        self.visualizers[self.active].as_ref()
+    }
+
+    pub(crate) fn active_mut(&mut self) -> &mut dyn Visualizer {
+        self.visualizers[self.active].as_mut()
+    }
+
+    /// The visualizer called `name`.
+    pub fn named(&self, name: &str) -> Option<&dyn Visualizer> {
+        Some(self.visualizers[self.position(name)?].as_ref())
+    }
+
+    pub(crate) fn named_mut(&mut self, name: &str) -> Option<&mut dyn Visualizer> {
+        let i = self.position(name)?;
+        Some(self.visualizers[i].as_mut())
+    }
+
+    /// Make the visualizer called `name` active. Returns `false`, changing
+    /// nothing, if there is none.
+    pub fn set_active(&mut self, name: &str) -> bool {
+        match self.position(name) {
+            Some(i) => {
+                self.active = i;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Make the next visualizer active, wrapping around.
+    pub fn cycle(&mut self) {
+        self.active = (self.active + 1) % self.visualizers.len();
+    }
+
+    /// Whether the active visualizer is of type `V`.
+    pub fn is_active<V: Visualizer>(&self) -> bool {
+        (self.active() as &dyn Any).is::<V>()
+    }
+
+    /// The first visualizer of type `V`.
+    pub fn get<V: Visualizer>(&self) -> Option<&V> {
+        self.visualizers
+            .iter()
+            .find_map(|v| (v.as_ref() as &dyn Any).downcast_ref())
+    }
+
+    pub fn get_mut<V: Visualizer>(&mut self) -> Option<&mut V> {
+        self.visualizers
+            .iter_mut()
+            .find_map(|v| (v.as_mut() as &mut dyn Any).downcast_mut())
+    }
+
+    pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = &mut Box<dyn Visualizer>> {
+        self.visualizers.iter_mut()
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &Box<dyn Visualizer>> {
+        self.visualizers.iter()
+    }
+}
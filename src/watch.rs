@@ -0,0 +1,52 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often the file's modification time is checked.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Polls a file's modification time to detect edits.
+///
+/// Polling once every few frames is cheap and works the same on every
+/// platform and editor (including ones that save by replacing the file).
+pub struct FileWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    last_poll: Instant,
+}
+
+impl FileWatcher {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let modified = modified_time(&path);
+        Self {
+            path,
+            modified,
+            last_poll: Instant::now(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns `true` once after each change to the file. Checks the disk
+    /// at most every `POLL_INTERVAL`, so it is fine to call every frame.
+    pub fn changed(&mut self) -> bool {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return false;
+        }
+        self.last_poll = Instant::now();
+
+        let modified = modified_time(&self.path);
+        // A missing file (mid-save) is not a change; wait for it to return
+        if modified.is_none() || modified == self.modified {
+            return false;
+        }
+        self.modified = modified;
+        true
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
@@ -0,0 +1,243 @@
+use audio_visualizer::agc::AgcMode;
+use audio_visualizer::cqt::CqtProcessor;
+use audio_visualizer::fft::{BarScale, FftProcessor, PIANO_KEYS};
+use audio_visualizer::{Analyzer, SpectrumAnalyzer, Transform, MAX_HEIGHT};
+
+const SAMPLE_RATE: u32 = 48_000;
+
+fn sine(frequency: f32, len: usize) -> Vec<f32> {
+    (0..len)
+        .map(|i| (std::f32::consts::TAU * frequency * i as f32 / SAMPLE_RATE as f32).sin())
+        .collect()
+}
+
+fn loudest(values: &[f32]) -> usize {
+    (0..values.len())
+        .max_by(|&a, &b| values[a].total_cmp(&values[b]))
+        .unwrap()
+}
+
+#[test]
+fn tone_peaks_in_the_bar_at_its_frequency() {
+    let mut fft = FftProcessor::new(4096, 64);
+    let frequencies = fft.bar_frequencies();
+
+    for tone in [110.0, 1000.0, 5000.0] {
+        let bars = fft.process(&sine(tone, 4096));
+        let closest = (0..frequencies.len())
+            .min_by(|&a, &b| {
+                (frequencies[a] - tone)
+                    .abs()
+                    .total_cmp(&(frequencies[b] - tone).abs())
+            })
+            .unwrap();
+        assert!(
+            loudest(&bars).abs_diff(closest) <= 1,
+            "{tone} Hz peaked in bar {} at {} Hz",
+            loudest(&bars),
+            frequencies[loudest(&bars)]
+        );
+    }
+}
+
+#[test]
+fn bar_frequencies_rise_and_stay_below_nyquist() {
+    let frequencies = FftProcessor::new(2048, 88).bar_frequencies();
+    assert_eq!(frequencies.len(), 88);
+    assert!(frequencies.windows(2).all(|pair| pair[0] <= pair[1]));
+    assert!(*frequencies.last().unwrap() < SAMPLE_RATE as f32 / 2.0);
+}
+
+#[test]
+fn silence_gives_flat_bars() {
+    let mut analyzer = Analyzer::new(1024, 32, 6.0, 0.88);
+    assert!(analyzer
+        .process(&vec![0.0; 1024], 1.0 / 60.0)
+        .iter()
+        .all(|&v| v == 0.0));
+}
+
+#[test]
+fn bars_jump_up_then_decay_with_time() {
+    let mut analyzer = Analyzer::new(1024, 32, 50.0, 0.5);
+    let peak = analyzer.process(&sine(1000.0, 1024), 1.0 / 60.0).to_vec();
+    let bar = loudest(&peak);
+    assert!(peak[bar] > 0.0 && peak[bar] <= MAX_HEIGHT);
+
+    // Half the height is kept per 1/60 s, whatever the step size
+    let after_one = analyzer.process(&[], 1.0 / 60.0)[bar];
+    assert!((after_one - peak[bar] * 0.5).abs() < 1e-4);
+    let after_three = analyzer.process(&[], 2.0 / 60.0)[bar];
+    assert!((after_three - peak[bar] * 0.125).abs() < 1e-4);
+}
+
+#[test]
+fn resize_restarts_from_zero() {
+    let mut analyzer = Analyzer::default();
+    analyzer.process(&sine(440.0, analyzer.fft_size()), 0.0);
+    analyzer.resize(512, 16);
+    assert_eq!((analyzer.fft_size(), analyzer.num_bars()), (512, 16));
+    assert_eq!(analyzer.values(), &[0.0; 16]);
+}
+
+#[test]
+fn piano_scale_has_a_bar_per_key() {
+    let mut fft = FftProcessor::with_scale(8192, PIANO_KEYS, BarScale::Piano, SAMPLE_RATE);
+    let frequencies = fft.bar_frequencies();
+    assert!((frequencies[0] - 27.5).abs() < 0.01);
+    assert!((frequencies[48] - 440.0).abs() < 0.01);
+    assert!((frequencies[87] - 4186.0).abs() < 0.1);
+
+    // A4, C4 and C7 light up their own keys
+    for (tone, key) in [(440.0, 48), (261.63, 39), (2093.0, 75)] {
+        let bars = fft.process(&sine(tone, 8192));
+        assert_eq!(loudest(&bars), key, "{tone} Hz");
+    }
+}
+
+#[test]
+fn constant_q_bins_are_a_fixed_fraction_of_an_octave_apart() {
+    let mut cqt = CqtProcessor::new(8192, PIANO_KEYS, BarScale::Piano, SAMPLE_RATE);
+    let frequencies = cqt.bin_frequencies();
+    assert!((frequencies[0] - 27.5).abs() < 0.01);
+    assert!(*frequencies.last().unwrap() < SAMPLE_RATE as f32 / 2.0);
+    let step = 2f32.powf(1.0 / cqt.bins_per_octave() as f32);
+    assert!(frequencies
+        .windows(2)
+        .all(|pair| (pair[1] / pair[0] - step).abs() < 1e-4));
+
+    // A1 in the bass as well as A4 and C7 light up their own bins and keys
+    for (tone, key) in [(55.0, 12), (440.0, 48), (2093.0, 75)] {
+        let bars = cqt.process(&sine(tone, 8192));
+        assert_eq!(loudest(&bars), key, "{tone} Hz");
+        let bin = frequencies[loudest(cqt.bins())];
+        assert!(
+            (bin / tone).log2().abs() < 0.5 / 12.0,
+            "{tone} Hz in bin {bin}"
+        );
+    }
+}
+
+#[test]
+fn constant_q_treble_follows_the_latest_samples() {
+    let mut fft = FftProcessor::with_scale(8192, PIANO_KEYS, BarScale::Piano, SAMPLE_RATE);
+    let mut cqt = CqtProcessor::new(8192, PIANO_KEYS, BarScale::Piano, SAMPLE_RATE);
+    let steady = sine(2093.0, 8192);
+    // The tone only started 20 ms before the end of the frame
+    let mut onset = vec![0.0; 8192];
+    onset[8192 - 960..].copy_from_slice(&steady[..960]);
+
+    let key = 75;
+    let fft_share = fft.process(&onset)[key] / fft.process(&steady)[key];
+    let cqt_share = cqt.process(&onset)[key] / cqt.process(&steady)[key];
+    assert!(fft_share < 0.1, "FFT at {fft_share}");
+    assert!(cqt_share > 0.8, "constant-Q at {cqt_share}");
+}
+
+#[test]
+fn switching_transform_keeps_the_bars_and_spectrum() {
+    let mut analyzer = Analyzer::new(4096, 64, 6.0, 0.88);
+    analyzer.set_scale(BarScale::Piano, SAMPLE_RATE);
+    analyzer.set_transform(Transform::Cqt);
+    assert_eq!(analyzer.transform(), Transform::Cqt);
+    assert_eq!((analyzer.fft_size(), analyzer.num_bars()), (4096, 64));
+    assert_eq!(analyzer.scale(), BarScale::Piano);
+
+    // The linear spectrum is still there, e.g. for the chroma
+    analyzer.process(&sine(1000.0, 4096), 0.0);
+    let spectrum = analyzer.spectrum();
+    assert_eq!(spectrum.len(), 2048);
+    let bin_hz = SAMPLE_RATE as f32 / 4096.0;
+    assert!((loudest(spectrum) as f32 * bin_hz - 1000.0).abs() <= bin_hz);
+}
+
+/// Heights the bars are driven to after `seconds` of `samples` at 60
+/// frames per second: the raw values through the gain and AGC, before the
+/// smoothing lets them fall.
+fn settle(analyzer: &mut Analyzer, samples: &[f32], seconds: f32) -> Vec<f32> {
+    for _ in 0..(seconds * 60.0) as usize {
+        analyzer.process(samples, 1.0 / 60.0);
+    }
+    let factors = analyzer.agc().factors();
+    analyzer
+        .raw_values()
+        .iter()
+        .zip(factors)
+        .map(|(raw, factor)| (raw * analyzer.gain() * factor).min(MAX_HEIGHT))
+        .collect()
+}
+
+#[test]
+fn agc_brings_quiet_and_loud_tones_to_the_same_height() {
+    let tone = sine(1000.0, 1024);
+    let mut peaks = Vec::new();
+    // 20 dB apart, and the loud one too loud for the fixed gain
+    for amplitude in [0.1, 1.0] {
+        let samples: Vec<f32> = tone.iter().map(|s| s * amplitude).collect();
+        let mut fixed = Analyzer::new(1024, 32, 60.0, 0.88);
+        let mut agc = Analyzer::new(1024, 32, 60.0, 0.88);
+        agc.agc_mut().mode = AgcMode::Global;
+        let fixed_bars = settle(&mut fixed, &samples, 1.0);
+        let agc_bars = settle(&mut agc, &samples, 1.0);
+
+        // The raw values are the same either way, for metering
+        assert_eq!(agc.raw_values(), fixed.raw_values());
+        let peak = agc_bars.iter().copied().fold(0.0, f32::max);
+        assert!(peak < MAX_HEIGHT, "{amplitude}: pinned");
+        peaks.push(peak);
+        if amplitude == 1.0 {
+            assert!(
+                fixed_bars.contains(&MAX_HEIGHT),
+                "loud tone pins the fixed gain"
+            );
+        }
+    }
+    assert!((peaks[0] - peaks[1]).abs() < 1e-3 * MAX_HEIGHT, "{peaks:?}");
+}
+
+#[test]
+fn per_band_agc_evens_out_the_bands() {
+    let samples: Vec<f32> = sine(500.0, 1024)
+        .iter()
+        .zip(sine(8000.0, 1024))
+        .map(|(low, high)| 0.5 * low + 0.1 * high)
+        .collect();
+    let mut ratios = Vec::new();
+    for mode in [AgcMode::Global, AgcMode::PerBand] {
+        let mut analyzer = Analyzer::new(1024, 32, 30.0, 0.88);
+        analyzer.agc_mut().mode = mode;
+        analyzer.agc_mut().max_gain = 100.0;
+        let bars = settle(&mut analyzer, &samples, 1.0);
+        let half = bars.len() / 2;
+        let (low, high) = (loudest(&bars[..half]), loudest(&bars[half..]) + half);
+        ratios.push(bars[high] / bars[low]);
+    }
+    assert!(ratios[0] < 0.1, "global keeps the treble quiet: {ratios:?}");
+    assert!(
+        (ratios[1] - 1.0).abs() < 0.01,
+        "per-band evens it: {ratios:?}"
+    );
+}
+
+#[test]
+fn agc_stays_within_its_limits_and_holds_when_frozen() {
+    let mut analyzer = Analyzer::new(1024, 32, 6.0, 0.88);
+    analyzer.agc_mut().mode = AgcMode::Global;
+    let whisper: Vec<f32> = sine(1000.0, 1024).iter().map(|s| s * 1e-3).collect();
+    settle(&mut analyzer, &whisper, 1.0);
+    let max_gain = analyzer.agc().max_gain;
+    assert!(analyzer.agc().factors().iter().all(|&f| f == max_gain));
+
+    // Frozen, a loud passage keeps the whisper's boost
+    analyzer.agc_mut().frozen = true;
+    settle(&mut analyzer, &sine(1000.0, 1024), 1.0);
+    assert!(analyzer.agc().factors().iter().all(|&f| f == max_gain));
+    analyzer.agc_mut().frozen = false;
+    settle(&mut analyzer, &sine(1000.0, 1024), 1.0);
+    assert!(analyzer.agc().factors()[0] < max_gain);
+
+    // Silence doesn't raise the gain
+    let factors = analyzer.agc().factors().to_vec();
+    settle(&mut analyzer, &vec![0.0; 1024], 5.0);
+    assert_eq!(analyzer.agc().factors(), factors);
+}
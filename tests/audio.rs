@@ -0,0 +1,65 @@
+use std::path::PathBuf;
+
+use audio_visualizer::audio::decode_wav;
+
+/// Write a WAV file into the temp directory and return its path.
+fn write_wav(name: &str, spec: hound::WavSpec, samples: &[i16]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "audio-visualizer-{}-{name}.wav",
+        std::process::id()
+    ));
+    let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+    for &sample in samples {
+        writer.write_sample(sample).unwrap();
+    }
+    writer.finalize().unwrap();
+    path
+}
+
+#[test]
+fn decodes_16_bit_stereo() {
+    let spec = hound::WavSpec {
+        channels: 2,
+        sample_rate: 44_100,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let path = write_wav(
+        "stereo",
+        spec,
+        &[i16::MAX, 0, 0, i16::MAX / 2, -i16::MAX, -i16::MAX],
+    );
+    let audio = decode_wav(path.to_str().unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!((audio.channels, audio.sample_rate), (2, 44_100));
+    assert_eq!(audio.frames(), 3);
+    assert_eq!(audio.samples[0], 1.0);
+
+    let mono = audio.mono_range(0, 3);
+    assert!((mono[0] - 0.5).abs() < 1e-4);
+    assert!((mono[1] - 0.25).abs() < 1e-4);
+    assert!((mono[2] + 1.0).abs() < 1e-4);
+}
+
+#[test]
+fn ranges_outside_the_file_are_silent() {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: 8_000,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let path = write_wav("mono", spec, &[i16::MAX, i16::MAX]);
+    let audio = decode_wav(path.to_str().unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(audio.mono_range(-2, 4), [0.0, 0.0, 1.0, 1.0, 0.0, 0.0]);
+    // Mono is duplicated to both sides
+    assert_eq!(audio.stereo_range(1, 3), [[1.0, 1.0], [0.0, 0.0]]);
+}
+
+#[test]
+fn missing_file_is_an_error() {
+    assert!(decode_wav("/nonexistent/song.wav").is_err());
+}
@@ -0,0 +1,127 @@
+use audio_visualizer::beat::{BeatEvent, BeatTracker, OnsetMethod};
+
+/// `seconds` of single-sample clicks at `bpm`, starting at `offset` seconds.
+fn click_track(sample_rate: u32, bpm: f32, seconds: f32, offset: f32) -> (Vec<f32>, Vec<f32>) {
+    let len = (seconds * sample_rate as f32) as usize;
+    let mut samples = vec![0.0; len];
+    let mut times = Vec::new();
+    let mut time = offset;
+    while ((time * sample_rate as f32) as usize) < len {
+        samples[(time * sample_rate as f32) as usize] = 1.0;
+        times.push(time);
+        time += 60.0 / bpm;
+    }
+    (samples, times)
+}
+
+/// Feed `samples` in uneven chunks, like audio callbacks, and collect the
+/// onset and beat times.
+fn track(tracker: &mut BeatTracker, samples: &[f32]) -> (Vec<f32>, Vec<f32>) {
+    let (mut onsets, mut beats) = (Vec::new(), Vec::new());
+    let mut rest = samples;
+    for size in [441, 1000, 1600, 128].into_iter().cycle() {
+        if rest.is_empty() {
+            break;
+        }
+        let (chunk, tail) = rest.split_at(size.min(rest.len()));
+        rest = tail;
+        for event in tracker.process(chunk) {
+            match *event {
+                BeatEvent::Onset { time, .. } => onsets.push(time),
+                BeatEvent::Beat { time } => beats.push(time),
+            }
+        }
+    }
+    (onsets, beats)
+}
+
+fn nearest(times: &[f32], time: f32) -> f32 {
+    times
+        .iter()
+        .map(|t| (t - time).abs())
+        .fold(f32::INFINITY, f32::min)
+}
+
+#[test]
+fn every_click_is_an_onset() {
+    for method in [OnsetMethod::SpectralFlux, OnsetMethod::ComplexDomain] {
+        let (samples, clicks) = click_track(44_100, 100.0, 6.0, 0.25);
+        let mut tracker = BeatTracker::with_method(44_100, method);
+        let (onsets, _) = track(&mut tracker, &samples);
+
+        assert_eq!(onsets.len(), clicks.len(), "{method:?}: {onsets:?}");
+        for (onset, click) in onsets.iter().zip(&clicks) {
+            assert!(
+                (onset - click).abs() < 0.02,
+                "{method:?}: {onset} for {click}"
+            );
+        }
+    }
+}
+
+#[test]
+fn tempo_of_click_tracks() {
+    for (sample_rate, bpm) in [(48_000, 90.0), (44_100, 120.0), (48_000, 140.0)] {
+        let (samples, clicks) = click_track(sample_rate, bpm, 10.0, 0.1);
+        let mut tracker = BeatTracker::new(sample_rate);
+        let (_, beats) = track(&mut tracker, &samples);
+
+        let tempo = tracker.tempo().expect("a tempo");
+        assert!(
+            (tempo.bpm - bpm).abs() < 1.5,
+            "{bpm} BPM read as {}",
+            tempo.bpm
+        );
+        assert!(tempo.confidence > 0.5, "{bpm} BPM: {tempo:?}");
+
+        // Once the tempo is known, a beat on every click
+        let settled: Vec<f32> = beats.into_iter().filter(|&t| t > 4.0).collect();
+        let expected = clicks.iter().filter(|&&t| t > 4.0 && t < 9.9).count();
+        assert!(
+            settled.len().abs_diff(expected) <= 1,
+            "{bpm} BPM: {settled:?}"
+        );
+        for beat in settled {
+            assert!(nearest(&clicks, beat) < 0.03, "{bpm} BPM: beat at {beat}");
+        }
+    }
+}
+
+#[test]
+fn beats_continue_through_a_missing_click() {
+    let (mut samples, clicks) = click_track(48_000, 120.0, 10.0, 0.0);
+    let missing = clicks[14];
+    samples[(missing * 48_000.0) as usize] = 0.0;
+
+    let mut tracker = BeatTracker::new(48_000);
+    let (onsets, beats) = track(&mut tracker, &samples);
+    assert!(nearest(&onsets, missing) > 0.1);
+    assert!(nearest(&beats, missing) < 0.03, "{beats:?}");
+}
+
+#[test]
+fn silence_has_no_onsets_or_tempo() {
+    let mut tracker = BeatTracker::new(48_000);
+    let (onsets, beats) = track(&mut tracker, &vec![0.0; 48_000 * 5]);
+    assert!(onsets.is_empty() && beats.is_empty());
+    assert_eq!(tracker.tempo(), None);
+}
+
+#[test]
+fn noise_has_no_steady_beat() {
+    // xorshift, so the test is the same every run
+    let mut state = 0x2545_f491_u32;
+    let noise: Vec<f32> = (0..48_000 * 8)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as f32 / u32::MAX as f32 - 0.5
+        })
+        .collect();
+
+    let mut tracker = BeatTracker::new(48_000);
+    let (_, beats) = track(&mut tracker, &noise);
+    assert!(beats.is_empty(), "{beats:?}");
+    assert_eq!(tracker.tempo().map_or(0.0, |t| t.pulse()), 0.0);
+}
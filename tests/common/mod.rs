@@ -0,0 +1,26 @@
+//! Fixtures shared by the rendering tests.
+
+use audio_visualizer::target::OffscreenTarget;
+use audio_visualizer::{Layout, LayoutParams, Renderer};
+
+pub const SIZE: (u32, u32) = (160, 90);
+
+/// An offscreen renderer of `num_bars` bars in `layout`, or `None`
+/// (skipping the test) on machines with no adapter at all.
+pub fn renderer(num_bars: u32, layout: Layout) -> Option<Renderer<OffscreenTarget>> {
+    let new = |software| {
+        pollster::block_on(Renderer::new_headless(
+            SIZE.0,
+            SIZE.1,
+            num_bars,
+            layout,
+            LayoutParams::default(),
+            software,
+        ))
+    };
+    let renderer = new(true).or_else(|| new(false));
+    if renderer.is_none() {
+        eprintln!("No GPU adapter; skipping");
+    }
+    renderer
+}
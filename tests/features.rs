@@ -0,0 +1,138 @@
+use audio_visualizer::features::{FeatureExtractor, Features};
+use audio_visualizer::fft::FftProcessor;
+
+const SAMPLE_RATE: u32 = 48_000;
+const FFT_SIZE: usize = 4096;
+/// Width of an FFT bin in Hz; tones on a bin's centre leak symmetrically.
+const BIN_HZ: f32 = SAMPLE_RATE as f32 / FFT_SIZE as f32;
+
+/// A frame of sines at each `(frequency, amplitude)`, summed.
+fn tones(parts: &[(f32, f32)]) -> Vec<f32> {
+    (0..FFT_SIZE)
+        .map(|i| {
+            let t = i as f64 / SAMPLE_RATE as f64;
+            parts
+                .iter()
+                .map(|&(f, a)| a as f64 * (std::f64::consts::TAU * f as f64 * t).sin())
+                .sum::<f64>() as f32
+        })
+        .collect()
+}
+
+/// A frame of uniform white noise from a fixed seed.
+fn noise() -> Vec<f32> {
+    let mut state = 0x2545_f491_u32;
+    (0..FFT_SIZE)
+        .map(|_| {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (state >> 8) as f32 / (1 << 23) as f32 - 1.0
+        })
+        .collect()
+}
+
+/// Features of each frame in turn, from one extractor.
+fn features(frames: &[Vec<f32>]) -> Vec<Features> {
+    let mut fft = FftProcessor::new(FFT_SIZE, 64);
+    let mut extractor = FeatureExtractor::new(SAMPLE_RATE);
+    frames
+        .iter()
+        .map(|samples| {
+            fft.process(samples);
+            extractor.process(samples, fft.spectrum())
+        })
+        .collect()
+}
+
+fn single(samples: Vec<f32>) -> Features {
+    features(&[samples])[0]
+}
+
+#[test]
+fn a_sine_is_a_narrow_peak_at_its_frequency() {
+    let frequency = 85.0 * BIN_HZ;
+    let sine = single(tones(&[(frequency, 0.5)]));
+    assert!(
+        (sine.centroid - frequency).abs() < 0.01 * frequency,
+        "{sine:?}"
+    );
+    assert!(sine.spread < 2.0 * BIN_HZ, "{sine:?}");
+    assert!((sine.rolloff - frequency).abs() <= 2.0 * BIN_HZ, "{sine:?}");
+    assert!(sine.flatness < 0.01, "{sine:?}");
+}
+
+#[test]
+fn two_tones_centre_between_them_and_skew_towards_the_quiet_one() {
+    let (low, high) = (85.0 * BIN_HZ, 256.0 * BIN_HZ);
+    let even = single(tones(&[(low, 0.5), (high, 0.5)]));
+    let middle = (low + high) / 2.0;
+    assert!((even.centroid - middle).abs() < 0.01 * middle, "{even:?}");
+    let half_gap = (high - low) / 2.0;
+    assert!((even.spread - half_gap).abs() < 0.05 * half_gap, "{even:?}");
+    assert!(even.skewness.abs() < 0.1, "{even:?}");
+    // Most of the magnitude is only reached with the upper tone
+    assert!((even.rolloff - high).abs() <= 2.0 * BIN_HZ, "{even:?}");
+
+    let bass_heavy = single(tones(&[(low, 0.8), (high, 0.2)]));
+    assert!(bass_heavy.centroid < middle);
+    assert!(bass_heavy.skewness > 0.5, "{bass_heavy:?}");
+    let treble_heavy = single(tones(&[(low, 0.2), (high, 0.8)]));
+    assert!(treble_heavy.skewness < -0.5, "{treble_heavy:?}");
+}
+
+#[test]
+fn white_noise_is_flat_and_crosses_zero_often() {
+    let noise = single(noise());
+    // Hann-windowed noise: the powers are exponentially distributed, with
+    // a flatness of e^-γ ≈ 0.56
+    assert!((0.4..0.7).contains(&noise.flatness), "{noise:?}");
+    let nyquist = SAMPLE_RATE as f32 / 2.0;
+    assert!(
+        (noise.centroid - nyquist / 2.0).abs() < 0.1 * nyquist,
+        "{noise:?}"
+    );
+    assert!((noise.zero_crossing_rate - 0.5).abs() < 0.05, "{noise:?}");
+    // Uniform in -1..1
+    assert!((noise.rms - 3f32.sqrt().recip()).abs() < 0.02, "{noise:?}");
+}
+
+#[test]
+fn a_sine_crosses_zero_twice_a_period_at_its_rms() {
+    for frequency in [110.0, 1000.0, 5000.0] {
+        let sine = single(tones(&[(frequency, 0.5)]));
+        let expected = 2.0 * frequency / SAMPLE_RATE as f32;
+        // Give or take the crossing at either end of the frame
+        assert!(
+            (sine.zero_crossing_rate - expected).abs() <= 1.0 / (FFT_SIZE - 1) as f32,
+            "{frequency} Hz: {sine:?}"
+        );
+        assert!((sine.rms - 0.5 / 2f32.sqrt()).abs() < 0.005, "{sine:?}");
+    }
+}
+
+#[test]
+fn flux_rises_at_onsets_only() {
+    let silence = vec![0.0; FFT_SIZE];
+    let tone = tones(&[(1000.0, 0.5)]);
+    let frames = features(&[silence.clone(), tone.clone(), tone, silence]);
+    assert_eq!(frames[0].flux, 0.0, "no previous frame");
+    assert!(frames[1].flux > 0.1, "onset: {}", frames[1].flux);
+    assert!(frames[2].flux < 1e-4, "steady: {}", frames[2].flux);
+    assert_eq!(frames[3].flux, 0.0, "release");
+    // The spectral shape isn't defined in silence
+    assert_eq!(frames[3].centroid, 0.0);
+    assert_eq!(frames[3].band_balance(), [0.0; 3]);
+}
+
+#[test]
+fn band_energies_follow_the_tone() {
+    for (frequency, band) in [(100.0, 0), (1000.0, 1), (8000.0, 2)] {
+        let balance = single(tones(&[(frequency, 0.5)])).band_balance();
+        assert!(balance[band] > 0.99, "{frequency} Hz: {balance:?}");
+    }
+
+    // A sine of amplitude a peaks at a / 4 after the Hann window, with
+    // half that in each neighbouring bin
+    let sine = single(tones(&[(85.0 * BIN_HZ, 0.5)]));
+    let expected = 0.125f32.powi(2) * 1.5;
+    assert!((sine.mid - expected).abs() < 0.01 * expected, "{sine:?}");
+}
@@ -0,0 +1,112 @@
+use audio_visualizer::fft::FftProcessor;
+use audio_visualizer::harmony::{self, ChordQuality, HarmonyTracker, Key, Mode};
+use audio_visualizer::pitch::midi_frequency;
+
+const SAMPLE_RATE: u32 = 48_000;
+const FFT_SIZE: usize = 8192;
+
+/// Magnitude spectrum of the MIDI `notes` played together, each with a
+/// couple of quieter harmonics like a real instrument.
+fn spectrum(fft: &mut FftProcessor, notes: &[i32]) -> Vec<f32> {
+    let samples: Vec<f32> = (0..FFT_SIZE)
+        .map(|i| {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            notes
+                .iter()
+                .flat_map(|&note| {
+                    let f = midi_frequency(note as f32);
+                    [(1.0, 1.0), (2.0, 0.4), (3.0, 0.2)]
+                        .map(|(k, a)| a * (std::f32::consts::TAU * f * k * t).sin())
+                })
+                .sum::<f32>()
+                * 0.1
+        })
+        .collect();
+    fft.process(&samples);
+    fft.spectrum().to_vec()
+}
+
+#[test]
+fn chroma_peaks_at_the_pitch_class_played() {
+    let mut fft = FftProcessor::new(FFT_SIZE, 64);
+    for note in [57, 60, 66, 71] {
+        let chroma = harmony::chroma(&spectrum(&mut fft, &[note]), SAMPLE_RATE);
+        let class = note as usize % 12;
+        assert_eq!(chroma[class], 1.0, "{note}: {chroma:?}");
+        // Only the fifth from the third harmonic comes close
+        let fifth = (class + 7) % 12;
+        for (i, &c) in chroma.iter().enumerate() {
+            if i != class && i != fifth {
+                assert!(c < 0.1, "{note}: {chroma:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn chords_are_named_by_root_and_quality() {
+    let mut fft = FftProcessor::new(FFT_SIZE, 64);
+    for (notes, name, quality) in [
+        (&[48, 52, 55][..], "C", ChordQuality::Major),
+        (&[57, 60, 64], "Amin", ChordQuality::Minor),
+        (&[54, 57, 61], "F#min", ChordQuality::Minor),
+        (&[55, 59, 62, 65], "G7", ChordQuality::Seventh),
+        (&[59, 62, 65], "Bdim", ChordQuality::Diminished),
+        // Inversions have the same root
+        (&[52, 55, 60], "C", ChordQuality::Major),
+    ] {
+        let mut tracker = HarmonyTracker::new(SAMPLE_RATE);
+        let chord = tracker
+            .process(&spectrum(&mut fft, notes), 0.0)
+            .chord
+            .unwrap_or_else(|| panic!("no chord for {notes:?}"));
+        assert_eq!(chord.to_string(), name, "{notes:?}: {chord:?}");
+        assert_eq!(chord.quality, quality);
+    }
+}
+
+#[test]
+fn progressions_settle_on_their_key() {
+    let mut fft = FftProcessor::new(FFT_SIZE, 64);
+    // I-IV-V-I in G major, and i-iv-V-i in A minor
+    let g_major: [&[i32]; 4] = [&[55, 59, 62], &[60, 64, 67], &[62, 66, 69], &[55, 59, 62]];
+    let a_minor: [&[i32]; 4] = [&[57, 60, 64], &[62, 65, 69], &[64, 68, 71], &[57, 60, 64]];
+    for (progression, key, camelot) in [(g_major, "G major", "9B"), (a_minor, "A minor", "8A")] {
+        let mut tracker = HarmonyTracker::new(SAMPLE_RATE);
+        let mut last = None;
+        // Each chord for a second, twice through
+        for notes in progression.iter().cycle().take(8) {
+            let spectrum = spectrum(&mut fft, notes);
+            for _ in 0..60 {
+                last = tracker.process(&spectrum, 1.0 / 60.0).key;
+            }
+        }
+        let found = last.unwrap();
+        assert_eq!(found.to_string(), key, "{found:?}");
+        assert_eq!(found.camelot(), camelot);
+    }
+}
+
+#[test]
+fn camelot_numbers_follow_the_circle_of_fifths() {
+    let key = |tonic, mode| Key {
+        tonic,
+        mode,
+        confidence: 1.0,
+    };
+    assert_eq!(key(0, Mode::Major).camelot(), "8B");
+    assert_eq!(key(7, Mode::Major).camelot(), "9B");
+    assert_eq!(key(5, Mode::Major).camelot(), "7B");
+    assert_eq!(key(11, Mode::Major).camelot(), "1B");
+    assert_eq!(key(4, Mode::Minor).camelot(), "9A");
+    assert_eq!(key(6, Mode::Minor).camelot(), "11A");
+    assert_eq!(key(1, Mode::Major).to_string(), "C# major");
+}
+
+#[test]
+fn silence_has_no_chord_or_key() {
+    let mut tracker = HarmonyTracker::new(SAMPLE_RATE);
+    let harmony = tracker.process(&vec![0.0; FFT_SIZE / 2], 1.0 / 60.0);
+    assert_eq!(harmony.chroma, [0.0; 12]);
+    assert_eq!((harmony.key, harmony.chord), (None, None));
+}
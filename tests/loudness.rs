@@ -0,0 +1,147 @@
+use audio_visualizer::loudness::LoudnessMeter;
+use audio_visualizer::pipeline::StreamAnalysis;
+
+const SAMPLE_RATE: u32 = 48_000;
+
+/// A stereo sine of `frequency` at `dbfs` in both channels for `seconds`,
+/// starting at `phase` radians.
+fn sine(frequency: f32, dbfs: f32, seconds: f32, phase: f32) -> Vec<[f32; 2]> {
+    let amplitude = 10f64.powf(dbfs as f64 / 20.0);
+    (0..(seconds * SAMPLE_RATE as f32).round() as usize)
+        .map(|i| {
+            let t = i as f64 / SAMPLE_RATE as f64;
+            let s = amplitude * (std::f64::consts::TAU * frequency as f64 * t + phase as f64).sin();
+            [s as f32; 2]
+        })
+        .collect()
+}
+
+/// EBU test signals: 1 kHz stereo sines at each `(dBFS, seconds)` in turn.
+fn tones(parts: &[(f32, f32)]) -> Vec<[f32; 2]> {
+    parts
+        .iter()
+        .flat_map(|&(dbfs, seconds)| sine(1000.0, dbfs, seconds, 0.0))
+        .collect()
+}
+
+fn meter(frames: &[[f32; 2]]) -> LoudnessMeter {
+    let mut meter = LoudnessMeter::new(SAMPLE_RATE, 2);
+    meter.process(frames);
+    meter
+}
+
+#[test]
+fn steady_tones_read_their_level_in_every_window() {
+    // EBU Tech 3341 cases 1 and 2
+    for level in [-23.0, -33.0] {
+        let loudness = meter(&tones(&[(level, 20.0)])).loudness();
+        for (name, lufs) in [
+            ("momentary", loudness.momentary),
+            ("short-term", loudness.short_term),
+            ("integrated", loudness.integrated),
+        ] {
+            assert!((lufs - level).abs() < 0.1, "{name} {lufs} LUFS for {level}");
+        }
+    }
+}
+
+#[test]
+fn integrated_loudness_gates_out_quiet_parts() {
+    // EBU Tech 3341 cases 3, 4 and 5: all -23 LUFS
+    let cases: [&[(f32, f32)]; 3] = [
+        &[(-36.0, 10.0), (-23.0, 60.0), (-36.0, 10.0)],
+        &[
+            (-72.0, 10.0),
+            (-36.0, 10.0),
+            (-23.0, 60.0),
+            (-36.0, 10.0),
+            (-72.0, 10.0),
+        ],
+        &[(-26.0, 20.0), (-20.0, 20.1), (-26.0, 20.0)],
+    ];
+    for parts in cases {
+        let integrated = meter(&tones(parts)).loudness().integrated;
+        assert!(
+            (integrated + 23.0).abs() < 0.1,
+            "{integrated} LUFS for {parts:?}"
+        );
+    }
+}
+
+#[test]
+fn loudness_range_spans_the_level_changes() {
+    // EBU Tech 3342 cases 1 to 3
+    for (parts, range) in [
+        ([(-20.0, 20.0), (-30.0, 20.0)], 10.0),
+        ([(-20.0, 20.0), (-15.0, 20.0)], 5.0),
+        ([(-40.0, 20.0), (-20.0, 20.0)], 20.0),
+    ] {
+        let measured = meter(&tones(&parts)).loudness().range;
+        assert!(
+            (measured - range).abs() < 1.0,
+            "{measured} LU for {parts:?}"
+        );
+    }
+}
+
+#[test]
+fn true_peak_finds_the_peak_between_samples() {
+    // A quarter of the sample rate, 45° off: every sample is 3 dB below
+    // the peak of the wave
+    let loudness = meter(&sine(12_000.0, -6.0, 1.0, std::f32::consts::FRAC_PI_4)).loudness();
+    assert!(
+        (loudness.sample_peak + 9.01).abs() < 0.05,
+        "{}",
+        loudness.sample_peak
+    );
+    assert!(
+        (-6.4..=-5.8).contains(&loudness.true_peak),
+        "{} dBTP",
+        loudness.true_peak
+    );
+    // The start of the tone overshoots a little
+    assert!(loudness.max_true_peak >= loudness.true_peak);
+}
+
+#[test]
+fn sine_has_a_crest_factor_of_3_db() {
+    let loudness = meter(&tones(&[(-12.0, 1.0)])).loudness();
+    assert!((loudness.rms + 15.01).abs() < 0.05, "{} dBFS", loudness.rms);
+    let crest = loudness.crest_factor().unwrap();
+    assert!((crest - 3.01).abs() < 0.05, "{crest} dB");
+}
+
+#[test]
+fn mono_counts_once_and_silence_reads_nothing() {
+    let mut mono = LoudnessMeter::new(SAMPLE_RATE, 1);
+    mono.process(&tones(&[(-23.0, 5.0)]));
+    let integrated = mono.loudness().integrated;
+    assert!((integrated + 26.01).abs() < 0.1, "{integrated} LUFS");
+
+    mono.reset();
+    mono.process(&vec![[0.0; 2]; SAMPLE_RATE as usize]);
+    let loudness = mono.loudness();
+    assert_eq!(loudness.integrated, f32::NEG_INFINITY);
+    assert_eq!(loudness.momentary, f32::NEG_INFINITY);
+    assert_eq!(loudness.crest_factor(), None);
+}
+
+#[test]
+fn readings_dont_depend_on_how_the_samples_are_chunked() {
+    let signal = tones(&[(-20.0, 3.0), (-30.0, 2.0), (-14.0, 3.0)]);
+    let whole = meter(&signal).loudness();
+
+    // Audio callbacks come in all sizes, some smaller than a filter tap
+    let mut stream = StreamAnalysis::new(SAMPLE_RATE, 2);
+    let sizes = [1, 7, 480, 4096, 13, 1023, 8193];
+    let mut rest = &signal[..];
+    for &size in sizes.iter().cycle() {
+        if rest.is_empty() {
+            break;
+        }
+        let (chunk, tail) = rest.split_at(size.min(rest.len()));
+        stream.process(chunk);
+        rest = tail;
+    }
+    assert_eq!(stream.loudness(), whole);
+}
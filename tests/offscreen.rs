@@ -0,0 +1,84 @@
+use std::path::{Path, PathBuf};
+
+use audio_visualizer::Layout;
+
+mod common;
+
+use common::{renderer, SIZE};
+
+const NUM_BARS: usize = 8;
+/// Per-channel difference allowed between adapters, e.g. in how edges are
+/// rounded.
+const TOLERANCE: u8 = 8;
+
+/// A rising staircase of bars, from a short first bar to a full last one.
+fn staircase() -> Vec<f32> {
+    (0..NUM_BARS)
+        .map(|i| 2.0 * (i + 1) as f32 / NUM_BARS as f32)
+        .collect()
+}
+
+fn read_png(path: &Path) -> (u32, u32, Vec<u8>) {
+    let decoder = png::Decoder::new(std::fs::File::open(path).unwrap());
+    let mut reader = decoder.read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    assert_eq!(info.color_type, png::ColorType::Rgba);
+    pixels.truncate(info.buffer_size());
+    (info.width, info.height, pixels)
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(name)
+}
+
+/// Compare `pixels` with the golden image `name`, or rewrite it when
+/// `UPDATE_GOLDEN` is set.
+fn assert_matches_golden(name: &str, pixels: &[u8]) {
+    let path = golden_path(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        audio_visualizer::target::write_png(&path, SIZE.0, SIZE.1, pixels).unwrap();
+        return;
+    }
+
+    let (width, height, golden) = read_png(&path);
+    assert_eq!((width, height), SIZE);
+    let differing = pixels
+        .chunks(4)
+        .zip(golden.chunks(4))
+        .filter(|(a, b)| a.iter().zip(*b).any(|(x, y)| x.abs_diff(*y) > TOLERANCE))
+        .count();
+    // A few edge pixels may round the other way; the picture may not change
+    let allowed = (SIZE.0 * SIZE.1 / 100) as usize;
+    assert!(
+        differing <= allowed,
+        "{differing} pixels differ from {} (set UPDATE_GOLDEN=1 to accept)",
+        path.display()
+    );
+}
+
+#[test]
+fn bars_match_the_golden_image() {
+    let Some(mut renderer) = renderer(NUM_BARS as u32, Layout::Linear) else {
+        return;
+    };
+    renderer.render(&staircase());
+    assert_matches_golden("linear_bars.png", &renderer.read_pixels());
+}
+
+#[test]
+fn saved_png_holds_the_rendered_pixels() {
+    let Some(mut renderer) = renderer(NUM_BARS as u32, Layout::Linear) else {
+        return;
+    };
+    renderer.render(&staircase());
+    let path = std::env::temp_dir().join(format!("offscreen-{}.png", std::process::id()));
+    renderer.save_png(&path).unwrap();
+    let (width, height, saved) = read_png(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!((width, height), SIZE);
+    assert_eq!(saved, renderer.read_pixels());
+}
@@ -0,0 +1,114 @@
+use audio_visualizer::pitch::{Note, PitchDetector, MIN_CONFIDENCE};
+
+const SAMPLE_RATE: u32 = 48_000;
+
+/// `len` samples of a tone at `frequency` with harmonics of the given
+/// amplitudes, the first being the fundamental.
+fn tone(frequency: f32, harmonics: &[f32], len: usize) -> Vec<f32> {
+    (0..len)
+        .map(|i| {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            harmonics
+                .iter()
+                .enumerate()
+                .map(|(k, a)| a * (std::f32::consts::TAU * frequency * (k + 1) as f32 * t).sin())
+                .sum::<f32>()
+                * 0.3
+        })
+        .collect()
+}
+
+fn cents_between(a: f32, b: f32) -> f32 {
+    1200.0 * (a / b).log2()
+}
+
+#[test]
+fn sine_tones_are_found_within_a_few_cents() {
+    let mut detector = PitchDetector::new(SAMPLE_RATE);
+    let len = detector.window_size();
+    for frequency in [41.2, 82.41, 110.0, 261.63, 440.0, 1000.0, 3520.0] {
+        let pitch = detector
+            .process(&tone(frequency, &[1.0], len))
+            .unwrap_or_else(|| panic!("no pitch for {frequency} Hz"));
+        assert!(
+            cents_between(pitch.frequency, frequency).abs() < 3.0,
+            "{frequency} Hz detected as {}",
+            pitch.frequency
+        );
+        assert!(pitch.confidence > 0.95, "{frequency} Hz: {pitch:?}");
+    }
+}
+
+#[test]
+fn harmonics_do_not_change_the_octave() {
+    let mut detector = PitchDetector::new(SAMPLE_RATE);
+    let len = detector.window_size();
+
+    // Sawtooth-like, and one whose second harmonic is louder than the
+    // fundamental
+    for harmonics in [
+        &[1.0, 0.5, 0.33, 0.25, 0.2, 0.17][..],
+        &[0.4, 1.0, 0.6, 0.3],
+    ] {
+        let pitch = detector.process(&tone(196.0, harmonics, len)).unwrap();
+        assert!(
+            cents_between(pitch.frequency, 196.0).abs() < 5.0,
+            "{harmonics:?}: {}",
+            pitch.frequency
+        );
+    }
+}
+
+#[test]
+fn notes_have_names_octaves_and_cents() {
+    let note = Note::from_frequency(440.0);
+    assert_eq!((note.to_string(), note.midi), ("A4".to_string(), 69));
+    assert!(note.cents.abs() < 0.01);
+
+    assert_eq!(Note::from_frequency(261.63).to_string(), "C4");
+    assert_eq!(Note::from_frequency(27.5).to_string(), "A0");
+    assert_eq!(Note::from_frequency(4186.0).to_string(), "C8");
+    assert_eq!(Note::from_frequency(277.18).to_string(), "C#4");
+
+    // Off-key frequencies round to the nearest note
+    let sharp = Note::from_frequency(445.0);
+    assert_eq!(sharp.to_string(), "A4");
+    assert!((sharp.cents - 19.56).abs() < 0.1, "{sharp:?}");
+    let flat = Note::from_frequency(430.0);
+    assert!((flat.cents + 39.8).abs() < 0.1, "{flat:?}");
+    assert!((flat.frequency() - 440.0).abs() < 0.01);
+}
+
+#[test]
+fn detected_tone_gives_its_note() {
+    let mut detector = PitchDetector::new(SAMPLE_RATE);
+    let len = detector.window_size();
+    let note = detector
+        .process(&tone(329.63, &[1.0, 0.3], len))
+        .unwrap()
+        .note();
+    assert_eq!(note.to_string(), "E4");
+    assert!(note.cents.abs() < 3.0, "{note:?}");
+}
+
+#[test]
+fn silence_and_noise_have_no_confident_pitch() {
+    let mut detector = PitchDetector::new(SAMPLE_RATE);
+    let len = detector.window_size();
+    assert_eq!(detector.process(&vec![0.0; len]), None);
+    assert_eq!(detector.process(&[]), None);
+
+    // xorshift white noise
+    let mut state = 0x2545_f491_u32;
+    let noise: Vec<f32> = (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as f32 / u32::MAX as f32 - 0.5
+        })
+        .collect();
+    if let Some(pitch) = detector.process(&noise) {
+        assert!(pitch.confidence < MIN_CONFIDENCE, "{pitch:?}");
+    }
+}
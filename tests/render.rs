@@ -0,0 +1,187 @@
+use audio_visualizer::harmony::Harmony;
+use audio_visualizer::{BlendMode, Layer, Layout, LayoutParams, Theme};
+
+mod common;
+
+use common::{renderer, SIZE};
+
+fn pixel(pixels: &[u8], x: u32, y: u32) -> [u8; 4] {
+    let i = ((y * SIZE.0 + x) * 4) as usize;
+    pixels[i..i + 4].try_into().unwrap()
+}
+
+#[test]
+fn silence_draws_only_the_background() {
+    let Some(mut renderer) = renderer(8, Layout::Linear) else {
+        return;
+    };
+    renderer.render(&[0.0; 8]);
+    let pixels = renderer.read_pixels();
+    assert_eq!(pixels.len(), (SIZE.0 * SIZE.1 * 4) as usize);
+
+    // The background is a vertical gradient, so every row is one color
+    let row = SIZE.1 / 2;
+    let first = pixel(&pixels, 0, row);
+    assert!((0..SIZE.0).all(|x| pixel(&pixels, x, row) == first));
+}
+
+#[test]
+fn full_bars_cover_the_bottom_of_the_screen() {
+    let Some(mut renderer) = renderer(8, Layout::Linear) else {
+        return;
+    };
+    renderer.render(&[0.0; 8]);
+    let silent = renderer.read_pixels();
+    renderer.render(&[2.0; 8]);
+    let loud = renderer.read_pixels();
+
+    // Middle of the first bar, near the bottom
+    let (x, y) = (SIZE.0 / 16, SIZE.1 - 4);
+    assert_ne!(pixel(&silent, x, y), pixel(&loud, x, y));
+}
+
+#[test]
+fn every_layout_and_theme_renders() {
+    let Some(mut renderer) = renderer(16, Layout::Linear) else {
+        return;
+    };
+    for theme in Theme::builtin() {
+        renderer.set_theme(&theme);
+        for layout in Layout::ALL {
+            renderer.set_layout(layout, LayoutParams::default());
+            renderer.render(&[1.0; 16]);
+        }
+    }
+    renderer.set_num_bars(4);
+    renderer.render(&[1.0; 4]);
+    assert_eq!(renderer.size(), SIZE);
+}
+
+#[test]
+fn every_view_renders() {
+    let Some(mut renderer) = renderer(8, Layout::Linear) else {
+        return;
+    };
+    let views: Vec<String> = renderer.views().map(str::to_string).collect();
+    assert_eq!(
+        views,
+        [
+            "bars",
+            "spectrogram",
+            "chromagram",
+            "vectorscope",
+            "shadertoy"
+        ]
+    );
+    for view in &views {
+        assert!(renderer.set_view(view));
+        renderer.update_stereo(&[[0.5, -0.5]; 64]);
+        renderer.render(&[1.0; 8]);
+        assert_eq!(renderer.view(), view);
+    }
+    assert!(!renderer.set_view("nope"));
+
+    // Cycling wraps around to the first view
+    renderer.cycle_view();
+    assert_eq!(renderer.view(), "bars");
+}
+
+#[test]
+fn spectrogram_shows_loud_bars() {
+    let Some(mut renderer) = renderer(8, Layout::Linear) else {
+        return;
+    };
+    renderer.set_view("spectrogram");
+    renderer.render(&[0.0; 8]);
+    let silent = renderer.read_pixels();
+    // One column a frame without a clock, and a pixel spans a few columns
+    for _ in 0..4 {
+        renderer.render(&[2.0; 8]);
+    }
+    let loud = renderer.read_pixels();
+
+    // The newest columns are at the right edge
+    let (x, y) = (SIZE.0 - 1, SIZE.1 / 2);
+    assert_ne!(pixel(&silent, x, y), pixel(&loud, x, y));
+}
+
+#[test]
+fn chromagram_lights_the_band_of_a_sounding_pitch_class() {
+    let Some(mut renderer) = renderer(8, Layout::Linear) else {
+        return;
+    };
+    renderer.set_view("chromagram");
+    renderer.render(&[0.0; 8]);
+    let silent = renderer.read_pixels();
+
+    // Only C sounds
+    let mut chroma = [0.0; 12];
+    chroma[0] = 1.0;
+    renderer.set_harmony(Some(Harmony {
+        chroma,
+        key: None,
+        chord: None,
+    }));
+    for _ in 0..4 {
+        renderer.render(&[0.0; 8]);
+    }
+    let loud = renderer.read_pixels();
+
+    // C is the bottom band, D# a few up
+    let x = SIZE.0 - 1;
+    let band = |i: u32| SIZE.1 - 1 - (i * SIZE.1 / 12 + SIZE.1 / 24);
+    assert_ne!(pixel(&silent, x, band(0)), pixel(&loud, x, band(0)));
+    assert_eq!(pixel(&silent, x, band(3)), pixel(&loud, x, band(3)));
+}
+
+#[test]
+fn layers_draw_only_inside_their_viewports() {
+    let Some(mut renderer) = renderer(8, Layout::Linear) else {
+        return;
+    };
+    renderer.render(&[0.0; 8]);
+    let silent = renderer.read_pixels();
+
+    // Bars in the right half, with the spectrogram under everything
+    let layers = vec![
+        Layer {
+            view: "spectrogram".to_string(),
+            opacity: 0.5,
+            ..Layer::default()
+        },
+        Layer {
+            view: "bars".to_string(),
+            rect: [0.5, 0.0, 0.5, 1.0],
+            blend: BlendMode::Add,
+            ..Layer::default()
+        },
+    ];
+    renderer.set_layers(layers.clone()).unwrap();
+    assert_eq!(renderer.layers(), layers);
+    renderer.render(&[2.0; 8]);
+    let loud = renderer.read_pixels();
+
+    // The spectrogram has one column so far, at the right edge, so away
+    // from it only the bars' half changed
+    let y = 2;
+    assert_eq!(pixel(&silent, SIZE.0 / 4, y), pixel(&loud, SIZE.0 / 4, y));
+    assert_ne!(
+        pixel(&silent, SIZE.0 * 3 / 4, y),
+        pixel(&loud, SIZE.0 * 3 / 4, y)
+    );
+
+    // Unknown and repeated views are refused, keeping the layers
+    let unknown = Layer {
+        view: "nope".to_string(),
+        ..Layer::default()
+    };
+    assert!(renderer.set_layers(vec![unknown]).is_err());
+    assert!(renderer
+        .set_layers([layers.clone(), layers].concat())
+        .is_err());
+    assert_eq!(renderer.layers().len(), 2);
+
+    // Picking a single view leaves the layers
+    renderer.set_view("bars");
+    assert!(renderer.layers().is_empty());
+}